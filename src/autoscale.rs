@@ -0,0 +1,253 @@
+//! Autoscaling policy for elastic engine pools.
+//!
+//! An elastic pool keeps between `min_size` and `max_size` engines. The
+//! autoscaler samples the pool periodically and grows it when callers queue
+//! up or wait too long for an engine, and shrinks it after engines have sat
+//! idle for a sustained period.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Autoscaling policy for an elastic pool.
+#[derive(Debug, Clone)]
+pub struct AutoscaleConfig {
+    /// How often the autoscaler samples the pool.
+    pub interval: Duration,
+    /// Grow when the average acquire wait over an interval reaches this.
+    pub scale_up_wait: Duration,
+    /// Grow when at least this many callers are waiting for an engine.
+    pub scale_up_queue_depth: usize,
+    /// Shrink after idle engines have been available for this long.
+    pub scale_down_idle: Duration,
+    /// Number of engines added or removed per decision.
+    pub step: usize,
+}
+
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            scale_up_wait: Duration::from_millis(10),
+            scale_up_queue_depth: 1,
+            scale_down_idle: Duration::from_secs(30),
+            step: 1,
+        }
+    }
+}
+
+impl AutoscaleConfig {
+    /// Create the default autoscaling policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the sampling interval.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the average wait time that triggers growth.
+    pub fn with_scale_up_wait(mut self, wait: Duration) -> Self {
+        self.scale_up_wait = wait;
+        self
+    }
+
+    /// Set the queue depth that triggers growth.
+    pub fn with_scale_up_queue_depth(mut self, depth: usize) -> Self {
+        self.scale_up_queue_depth = depth.max(1);
+        self
+    }
+
+    /// Set how long engines must be idle before the pool shrinks.
+    pub fn with_scale_down_idle(mut self, idle: Duration) -> Self {
+        self.scale_down_idle = idle;
+        self
+    }
+
+    /// Set the number of engines added or removed per decision.
+    pub fn with_step(mut self, step: usize) -> Self {
+        self.step = step.max(1);
+        self
+    }
+}
+
+/// Why the autoscaler resized the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleReason {
+    /// Callers were queued waiting for an engine.
+    QueueDepth(usize),
+    /// The average acquire wait was too long.
+    WaitTime(Duration),
+    /// Engines sat idle for the given duration.
+    Idle(Duration),
+}
+
+impl ScaleReason {
+    /// Check if this reason grows the pool.
+    pub fn is_scale_up(&self) -> bool {
+        !matches!(self, ScaleReason::Idle(_))
+    }
+}
+
+impl fmt::Display for ScaleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaleReason::QueueDepth(depth) => write!(f, "{} callers waiting", depth),
+            ScaleReason::WaitTime(wait) => write!(f, "average wait {:?}", wait),
+            ScaleReason::Idle(idle) => write!(f, "idle for {:?}", idle),
+        }
+    }
+}
+
+/// A resize performed by the autoscaler.
+#[derive(Debug, Clone)]
+pub struct ScaleEvent {
+    /// Engine count before the resize.
+    pub from: usize,
+    /// Engine count after the resize.
+    pub to: usize,
+    /// Why the pool was resized.
+    pub reason: ScaleReason,
+    /// When the resize happened.
+    pub at: Instant,
+}
+
+/// Pool state sampled by the autoscaler.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PoolSample {
+    pub total: usize,
+    pub available: usize,
+    pub waiting: usize,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// Average acquire wait since the previous sample, if any acquires ran.
+    pub avg_wait: Option<Duration>,
+}
+
+/// Autoscaler decision state.
+#[derive(Debug)]
+pub(crate) struct Autoscaler {
+    policy: AutoscaleConfig,
+    idle_since: Option<Instant>,
+}
+
+impl Autoscaler {
+    pub(crate) fn new(policy: AutoscaleConfig) -> Self {
+        Self {
+            policy,
+            idle_since: None,
+        }
+    }
+
+    /// Decide the target engine count for a sample, if it should change.
+    pub(crate) fn decide(
+        &mut self,
+        sample: PoolSample,
+        now: Instant,
+    ) -> Option<(usize, ScaleReason)> {
+        let step = self.policy.step.max(1);
+
+        if sample.total < sample.max_size {
+            let reason = if sample.waiting >= self.policy.scale_up_queue_depth.max(1) {
+                Some(ScaleReason::QueueDepth(sample.waiting))
+            } else {
+                sample
+                    .avg_wait
+                    .filter(|wait| *wait >= self.policy.scale_up_wait)
+                    .map(ScaleReason::WaitTime)
+            };
+            if let Some(reason) = reason {
+                self.idle_since = None;
+                return Some(((sample.total + step).min(sample.max_size), reason));
+            }
+        }
+
+        if sample.waiting > 0 || sample.available == 0 || sample.total <= sample.min_size {
+            self.idle_since = None;
+            return None;
+        }
+
+        let idle_since = *self.idle_since.get_or_insert(now);
+        let idle = now.duration_since(idle_since);
+        if idle < self.policy.scale_down_idle {
+            return None;
+        }
+
+        // Restart the idle clock so the pool shrinks one step per period.
+        self.idle_since = Some(now);
+        let remove = step
+            .min(sample.available)
+            .min(sample.total - sample.min_size);
+        Some((sample.total - remove, ScaleReason::Idle(idle)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(total: usize, available: usize, waiting: usize) -> PoolSample {
+        PoolSample {
+            total,
+            available,
+            waiting,
+            min_size: 1,
+            max_size: 4,
+            avg_wait: None,
+        }
+    }
+
+    #[test]
+    fn test_scale_up_on_queue_depth() {
+        let mut scaler = Autoscaler::new(AutoscaleConfig::new().with_step(2));
+        let now = Instant::now();
+
+        assert_eq!(
+            scaler.decide(sample(1, 0, 3), now),
+            Some((3, ScaleReason::QueueDepth(3)))
+        );
+        // Never beyond max_size.
+        assert_eq!(
+            scaler.decide(sample(3, 0, 3), now),
+            Some((4, ScaleReason::QueueDepth(3)))
+        );
+        assert_eq!(scaler.decide(sample(4, 0, 3), now), None);
+    }
+
+    #[test]
+    fn test_scale_up_on_wait_time() {
+        let mut scaler = Autoscaler::new(AutoscaleConfig::new());
+        let wait = Duration::from_millis(50);
+        let s = PoolSample {
+            avg_wait: Some(wait),
+            ..sample(2, 0, 0)
+        };
+
+        assert_eq!(
+            scaler.decide(s, Instant::now()),
+            Some((3, ScaleReason::WaitTime(wait)))
+        );
+    }
+
+    #[test]
+    fn test_scale_down_after_sustained_idle() {
+        let idle = Duration::from_secs(10);
+        let mut scaler = Autoscaler::new(AutoscaleConfig::new().with_scale_down_idle(idle));
+        let start = Instant::now();
+
+        assert_eq!(scaler.decide(sample(3, 3, 0), start), None);
+        assert_eq!(scaler.decide(sample(3, 3, 0), start + idle / 2), None);
+
+        let (target, reason) = scaler.decide(sample(3, 3, 0), start + idle).unwrap();
+        assert_eq!(target, 2);
+        assert!(!reason.is_scale_up());
+
+        // Activity resets the idle clock.
+        assert_eq!(scaler.decide(sample(2, 0, 0), start + idle * 2), None);
+        assert_eq!(scaler.decide(sample(2, 2, 0), start + idle * 2), None);
+
+        // Never below min_size.
+        assert_eq!(scaler.decide(sample(1, 1, 0), start + idle * 10), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Parallel execution of job batches across an engine pool.
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::error::{Error, Result};
+use crate::job::Job;
+use crate::pool::EnginePool;
+use crate::value::Value;
+
+/// Progress callback for a batch.
+pub type ProgressFn = Arc<dyn Fn(&BatchProgress) + Send + Sync>;
+
+/// Settings for [`EnginePool::execute_many_with`].
+#[derive(Clone, Default)]
+pub struct BatchOptions {
+    /// Most jobs running at once (`None` uses the pool's maximum size).
+    pub parallelism: Option<usize>,
+    /// Whether to stop starting jobs after the first failure.
+    pub fail_fast: bool,
+    /// Called after each job finishes.
+    pub on_progress: Option<ProgressFn>,
+}
+
+impl BatchOptions {
+    /// Create batch options with the defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bound the number of jobs running at once.
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = Some(parallelism.max(1));
+        self
+    }
+
+    /// Stop starting jobs after the first failure.
+    ///
+    /// Jobs that never started report [`Error::Cancelled`].
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Report progress after each job finishes.
+    ///
+    /// The callback runs on the worker thread that finished the job.
+    pub fn on_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&BatchProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+}
+
+impl std::fmt::Debug for BatchOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchOptions")
+            .field("parallelism", &self.parallelism)
+            .field("fail_fast", &self.fail_fast)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+/// Progress of a running batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// Jobs finished, successfully or not.
+    pub completed: usize,
+    /// Jobs that failed.
+    pub failed: usize,
+    /// Jobs in the batch.
+    pub total: usize,
+}
+
+/// Run `jobs` on `pool`, returning results in input order.
+pub(crate) fn execute_many(
+    pool: &EnginePool,
+    jobs: Vec<Job>,
+    options: &BatchOptions,
+) -> Vec<Result<Value>> {
+    let total = jobs.len();
+    let workers = options
+        .parallelism
+        .unwrap_or(pool.config().max_size)
+        .clamp(1, total.max(1));
+
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+
+    let worker = || {
+        let mut results = Vec::new();
+        while !stop.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(job) = jobs.get(index) else { break };
+
+            let result = pool.execute_job(job);
+            let failed = if result.is_err() {
+                stop.fetch_or(options.fail_fast, Ordering::Relaxed);
+                failed.fetch_add(1, Ordering::Relaxed) + 1
+            } else {
+                failed.load(Ordering::Relaxed)
+            };
+            let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(on_progress) = &options.on_progress {
+                on_progress(&BatchProgress {
+                    completed,
+                    failed,
+                    total,
+                });
+            }
+            results.push((index, result));
+        }
+        results
+    };
+
+    let mut slots: Vec<Option<Result<Value>>> = (0..total).map(|_| None).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(worker)).collect();
+        for handle in handles {
+            let results = handle
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload));
+            for (index, result) in results {
+                slots[index] = Some(result);
+            }
+        }
+    });
+
+    slots
+        .into_iter()
+        .map(|slot| slot.unwrap_or(Err(Error::Cancelled)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_context::{HostContext, LogLevel};
+    use crate::limits::Limits;
+    use crate::options::ExecOptions;
+    use crate::pool::PoolConfig;
+    use parking_lot::Mutex;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_in_input_order() {
+        let pool = EnginePool::new(PoolConfig::new(4)).unwrap();
+        let script: Arc<str> = Arc::from("x + 1");
+        let jobs = (0..50i64).map(|i| Job::source(script.clone()).with_global("x", i));
+
+        let results = pool.execute_many(jobs);
+        assert_eq!(results.len(), 50);
+        for (i, result) in results.into_iter().enumerate() {
+            assert_eq!(result.unwrap(), Value::Int(i as i64 + 1));
+        }
+    }
+
+    #[test]
+    fn test_bounded_parallelism() {
+        /// Tracks how many executions poll for cancellation at once.
+        #[derive(Default)]
+        struct Concurrency {
+            active: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        impl HostContext for Concurrency {
+            fn log(&self, _level: LogLevel, _message: &str) {}
+
+            fn record_metric(&self, _name: &str, _value: f64, _tags: &[(&str, &str)]) {}
+
+            fn should_cancel(&self) -> bool {
+                let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(active, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(2));
+                self.active.fetch_sub(1, Ordering::SeqCst);
+                false
+            }
+        }
+
+        let host = Arc::new(Concurrency::default());
+        let pool = EnginePool::new(PoolConfig::new(4).with_host_context(host.clone())).unwrap();
+        let results = pool.execute_many_with(
+            (0..20).map(|_| Job::source("1")),
+            &BatchOptions::new().with_parallelism(2),
+        );
+
+        assert!(results.iter().all(Result::is_ok));
+        assert!(host.peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_fail_fast_and_progress() {
+        let pool = EnginePool::new(PoolConfig::new(2)).unwrap();
+        let jobs = || {
+            (0..10).map(|i| match i {
+                0 => Job::source("1").with_options(
+                    ExecOptions::new().with_limits(Limits::default().with_max_instructions(1)),
+                ),
+                _ => Job::source("1"),
+            })
+        };
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let seen = progress.clone();
+        let options = BatchOptions::new()
+            .with_parallelism(1)
+            .on_progress(move |p| seen.lock().push(*p));
+        let results = pool.execute_many_with(jobs(), &options);
+        assert!(results[0].is_err());
+        assert!(results[1..].iter().all(Result::is_ok));
+        let last = *progress.lock().last().unwrap();
+        assert_eq!(
+            last,
+            BatchProgress {
+                completed: 10,
+                failed: 1,
+                total: 10
+            }
+        );
+
+        let results = pool.execute_many_with(
+            jobs(),
+            &BatchOptions::new().with_parallelism(1).with_fail_fast(true),
+        );
+        assert!(matches!(results[0], Err(Error::LimitViolation(_))));
+        assert!(results[1..]
+            .iter()
+            .all(|r| matches!(r, Err(Error::Cancelled))));
+    }
+}
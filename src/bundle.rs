@@ -0,0 +1,808 @@
+//! Single-file script bundles for multi-module plugins.
+//!
+//! A bundle packs a manifest, compiled modules, and optional raw assets into
+//! one file. Every entry carries an integrity hash that is checked when the
+//! bundle is decoded, so a truncated or corrupted bundle is rejected before
+//! any of its bytecode reaches the VM.
+//!
+//! # Layout
+//!
+//! ```text
+//! magic "FZBN" | format version (u8)
+//! manifest: name | version | entrypoint | capabilities[] | exports[]
+//! entry count (u32)
+//! entry: kind (u8) | name | imports[] | hash (u64) | payload
+//! ```
+//!
+//! Strings and payloads are length-prefixed with a little-endian `u32`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::capabilities::{Capabilities, Capability};
+use crate::compile::{compile_source, CompileOptions};
+use crate::error::{Error, Result};
+
+/// Magic bytes at the start of every bundle file.
+pub const BUNDLE_MAGIC: &[u8; 4] = b"FZBN";
+
+/// Current bundle format version.
+pub const BUNDLE_FORMAT_VERSION: u8 = 1;
+
+/// Name of the optional manifest file read by [`compile_project`].
+const MANIFEST_FILE: &str = "bundle.manifest";
+
+/// Directory (relative to the project root) whose files are packed as assets.
+const ASSETS_DIR: &str = "assets";
+
+const KIND_MODULE: u8 = 0;
+const KIND_ASSET: u8 = 1;
+
+/// Bundle manifest describing the plugin.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleManifest {
+    /// Plugin name.
+    pub name: String,
+    /// Plugin version.
+    pub version: String,
+    /// Name of the module executed when the bundle runs.
+    pub entrypoint: String,
+    /// Capabilities required by any module in the bundle.
+    pub required_capabilities: Vec<String>,
+    /// Functions exported by the entrypoint module.
+    pub exports: Vec<String>,
+}
+
+impl BundleManifest {
+    /// Create a new manifest.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            entrypoint: "main".to_string(),
+            required_capabilities: Vec::new(),
+            exports: Vec::new(),
+        }
+    }
+
+    /// Check if a capability is required.
+    pub fn requires_capability(&self, cap: &str) -> bool {
+        self.required_capabilities.iter().any(|c| c == cap)
+    }
+}
+
+/// A compiled module inside a bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleModule {
+    name: String,
+    bytecode: Vec<u8>,
+    imports: Vec<String>,
+    hash: u64,
+}
+
+impl BundleModule {
+    /// Get the module name (e.g. `lib.util`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the compiled bytecode.
+    pub fn bytecode(&self) -> &[u8] {
+        &self.bytecode
+    }
+
+    /// Get the bundle-local modules this module imports.
+    pub fn imports(&self) -> &[String] {
+        &self.imports
+    }
+
+    /// Get the integrity hash of the bytecode.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// A raw asset inside a bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleAsset {
+    path: String,
+    data: Vec<u8>,
+    hash: u64,
+}
+
+impl BundleAsset {
+    /// Get the asset path relative to the assets directory.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the asset contents.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the integrity hash of the contents.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// A multi-module script bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    manifest: BundleManifest,
+    modules: BTreeMap<String, BundleModule>,
+    assets: BTreeMap<String, BundleAsset>,
+}
+
+impl Bundle {
+    /// Get the bundle manifest.
+    pub fn manifest(&self) -> &BundleManifest {
+        &self.manifest
+    }
+
+    /// Look up a module by name.
+    pub fn module(&self, name: &str) -> Option<&BundleModule> {
+        self.modules.get(name)
+    }
+
+    /// Iterate over all modules in name order.
+    pub fn modules(&self) -> impl Iterator<Item = &BundleModule> {
+        self.modules.values()
+    }
+
+    /// Look up an asset by path.
+    pub fn asset(&self, path: &str) -> Option<&[u8]> {
+        self.assets.get(path).map(|a| a.data.as_slice())
+    }
+
+    /// Iterate over all assets in path order.
+    pub fn assets(&self) -> impl Iterator<Item = &BundleAsset> {
+        self.assets.values()
+    }
+
+    /// Get the entrypoint module.
+    pub fn entrypoint(&self) -> Result<&BundleModule> {
+        self.modules.get(&self.manifest.entrypoint).ok_or_else(|| {
+            Error::invalid_bundle(format!(
+                "entrypoint module not found: {}",
+                self.manifest.entrypoint
+            ))
+        })
+    }
+
+    /// Resolve the modules reachable from the entrypoint in execution order.
+    ///
+    /// Dependencies come before the modules that import them and the
+    /// entrypoint is always last. Import cycles are rejected.
+    pub fn load_order(&self) -> Result<Vec<&BundleModule>> {
+        let entry = self.entrypoint()?;
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut visiting = Vec::new();
+        self.visit(entry, &mut visited, &mut visiting, &mut order)?;
+        Ok(order)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        module: &'a BundleModule,
+        visited: &mut HashSet<&'a str>,
+        visiting: &mut Vec<&'a str>,
+        order: &mut Vec<&'a BundleModule>,
+    ) -> Result<()> {
+        if visited.contains(module.name.as_str()) {
+            return Ok(());
+        }
+        if visiting.contains(&module.name.as_str()) {
+            visiting.push(&module.name);
+            return Err(Error::invalid_bundle(format!(
+                "import cycle: {}",
+                visiting.join(" -> ")
+            )));
+        }
+
+        visiting.push(&module.name);
+        for import in &module.imports {
+            let dep = self.modules.get(import).ok_or_else(|| {
+                Error::invalid_bundle(format!(
+                    "module {} imports missing module {}",
+                    module.name, import
+                ))
+            })?;
+            self.visit(dep, visited, visiting, order)?;
+        }
+        visiting.pop();
+
+        visited.insert(&module.name);
+        order.push(module);
+        Ok(())
+    }
+
+    /// Check that the given capabilities cover everything the manifest requires.
+    pub fn check_capabilities(&self, capabilities: &Capabilities) -> Result<()> {
+        for name in &self.manifest.required_capabilities {
+            match Capability::from_name(name) {
+                Some(cap) => capabilities.require(cap)?,
+                None => return Err(Error::capability_denied(name.clone())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-check every entry against its integrity hash.
+    pub fn verify(&self) -> Result<()> {
+        for module in self.modules.values() {
+            check_hash(&module.name, &module.bytecode, module.hash)?;
+        }
+        for asset in self.assets.values() {
+            check_hash(&asset.path, &asset.data, asset.hash)?;
+        }
+        Ok(())
+    }
+
+    /// Encode the bundle into its single-file representation.
+    ///
+    /// Fails if a section is too large for the format's 32-bit lengths.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        out.extend_from_slice(BUNDLE_MAGIC);
+        out.push(BUNDLE_FORMAT_VERSION);
+
+        write_str(&mut out, &self.manifest.name)?;
+        write_str(&mut out, &self.manifest.version)?;
+        write_str(&mut out, &self.manifest.entrypoint)?;
+        write_strs(&mut out, &self.manifest.required_capabilities)?;
+        write_strs(&mut out, &self.manifest.exports)?;
+
+        write_len(&mut out, self.modules.len() + self.assets.len())?;
+        for module in self.modules.values() {
+            out.push(KIND_MODULE);
+            write_str(&mut out, &module.name)?;
+            write_strs(&mut out, &module.imports)?;
+            out.extend_from_slice(&module.hash.to_le_bytes());
+            write_bytes(&mut out, &module.bytecode)?;
+        }
+        for asset in self.assets.values() {
+            out.push(KIND_ASSET);
+            write_str(&mut out, &asset.path)?;
+            write_strs(&mut out, &[])?;
+            out.extend_from_slice(&asset.hash.to_le_bytes());
+            write_bytes(&mut out, &asset.data)?;
+        }
+
+        Ok(out)
+    }
+
+    /// Decode a bundle, verifying every entry's integrity hash.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+
+        if reader.take(4)? != BUNDLE_MAGIC {
+            return Err(Error::invalid_bundle("invalid magic number"));
+        }
+        let version = reader.u8()?;
+        if version != BUNDLE_FORMAT_VERSION {
+            return Err(Error::invalid_bundle(format!(
+                "unsupported bundle format version: {}",
+                version
+            )));
+        }
+
+        let manifest = BundleManifest {
+            name: reader.string()?,
+            version: reader.string()?,
+            entrypoint: reader.string()?,
+            required_capabilities: reader.strings()?,
+            exports: reader.strings()?,
+        };
+
+        let mut modules = BTreeMap::new();
+        let mut assets = BTreeMap::new();
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let kind = reader.u8()?;
+            let name = reader.string()?;
+            let imports = reader.strings()?;
+            let hash = reader.u64()?;
+            let data = reader.bytes()?.to_vec();
+            check_hash(&name, &data, hash)?;
+
+            match kind {
+                KIND_MODULE => {
+                    modules.insert(
+                        name.clone(),
+                        BundleModule {
+                            name,
+                            bytecode: data,
+                            imports,
+                            hash,
+                        },
+                    );
+                }
+                KIND_ASSET => {
+                    assets.insert(
+                        name.clone(),
+                        BundleAsset {
+                            path: name,
+                            data,
+                            hash,
+                        },
+                    );
+                }
+                other => {
+                    return Err(Error::invalid_bundle(format!(
+                        "unknown entry kind: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        if !reader.is_empty() {
+            return Err(Error::invalid_bundle("trailing data after last entry"));
+        }
+
+        let bundle = Self {
+            manifest,
+            modules,
+            assets,
+        };
+        bundle.entrypoint()?;
+        Ok(bundle)
+    }
+
+    /// Write the bundle to a file.
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Read and verify a bundle from a file.
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Builder for assembling a [`Bundle`] from sources and assets.
+#[derive(Debug, Clone)]
+pub struct BundleBuilder {
+    manifest: BundleManifest,
+    sources: BTreeMap<String, String>,
+    assets: BTreeMap<String, Vec<u8>>,
+    options: CompileOptions,
+}
+
+impl BundleBuilder {
+    /// Create a new builder for a bundle with the given name and version.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            manifest: BundleManifest::new(name, version),
+            sources: BTreeMap::new(),
+            assets: BTreeMap::new(),
+            options: CompileOptions::default(),
+        }
+    }
+
+    /// Set the entrypoint module name.
+    pub fn with_entrypoint(mut self, module: impl Into<String>) -> Self {
+        self.manifest.entrypoint = module.into();
+        self
+    }
+
+    /// Add a module from source.
+    pub fn with_module(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.sources.insert(name.into(), source.into());
+        self
+    }
+
+    /// Add a raw asset.
+    pub fn with_asset(mut self, path: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        self.assets.insert(path.into(), data.into());
+        self
+    }
+
+    /// Set the compile options used for every module.
+    pub fn with_options(mut self, options: CompileOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Compile all modules and assemble the bundle.
+    ///
+    /// Imports naming another module in the bundle are resolved to it; any
+    /// other imports are left for the host to provide.
+    pub fn build(self) -> Result<Bundle> {
+        let mut manifest = self.manifest;
+        let mut modules = BTreeMap::new();
+        let mut capabilities = Vec::new();
+
+        for (name, source) in &self.sources {
+            let options = self.options.clone().with_source_name(name.clone());
+            let compiled = compile_source(source, &options)?;
+
+            for cap in &compiled.metadata.required_capabilities {
+                if !capabilities.contains(cap) {
+                    capabilities.push(cap.clone());
+                }
+            }
+            if *name == manifest.entrypoint {
+                manifest.exports = compiled
+                    .metadata
+                    .exports
+                    .iter()
+                    .map(|e| e.name.clone())
+                    .collect();
+            }
+
+            let imports = compiled
+                .metadata
+                .imports
+                .iter()
+                .map(|i| i.module.clone())
+                .filter(|m| self.sources.contains_key(m))
+                .collect();
+
+            modules.insert(
+                name.clone(),
+                BundleModule {
+                    name: name.clone(),
+                    hash: integrity_hash(&compiled.bytecode),
+                    bytecode: compiled.bytecode,
+                    imports,
+                },
+            );
+        }
+
+        capabilities.sort();
+        manifest.required_capabilities = capabilities;
+
+        let assets = self
+            .assets
+            .into_iter()
+            .map(|(path, data)| {
+                let asset = BundleAsset {
+                    hash: integrity_hash(&data),
+                    path: path.clone(),
+                    data,
+                };
+                (path, asset)
+            })
+            .collect();
+
+        let bundle = Bundle {
+            manifest,
+            modules,
+            assets,
+        };
+        bundle.load_order()?;
+        Ok(bundle)
+    }
+}
+
+/// Compile a project directory into a bundle.
+///
+/// Every `.fsx`/`.fusabi` file becomes a module named after its path relative
+/// to `dir` (`lib/util.fsx` becomes `lib.util`). Files under `assets/` are
+/// packed as raw assets. An optional `bundle.manifest` file supplies
+/// `name`, `version`, and `entrypoint` as `key = value` lines; otherwise the
+/// directory name, `0.0.0`, and `main` are used.
+pub fn compile_project(dir: &Path, options: &CompileOptions) -> Result<Bundle> {
+    if !dir.is_dir() {
+        return Err(Error::compilation(format!(
+            "expected a project directory, got: {}",
+            dir.display()
+        )));
+    }
+
+    let default_name = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("bundle")
+        .to_string();
+    let mut builder = BundleBuilder::new(default_name, "0.0.0").with_options(options.clone());
+
+    let manifest_path = dir.join(MANIFEST_FILE);
+    if manifest_path.is_file() {
+        let text = std::fs::read_to_string(&manifest_path)?;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(Error::invalid_bundle(format!(
+                    "malformed manifest line: {}",
+                    line
+                )));
+            };
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "name" => builder.manifest.name = value,
+                "version" => builder.manifest.version = value,
+                "entrypoint" => builder.manifest.entrypoint = value,
+                other => {
+                    return Err(Error::invalid_bundle(format!(
+                        "unknown manifest key: {}",
+                        other
+                    )))
+                }
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.sort();
+
+    let assets_root = dir.join(ASSETS_DIR);
+    for path in files {
+        if let Ok(rel) = path.strip_prefix(&assets_root) {
+            let data = std::fs::read(&path)?;
+            builder = builder.with_asset(path_key(rel, "/"), data);
+            continue;
+        }
+
+        let extension = path.extension().and_then(|e| e.to_str());
+        if extension == Some("fsx") || extension == Some("fusabi") {
+            let rel = path.strip_prefix(dir).unwrap_or(&path).with_extension("");
+            let source = std::fs::read_to_string(&path)?;
+            builder = builder.with_module(path_key(&rel, "."), source);
+        }
+    }
+
+    builder.build()
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn path_key(path: &Path, sep: &str) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+/// Compute the integrity hash of a bundle entry (64-bit FNV-1a).
+///
+/// This detects corruption and truncation; it is not a signature and does
+/// not protect against deliberate tampering.
+fn integrity_hash(data: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    data.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+fn check_hash(name: &str, data: &[u8], expected: u64) -> Result<()> {
+    if integrity_hash(data) == expected {
+        Ok(())
+    } else {
+        Err(Error::invalid_bundle(format!(
+            "integrity check failed for entry: {}",
+            name
+        )))
+    }
+}
+
+/// Write a length prefix, rejecting lengths that do not fit the format.
+fn write_len(out: &mut Vec<u8>, len: usize) -> Result<()> {
+    let len = u32::try_from(len)
+        .map_err(|_| Error::invalid_bundle(format!("section too large to encode: {}", len)))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    write_len(out, bytes.len())?;
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) -> Result<()> {
+    write_bytes(out, s.as_bytes())
+}
+
+fn write_strs(out: &mut Vec<u8>, items: &[String]) -> Result<()> {
+    write_len(out, items.len())?;
+    for item in items {
+        write_str(out, item)?;
+    }
+    Ok(())
+}
+
+/// Cursor over an encoded bundle.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(Error::invalid_bundle("unexpected end of bundle"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::invalid_bundle("string is not valid UTF-8"))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>> {
+        let count = self.u32()?;
+        (0..count).map(|_| self.string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Bundle {
+        BundleBuilder::new("plugin", "1.2.0")
+            .with_module(
+                "main",
+                "// @require fs:read\n// import util\n// export fn run()\n1 + 2",
+            )
+            .with_module("util", "// @require time:read\n40")
+            .with_asset("logo.txt", b"hello".to_vec())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_builder_manifest() {
+        let bundle = sample();
+        let manifest = bundle.manifest();
+
+        assert_eq!(manifest.name, "plugin");
+        assert_eq!(manifest.version, "1.2.0");
+        assert_eq!(manifest.entrypoint, "main");
+        assert_eq!(manifest.required_capabilities, vec!["fs:read", "time:read"]);
+        assert_eq!(manifest.exports, vec!["run"]);
+        assert_eq!(bundle.module("main").unwrap().imports(), ["util"]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bundle = sample();
+        let decoded = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(decoded, bundle);
+        assert_eq!(decoded.asset("logo.txt"), Some(&b"hello"[..]));
+        assert!(decoded.verify().is_ok());
+    }
+
+    #[test]
+    fn test_load_order() {
+        let bundle = sample();
+        let order: Vec<&str> = bundle
+            .load_order()
+            .unwrap()
+            .iter()
+            .map(|m| m.name())
+            .collect();
+        assert_eq!(order, vec!["util", "main"]);
+    }
+
+    #[test]
+    fn test_corrupted_entry_rejected() {
+        let mut bytes = sample().to_bytes().unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        assert!(matches!(
+            Bundle::from_bytes(&bytes),
+            Err(Error::InvalidBundle(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_and_bad_magic() {
+        let bytes = sample().to_bytes().unwrap();
+        assert!(Bundle::from_bytes(&bytes[..bytes.len() - 3]).is_err());
+        assert!(Bundle::from_bytes(b"NOPE\x01").is_err());
+    }
+
+    #[test]
+    fn test_missing_entrypoint() {
+        let result = BundleBuilder::new("plugin", "1.0.0")
+            .with_module("util", "1")
+            .build();
+        assert!(matches!(result, Err(Error::InvalidBundle(_))));
+    }
+
+    #[test]
+    fn test_import_cycle() {
+        let result = BundleBuilder::new("plugin", "1.0.0")
+            .with_module("main", "// import a\n1")
+            .with_module("a", "// import main\n2")
+            .build();
+        assert!(matches!(result, Err(Error::InvalidBundle(_))));
+    }
+
+    #[test]
+    fn test_check_capabilities() {
+        let bundle = sample();
+        assert!(bundle.check_capabilities(&Capabilities::none()).is_err());
+
+        let caps = Capabilities::none()
+            .with(Capability::FsRead)
+            .with(Capability::TimeRead);
+        assert!(bundle.check_capabilities(&caps).is_ok());
+    }
+
+    #[test]
+    fn test_compile_project() {
+        let dir = std::env::temp_dir().join(format!("fusabi-bundle-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join(MANIFEST_FILE), "name = demo\nversion = 0.3.0\n").unwrap();
+        std::fs::write(dir.join("main.fsx"), "// import lib.util\n42").unwrap();
+        std::fs::write(dir.join("lib/util.fsx"), "1").unwrap();
+        std::fs::write(dir.join("assets/data.json"), "{}").unwrap();
+
+        let bundle = compile_project(&dir, &CompileOptions::default());
+        let _ = std::fs::remove_dir_all(&dir);
+        let bundle = bundle.unwrap();
+
+        assert_eq!(bundle.manifest().name, "demo");
+        assert_eq!(bundle.manifest().version, "0.3.0");
+        assert!(bundle.module("lib.util").is_some());
+        assert_eq!(bundle.module("main").unwrap().imports(), ["lib.util"]);
+        assert_eq!(bundle.asset("data.json"), Some(&b"{}"[..]));
+    }
+}
@@ -3,7 +3,9 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use crate::disasm::disassemble;
 use crate::error::{Error, Result};
+use crate::link::host_references;
 
 /// Options for compilation.
 #[derive(Debug, Clone, Default)]
@@ -106,6 +108,8 @@ pub struct Metadata {
     pub exports: Vec<ExportInfo>,
     /// Imported modules.
     pub imports: Vec<ImportInfo>,
+    /// Host functions referenced by the bytecode.
+    pub host_references: Vec<HostReference>,
     /// Custom metadata entries.
     pub custom: HashMap<String, String>,
 }
@@ -134,6 +138,31 @@ pub struct ImportInfo {
     pub version: Option<String>,
 }
 
+/// A reference from a script to a global or module host function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostReference {
+    /// Module name for module functions (`math` in `math.add`).
+    pub module: Option<String>,
+    /// Function name.
+    pub name: String,
+    /// Number of arguments at the call site, if a call was found.
+    pub arg_count: Option<usize>,
+    /// Index of the function holding the reference (0 is the top-level chunk).
+    pub function: usize,
+    /// Instruction offset of the reference within that function.
+    pub offset: usize,
+}
+
+impl HostReference {
+    /// Get the fully qualified name (`module.name` or `name`).
+    pub fn qualified_name(&self) -> String {
+        match &self.module {
+            Some(module) => format!("{}.{}", module, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
 impl Metadata {
     /// Check if a capability is required.
     pub fn requires_capability(&self, cap: &str) -> bool {
@@ -219,7 +248,10 @@ pub fn compile_source(source: &str, options: &CompileOptions) -> Result<CompileR
 
     // Compile source -> bytecode via the real fusabi-frontend compiler.
     let bytecode = generate_bytecode(source, options)?;
-    let metadata = extract_metadata(source, options);
+    let mut metadata = extract_metadata(source, options);
+    if let Ok(disassembly) = disassemble(&bytecode) {
+        metadata.host_references = host_references(&disassembly);
+    }
     let warnings = check_warnings(source);
 
     let compile_time = start.elapsed();
@@ -268,6 +300,10 @@ pub fn compile_file(path: &Path, options: &CompileOptions) -> Result<CompileResu
 
 /// Validate bytecode without executing.
 ///
+/// This checks the container and that the chunk deserializes; use
+/// [`verify_bytecode`](crate::verify_bytecode) for a structural check of
+/// untrusted bytecode.
+///
 /// # Arguments
 ///
 /// * `bytecode` - The bytecode to validate
@@ -300,13 +336,35 @@ pub fn validate_bytecode(bytecode: &[u8]) -> Result<Metadata> {
         required_capabilities: Vec::new(),
         exports: Vec::new(),
         imports: Vec::new(),
+        host_references: Vec::new(),
         custom: HashMap::new(),
     })
 }
 
 /// Extract metadata from existing bytecode.
+///
+/// Named functions found by the disassembler are reported as exports, and
+/// global loads that the chunk does not define itself as host references.
 pub fn extract_bytecode_metadata(bytecode: &[u8]) -> Result<Metadata> {
-    validate_bytecode(bytecode)
+    let mut metadata = validate_bytecode(bytecode)?;
+
+    let disassembly = disassemble(bytecode)?;
+    metadata.host_references = host_references(&disassembly);
+    metadata.exports = disassembly
+        .functions
+        .iter()
+        .skip(1)
+        .filter_map(|f| {
+            f.name.as_ref().map(|name| ExportInfo {
+                name: name.clone(),
+                param_count: f.arity.unwrap_or(0),
+                is_async: false,
+                doc: None,
+            })
+        })
+        .collect();
+
+    Ok(metadata)
 }
 
 // Internal helper functions
@@ -336,6 +394,7 @@ fn extract_metadata(source: &str, options: &CompileOptions) -> Metadata {
         required_capabilities: Vec::new(),
         exports: Vec::new(),
         imports: Vec::new(),
+        host_references: Vec::new(),
         custom: HashMap::new(),
     };
 
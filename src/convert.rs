@@ -3,7 +3,7 @@
 //! This module provides traits and implementations for converting between
 //! Fusabi [`Value`] and Rust types, including serde support when enabled.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use thiserror::Error;
 
 use crate::value::{Value, ValueType};
@@ -238,6 +238,21 @@ impl<T: FromValue> FromValue for Vec<T> {
     }
 }
 
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn from_value(value: Value) -> Result<Self, ValueConversionError> {
+        match value {
+            Value::Map(map) => map
+                .into_iter()
+                .map(|(k, v)| T::from_value(v).map(|v| (k, v)))
+                .collect(),
+            _ => Err(ValueConversionError::type_mismatch(
+                ValueType::Map,
+                value.value_type(),
+            )),
+        }
+    }
+}
+
 impl<T: FromValue> FromValue for HashMap<String, T> {
     fn from_value(value: Value) -> Result<Self, ValueConversionError> {
         match value {
@@ -493,6 +508,16 @@ mod tests {
         assert_eq!(result.get("b"), Some(&2));
     }
 
+    #[test]
+    fn test_from_value_btreemap() {
+        let map: HashMap<String, Value> = ["c", "a", "b"]
+            .iter()
+            .map(|k| (k.to_string(), Value::Int(1)))
+            .collect();
+        let result: BTreeMap<String, i64> = BTreeMap::from_value(Value::Map(map)).unwrap();
+        assert_eq!(result.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+    }
+
     #[test]
     fn test_from_value_option() {
         let opt: Option<i64> = Option::from_value(Value::Null).unwrap();
@@ -0,0 +1,301 @@
+//! Cron expressions for scheduled scripts.
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// Seconds in a day.
+const DAY: u64 = 86_400;
+
+/// How far ahead to search for a matching time before giving up.
+const SEARCH_DAYS: u64 = 5 * 366;
+
+/// A five-field cron expression, evaluated in UTC.
+///
+/// Fields are minute (0-59), hour (0-23), day of month (1-31), month (1-12)
+/// and day of week (0-7, where 0 and 7 are Sunday). Each field accepts `*`,
+/// single values, ranges `a-b`, steps `*/n`, `a/n` or `a-b/n`, and
+/// comma-separated lists of these. `@hourly`, `@daily`, `@weekly`,
+/// `@monthly` and `@yearly` are accepted as shorthands.
+///
+/// As in cron, when both day fields are restricted a day matching either
+/// of them matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Parse a cron expression.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(Error::invalid_config(format!(
+                "cron expression {:?} must have 5 fields",
+                expr
+            )));
+        };
+
+        let mut weekdays = parse_field(weekday, "day of week", 0, 7)?;
+        // Sunday may be written as 7.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expr: expr.trim().to_string(),
+            minutes: parse_field(minute, "minute", 0, 59)?,
+            hours: parse_field(hour, "hour", 0, 23)?,
+            days: parse_field(day, "day of month", 1, 31)?,
+            months: parse_field(month, "month", 1, 12)?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// Get the expression as written.
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+
+    /// Get the first matching time strictly after `time`.
+    ///
+    /// Returns `None` if nothing matches within five years, as for
+    /// `0 0 30 2 *`.
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let mut t = secs / 60 * 60 + 60;
+        let limit = t + SEARCH_DAYS * DAY;
+
+        while t < limit {
+            let days = t / DAY;
+            let (year, month, day) = civil_from_days(days as i64);
+            if !has(self.months, month) {
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                t = days_from_civil(year, month, 1) as u64 * DAY;
+                continue;
+            }
+            // 1970-01-01 was a Thursday.
+            let weekday = ((days + 4) % 7) as u32;
+            if !self.day_matches(day, weekday) {
+                t = (days + 1) * DAY;
+                continue;
+            }
+            if !has(self.hours, ((t % DAY) / 3600) as u32) {
+                t = (t / 3600 + 1) * 3600;
+                continue;
+            }
+            if !has(self.minutes, ((t % 3600) / 60) as u32) {
+                t += 60;
+                continue;
+            }
+            return Some(UNIX_EPOCH + Duration::from_secs(t));
+        }
+        None
+    }
+
+    fn day_matches(&self, day: u32, weekday: u32) -> bool {
+        let day = has(self.days, day);
+        let weekday = has(self.weekdays, weekday);
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for Cron {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Parse one field into a bit mask of the values it matches.
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || Error::invalid_config(format!("invalid cron {} field {:?}", name, field));
+    let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(number(step)?)),
+            None => (part, None),
+        };
+        let (lo, hi) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((lo, hi)) => (number(lo)?, number(hi)?),
+            // `a/n` runs from `a` to the end of the range.
+            None if step.is_some() => (number(range)?, max),
+            None => {
+                let value = number(range)?;
+                (value, value)
+            }
+        };
+        let step = step.unwrap_or(1);
+        if lo < min || hi > max || lo > hi || step == 0 {
+            return Err(invalid());
+        }
+        for value in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a (year, month, day) date to days since the Unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// 2024-01-01T00:00:00Z, a Monday.
+    const NEW_YEAR_2024: u64 = 1_704_067_200;
+
+    #[test]
+    fn test_parse_errors() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "a * * * *",
+            "@often",
+        ] {
+            assert!(
+                matches!(Cron::parse(expr), Err(Error::InvalidConfig(_))),
+                "{} should not parse",
+                expr
+            );
+        }
+        assert_eq!("@daily".parse::<Cron>().unwrap().to_string(), "@daily");
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_15 = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            every_15.next_after(at(NEW_YEAR_2024)),
+            Some(at(NEW_YEAR_2024 + 900))
+        );
+        assert_eq!(
+            every_15.next_after(at(NEW_YEAR_2024 + 1)),
+            Some(at(NEW_YEAR_2024 + 900))
+        );
+
+        // Saturday 2024-01-06 10:00 to Monday 2024-01-08 09:00.
+        let weekdays = Cron::parse("0 9 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_after(at(1_704_535_200)),
+            Some(at(1_704_704_400))
+        );
+
+        let monthly = Cron::parse("@monthly").unwrap();
+        assert_eq!(
+            monthly.next_after(at(NEW_YEAR_2024 + 30 * DAY)),
+            Some(at(1_706_745_600))
+        );
+
+        // The next leap day after 2024-03-01 is 2028-02-29.
+        let leap_day = Cron::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap_day.next_after(at(1_709_251_200)),
+            Some(at(1_835_395_200))
+        );
+
+        assert_eq!(Cron::parse("0 0 30 2 *").unwrap().next_after(at(0)), None);
+    }
+
+    #[test]
+    fn test_day_fields() {
+        // Sunday as 7, and day of month or day of week when both are set.
+        let sundays = Cron::parse("0 0 * * 7").unwrap();
+        assert_eq!(
+            sundays.next_after(at(NEW_YEAR_2024)),
+            Some(at(NEW_YEAR_2024 + 6 * DAY))
+        );
+
+        let either = Cron::parse("0 0 3 * 2").unwrap();
+        assert_eq!(
+            either.next_after(at(NEW_YEAR_2024)),
+            Some(at(NEW_YEAR_2024 + DAY))
+        );
+        assert_eq!(
+            either.next_after(at(NEW_YEAR_2024 + DAY)),
+            Some(at(NEW_YEAR_2024 + 2 * DAY))
+        );
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        for days in [-1, 0, 59, 10_956, 19_723, 20_000, 100_000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+}
@@ -0,0 +1,302 @@
+//! Declaration stubs for host functions.
+//!
+//! Host functions registered with a [`HostFnDecl`] carry parameter names,
+//! types and documentation. [`HostRegistry::emit_declarations`] renders them
+//! as a Fusabi signature file that editors and the frontend type checker can
+//! load.
+//!
+//! Declarations use curried `val` signatures. Untyped values are declared as
+//! `obj`, optional parameters as `option` types, and trailing variadic
+//! arguments as a `list`, the closest forms a curried signature has.
+//!
+//! [`HostRegistry::emit_declarations`]: crate::HostRegistry::emit_declarations
+
+use std::fmt::Write;
+
+use crate::engine::HostFnSignature;
+use crate::value::ValueType;
+
+/// A named host function parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostParam {
+    /// Parameter name.
+    pub name: String,
+    /// Parameter type (`None` accepts any value).
+    pub ty: Option<ValueType>,
+    /// Whether callers may omit the argument.
+    pub optional: bool,
+}
+
+impl HostParam {
+    /// Create a typed parameter.
+    pub fn new(name: impl Into<String>, ty: ValueType) -> Self {
+        Self {
+            name: name.into(),
+            ty: Some(ty),
+            optional: false,
+        }
+    }
+
+    /// Create a parameter accepting any value.
+    pub fn any(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ty: None,
+            optional: false,
+        }
+    }
+
+    /// Mark the parameter as optional.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+/// Typed registration metadata for a host function.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostFnDecl {
+    /// Positional parameters; optional ones must follow the required ones.
+    pub params: Vec<HostParam>,
+    /// Trailing variadic parameter, if any.
+    pub rest: Option<HostParam>,
+    /// Return type (`None` for any value).
+    pub returns: Option<ValueType>,
+    /// Documentation shown to script authors.
+    pub doc: Option<String>,
+}
+
+impl HostFnDecl {
+    /// Create an empty declaration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a typed positional parameter.
+    pub fn with_param(mut self, name: impl Into<String>, ty: ValueType) -> Self {
+        self.params.push(HostParam::new(name, ty));
+        self
+    }
+
+    /// Add a positional parameter accepting any value.
+    pub fn with_any_param(mut self, name: impl Into<String>) -> Self {
+        self.params.push(HostParam::any(name));
+        self
+    }
+
+    /// Add a typed positional parameter that callers may omit.
+    pub fn with_optional_param(mut self, name: impl Into<String>, ty: ValueType) -> Self {
+        self.params.push(HostParam::new(name, ty).optional());
+        self
+    }
+
+    /// Accept any number of trailing arguments of the given type.
+    pub fn with_rest(mut self, name: impl Into<String>, ty: ValueType) -> Self {
+        self.rest = Some(HostParam::new(name, ty));
+        self
+    }
+
+    /// Set the return type.
+    pub fn with_returns(mut self, ty: ValueType) -> Self {
+        self.returns = Some(ty);
+        self
+    }
+
+    /// Set the documentation.
+    pub fn with_doc(mut self, doc: impl Into<String>) -> Self {
+        self.doc = Some(doc.into());
+        self
+    }
+
+    /// Get the argument-count signature implied by the parameters.
+    pub fn signature(&self) -> HostFnSignature {
+        let required = self
+            .params
+            .iter()
+            .position(|p| p.optional)
+            .unwrap_or(self.params.len());
+        match self.rest {
+            Some(_) => HostFnSignature::variadic(required),
+            None => HostFnSignature::range(required, self.params.len()),
+        }
+    }
+
+    /// Render the type of the function, e.g. `a: int -> b: int -> int`.
+    ///
+    /// Optional parameters render as `name: T option` and the variadic rest
+    /// as `name: T list`.
+    pub fn type_signature(&self) -> String {
+        let mut parts: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| {
+                let ty = type_name(p.ty);
+                if p.optional {
+                    format!("{}: {} option", p.name, ty)
+                } else {
+                    format!("{}: {}", p.name, ty)
+                }
+            })
+            .collect();
+        if let Some(rest) = &self.rest {
+            parts.push(format!("{}: {} list", rest.name, type_name(rest.ty)));
+        }
+        if parts.is_empty() {
+            parts.push("unit".into());
+        }
+        parts.push(type_name(self.returns));
+        parts.join(" -> ")
+    }
+}
+
+/// Fusabi type for a value type; `None` (any value) is `obj`.
+fn type_name(ty: Option<ValueType>) -> String {
+    let name = match ty {
+        None => "obj",
+        Some(ValueType::Null) => "unit",
+        Some(ValueType::Bool) => "bool",
+        Some(ValueType::Int) => "int",
+        Some(ValueType::Float) => "float",
+        Some(ValueType::String) | Some(ValueType::Error) => "string",
+        Some(ValueType::List) => "obj list",
+        Some(ValueType::Map) => "Map<string, obj>",
+        Some(ValueType::Function) => "(obj -> obj)",
+        Some(ValueType::Bytes) => "byte[]",
+    };
+    name.to_string()
+}
+
+/// Declaration for a function registered without typed metadata.
+pub(crate) fn undeclared(signature: Option<&HostFnSignature>) -> HostFnDecl {
+    let mut decl = HostFnDecl::new();
+    if let Some(signature) = signature {
+        for i in 0..signature.min_args {
+            decl = decl.with_any_param(format!("arg{}", i));
+        }
+        match signature.max_args {
+            Some(max) => {
+                for i in signature.min_args..max {
+                    decl.params
+                        .push(HostParam::any(format!("arg{}", i)).optional());
+                }
+            }
+            None => decl.rest = Some(HostParam::any("rest")),
+        }
+    } else {
+        decl.rest = Some(HostParam::any("args"));
+    }
+    decl
+}
+
+/// Render a declaration file.
+///
+/// `globals` and `modules` must already be sorted by name so the output is
+/// stable across runs.
+pub(crate) fn render(
+    globals: &[(&str, HostFnDecl)],
+    modules: &[(&str, Vec<(&str, HostFnDecl)>)],
+) -> String {
+    let mut out = String::from("// Host function declarations generated by fusabi-host.\n");
+
+    for (name, decl) in globals {
+        out.push('\n');
+        write_decl(&mut out, "", name, decl);
+    }
+
+    for (module, functions) in modules {
+        let _ = write!(out, "\nmodule {} =\n", module);
+        for (i, (name, decl)) in functions.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            write_decl(&mut out, "    ", name, decl);
+        }
+    }
+
+    out
+}
+
+fn write_decl(out: &mut String, indent: &str, name: &str, decl: &HostFnDecl) {
+    if let Some(doc) = &decl.doc {
+        for line in doc.lines() {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+    let _ = writeln!(out, "{}val {} : {}", indent, name, decl.type_signature());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_signature() {
+        let decl = HostFnDecl::new()
+            .with_param("a", ValueType::Int)
+            .with_param("b", ValueType::Int)
+            .with_returns(ValueType::Int);
+        assert_eq!(decl.type_signature(), "a: int -> b: int -> int");
+        assert_eq!(decl.signature(), HostFnSignature::fixed(2));
+
+        let decl = HostFnDecl::new().with_returns(ValueType::Null);
+        assert_eq!(decl.type_signature(), "unit -> unit");
+
+        let decl = HostFnDecl::new()
+            .with_param("fmt", ValueType::String)
+            .with_rest("args", ValueType::String);
+        assert_eq!(
+            decl.type_signature(),
+            "fmt: string -> args: string list -> obj"
+        );
+        assert_eq!(decl.signature(), HostFnSignature::variadic(1));
+
+        let decl = HostFnDecl::new()
+            .with_param("items", ValueType::List)
+            .with_optional_param("limit", ValueType::Int)
+            .with_returns(ValueType::Map);
+        assert_eq!(
+            decl.type_signature(),
+            "items: obj list -> limit: int option -> Map<string, obj>"
+        );
+        assert_eq!(decl.signature(), HostFnSignature::range(1, 2));
+    }
+
+    #[test]
+    fn test_undeclared() {
+        assert_eq!(undeclared(None).type_signature(), "args: obj list -> obj");
+        let decl = undeclared(Some(&HostFnSignature::range(1, 2)));
+        assert_eq!(
+            decl.type_signature(),
+            "arg0: obj -> arg1: obj option -> obj"
+        );
+        assert_eq!(decl.signature(), HostFnSignature::range(1, 2));
+        assert_eq!(
+            undeclared(Some(&HostFnSignature::variadic(1))).type_signature(),
+            "arg0: obj -> rest: obj list -> obj"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let log = HostFnDecl::new()
+            .with_param("message", ValueType::String)
+            .with_returns(ValueType::Null)
+            .with_doc("Write a message to the host log.");
+        let add = HostFnDecl::new()
+            .with_param("a", ValueType::Int)
+            .with_param("b", ValueType::Int)
+            .with_returns(ValueType::Int);
+
+        let out = render(&[("log", log)], &[("math", vec![("add", add)])]);
+        assert_eq!(
+            out,
+            "// Host function declarations generated by fusabi-host.\n\
+             \n\
+             /// Write a message to the host log.\n\
+             val log : message: string -> unit\n\
+             \n\
+             module math =\n    \
+             val add : a: int -> b: int -> int\n"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Deterministic execution: a host-controlled clock and seeded randomness.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A clock controlled by the host.
+///
+/// Clones share the same reading, so a host can keep one handle and advance
+/// the time seen by every engine configured with it. Starts at the Unix
+/// epoch unless created with [`VirtualClock::new`].
+#[derive(Clone, Default)]
+pub struct VirtualClock {
+    /// Nanoseconds since the Unix epoch.
+    nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// Create a clock reading `start`.
+    pub fn new(start: SystemTime) -> Self {
+        let clock = Self::default();
+        clock.set(start);
+        clock
+    }
+
+    /// Get the current reading.
+    pub fn now(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    /// Set the reading. Times before the Unix epoch read as the epoch.
+    pub fn set(&self, time: SystemTime) {
+        let nanos = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos().min(u64::MAX as u128) as u64);
+        self.nanos.store(nanos, Ordering::SeqCst);
+    }
+
+    /// Move the reading forward.
+    pub fn advance(&self, by: Duration) {
+        let by = by.as_nanos().min(u64::MAX as u128) as u64;
+        let _ = self
+            .nanos
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                Some(n.saturating_add(by))
+            });
+    }
+}
+
+impl std::fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
+
+/// Settings for deterministic execution.
+///
+/// Scripts read time from [`clock`](Self::clock) instead of the system
+/// clock, and draw random numbers from a generator seeded once per
+/// execution. The seed is recorded in the
+/// [`ExecutionReport`](crate::ExecutionReport), so a run can be repeated
+/// bit for bit by passing it to [`ExecOptions::with_seed`](crate::ExecOptions::with_seed).
+#[derive(Debug, Clone, Default)]
+pub struct DeterministicConfig {
+    /// Clock read by scripts granted `TimeRead`.
+    pub clock: VirtualClock,
+    /// Seed used by every execution (`None` draws a fresh seed each time).
+    pub seed: Option<u64>,
+}
+
+impl DeterministicConfig {
+    /// Create a configuration with a clock at the Unix epoch and fresh
+    /// seeds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read time from `clock`.
+    pub fn with_clock(mut self, clock: VirtualClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Seed every execution with `seed`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Clock and random state of one deterministic execution.
+#[derive(Debug)]
+pub(crate) struct Determinism {
+    pub(crate) clock: VirtualClock,
+    pub(crate) seed: u64,
+    /// Clock reading when the execution started.
+    pub(crate) started_at: SystemTime,
+    rng: SeededRng,
+}
+
+impl Determinism {
+    pub(crate) fn new(clock: VirtualClock, seed: u64) -> Self {
+        Self {
+            started_at: clock.now(),
+            clock,
+            seed,
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
+/// SplitMix64 pseudo-random generator.
+#[derive(Debug, Clone)]
+struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Draw an unpredictable 64-bit value.
+pub(crate) fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Map 64 random bits to a float in `[0, 1)`.
+pub(crate) fn unit_f64(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_is_shared() {
+        let clock = VirtualClock::new(UNIX_EPOCH + Duration::from_secs(1_000));
+        let other = clock.clone();
+        other.advance(Duration::from_millis(1_500));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_millis(1_001_500));
+
+        clock.set(UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(other.now(), UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_seeded_sequence_repeats() {
+        let mut a = SeededRng::new(42);
+        let mut b = SeededRng::new(42);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first[0], SeededRng::new(43).next_u64());
+
+        // Reference output of SplitMix64 seeded with 0.
+        assert_eq!(SeededRng::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+
+        for bits in [0, u64::MAX, first[0]] {
+            assert!((0.0..1.0).contains(&unit_f64(bits)));
+        }
+    }
+}
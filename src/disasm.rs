@@ -0,0 +1,489 @@
+//! Bytecode disassembly and inspection.
+//!
+//! Decodes an FZB container back into a listing of functions, constants, and
+//! instructions so that a misbehaving script can be inspected from its bytes
+//! alone. The listing renders as text via [`Display`](std::fmt::Display) and,
+//! with `serde-support`, as JSON.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// A disassembled bytecode container.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct Disassembly {
+    /// Size of the disassembled bytecode in bytes.
+    pub bytecode_bytes: usize,
+    /// Functions in the container; the top-level chunk is always first.
+    pub functions: Vec<FunctionListing>,
+}
+
+/// Disassembly of a single function.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct FunctionListing {
+    /// Index of the function within the disassembly.
+    pub index: usize,
+    /// Function name, if known.
+    pub name: Option<String>,
+    /// Declared parameter count, if known.
+    pub arity: Option<usize>,
+    /// Constant pool of the function.
+    pub constants: Vec<ConstantListing>,
+    /// Decoded instructions, in offset order.
+    pub instructions: Vec<InstructionListing>,
+}
+
+/// A constant pool entry.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct ConstantListing {
+    /// Index in the constant pool.
+    pub index: usize,
+    /// Kind of constant (`Int`, `Float`, `Bool`, `Str`, `Unit`, `Function`, or
+    /// `Value` for anything else).
+    pub kind: String,
+    /// Rendered value; strings are stored unquoted.
+    pub value: String,
+    /// Function listing index when this constant is a function.
+    pub function: Option<usize>,
+}
+
+/// A single decoded instruction.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct InstructionListing {
+    /// Instruction offset within the function.
+    pub offset: usize,
+    /// Opcode mnemonic. Instructions this crate does not know keep their
+    /// `Debug` rendering here and have no operands.
+    pub opcode: String,
+    /// Operands, in declaration order.
+    pub operands: Vec<i64>,
+    /// Absolute target offset for jump instructions.
+    pub jump_target: Option<usize>,
+    /// Source line the instruction was compiled from, if the chunk has a
+    /// line table.
+    pub line: Option<usize>,
+}
+
+impl InstructionListing {
+    /// Get an operand as an integer.
+    pub fn int_operand(&self, index: usize) -> Option<i64> {
+        self.operands.get(index).copied()
+    }
+
+    /// Returns true if this is a jump instruction.
+    pub fn is_jump(&self) -> bool {
+        matches!(self.opcode.as_str(), "Jump" | "JumpIfFalse")
+    }
+}
+
+impl Disassembly {
+    /// Get the top-level function.
+    pub fn main(&self) -> &FunctionListing {
+        &self.functions[0]
+    }
+
+    /// Look up a function by name.
+    pub fn function(&self, name: &str) -> Option<&FunctionListing> {
+        self.functions
+            .iter()
+            .find(|f| f.name.as_deref() == Some(name))
+    }
+
+    /// Total number of instructions across all functions.
+    pub fn instruction_count(&self) -> usize {
+        self.functions.iter().map(|f| f.instructions.len()).sum()
+    }
+
+    /// Render the disassembly as a JSON string.
+    #[cfg(feature = "serde-support")]
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Render the disassembly as a pretty JSON string.
+    #[cfg(feature = "serde-support")]
+    pub fn to_json_string_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "null".to_string())
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FunctionListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.as_deref().unwrap_or("<anonymous>");
+        match self.arity {
+            Some(arity) => writeln!(f, "== fn #{} {} (arity {}) ==", self.index, name, arity)?,
+            None => writeln!(f, "== fn #{} {} ==", self.index, name)?,
+        }
+
+        if !self.constants.is_empty() {
+            writeln!(f, "constants:")?;
+            for c in &self.constants {
+                if c.kind == "Str" {
+                    write!(f, "  [{:>3}] {:<8} {:?}", c.index, c.kind, c.value)?;
+                } else {
+                    write!(f, "  [{:>3}] {:<8} {}", c.index, c.kind, c.value)?;
+                }
+                if let Some(func) = c.function {
+                    write!(f, "  ; fn #{}", func)?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f, "code:")?;
+        let mut last_line = None;
+        for instr in &self.instructions {
+            match instr.line {
+                Some(line) if last_line != Some(line) => write!(f, "  {:>4} ", line)?,
+                Some(_) => write!(f, "     | ")?,
+                None => write!(f, "       ")?,
+            }
+            last_line = instr.line;
+            write!(f, "{:04} {:<16}", instr.offset, instr.opcode)?;
+            if !instr.operands.is_empty() {
+                let operands: Vec<String> = instr.operands.iter().map(i64::to_string).collect();
+                write!(f, " {}", operands.join(", "))?;
+            }
+            if let Some(target) = instr.jump_target {
+                write!(f, "  -> {:04}", target)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble FZB bytecode.
+///
+/// The top-level chunk is listed first, followed by every function prototype
+/// in its constant pool, recursively, in the order they are found.
+///
+/// # Arguments
+///
+/// * `bytecode` - The bytecode to disassemble
+///
+/// # Returns
+///
+/// A `Disassembly` listing, or an error if the bytecode is not a valid container.
+pub fn disassemble(bytecode: &[u8]) -> Result<Disassembly> {
+    if bytecode.len() < 5 || &bytecode[0..4] != fusabi_vm::FZB_MAGIC {
+        return Err(Error::invalid_bytecode("invalid bytecode header"));
+    }
+
+    let chunk = fusabi_vm::deserialize_chunk(bytecode)
+        .map_err(|e| Error::invalid_bytecode(e.to_string()))?;
+
+    let mut functions = Vec::new();
+    list_function(&chunk, chunk.name.clone(), None, &mut functions);
+
+    Ok(Disassembly {
+        bytecode_bytes: bytecode.len(),
+        functions,
+    })
+}
+
+/// List a chunk and, after it, the prototypes in its constant pool.
+///
+/// Returns the index of the chunk's listing.
+fn list_function(
+    chunk: &fusabi_vm::Chunk,
+    name: Option<String>,
+    arity: Option<usize>,
+    functions: &mut Vec<FunctionListing>,
+) -> usize {
+    let index = functions.len();
+    functions.push(FunctionListing {
+        index,
+        name,
+        arity,
+        constants: Vec::new(),
+        instructions: chunk
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(offset, instr)| InstructionListing {
+                line: chunk.lines.get(offset).copied(),
+                ..decode_instruction(offset, instr)
+            })
+            .collect(),
+    });
+
+    let constants = chunk
+        .constants
+        .iter()
+        .enumerate()
+        .map(|(i, value)| list_constant(i, value, functions))
+        .collect();
+    functions[index].constants = constants;
+    index
+}
+
+fn list_constant(
+    index: usize,
+    value: &fusabi_vm::Value,
+    functions: &mut Vec<FunctionListing>,
+) -> ConstantListing {
+    use fusabi_vm::Value as VmValue;
+
+    let (kind, value, function) = match value {
+        VmValue::Int(n) => ("Int", n.to_string(), None),
+        VmValue::Float(f) => ("Float", f.to_string(), None),
+        VmValue::Bool(b) => ("Bool", b.to_string(), None),
+        VmValue::Str(s) => ("Str", s.clone(), None),
+        VmValue::Unit => ("Unit", "()".to_string(), None),
+        VmValue::Closure(closure) => {
+            let function = list_function(
+                &closure.chunk,
+                closure.name.clone(),
+                Some(usize::from(closure.arity)),
+                functions,
+            );
+            let name = closure.name.as_deref().unwrap_or("<anonymous>");
+            ("Function", name.to_string(), Some(function))
+        }
+        other => ("Value", format!("{:?}", other), None),
+    };
+    ConstantListing {
+        index,
+        kind: kind.to_string(),
+        value,
+        function,
+    }
+}
+
+/// Decode a VM instruction into its listing.
+fn decode_instruction(offset: usize, instr: &fusabi_vm::Instruction) -> InstructionListing {
+    use fusabi_vm::Instruction as I;
+
+    let (opcode, operands): (&str, Vec<i64>) = match *instr {
+        I::LoadConst(k) => ("LoadConst", vec![k.into()]),
+        I::LoadLocal(n) => ("LoadLocal", vec![n.into()]),
+        I::StoreLocal(n) => ("StoreLocal", vec![n.into()]),
+        I::LoadUpvalue(n) => ("LoadUpvalue", vec![n.into()]),
+        I::StoreUpvalue(n) => ("StoreUpvalue", vec![n.into()]),
+        I::LoadGlobal(k) => ("LoadGlobal", vec![k.into()]),
+        I::StoreGlobal(k) => ("StoreGlobal", vec![k.into()]),
+        I::Pop => ("Pop", vec![]),
+        I::Dup => ("Dup", vec![]),
+        I::Add => ("Add", vec![]),
+        I::Sub => ("Sub", vec![]),
+        I::Mul => ("Mul", vec![]),
+        I::Div => ("Div", vec![]),
+        I::Mod => ("Mod", vec![]),
+        I::Eq => ("Eq", vec![]),
+        I::Neq => ("Neq", vec![]),
+        I::Lt => ("Lt", vec![]),
+        I::Lte => ("Lte", vec![]),
+        I::Gt => ("Gt", vec![]),
+        I::Gte => ("Gte", vec![]),
+        I::And => ("And", vec![]),
+        I::Or => ("Or", vec![]),
+        I::Not => ("Not", vec![]),
+        I::Neg => ("Neg", vec![]),
+        I::Jump(delta) => ("Jump", vec![delta.into()]),
+        I::JumpIfFalse(delta) => ("JumpIfFalse", vec![delta.into()]),
+        I::MakeTuple(n) => ("MakeTuple", vec![n.into()]),
+        I::GetTupleField(n) => ("GetTupleField", vec![n.into()]),
+        I::MakeList(n) => ("MakeList", vec![n.into()]),
+        I::Cons => ("Cons", vec![]),
+        I::ListHead => ("ListHead", vec![]),
+        I::ListTail => ("ListTail", vec![]),
+        I::IsNil => ("IsNil", vec![]),
+        I::MakeArray(n) => ("MakeArray", vec![n.into()]),
+        I::MakeClosure(k, n) => ("MakeClosure", vec![k.into(), n.into()]),
+        I::Call(n) => ("Call", vec![n.into()]),
+        I::TailCall(n) => ("TailCall", vec![n.into()]),
+        I::Return => ("Return", vec![]),
+        #[allow(unreachable_patterns)]
+        _ => {
+            return InstructionListing {
+                offset,
+                opcode: format!("{:?}", instr),
+                operands: Vec::new(),
+                jump_target: None,
+                line: None,
+            }
+        }
+    };
+
+    let mut listing = InstructionListing {
+        offset,
+        opcode: opcode.to_string(),
+        operands,
+        jump_target: None,
+        line: None,
+    };
+
+    // Jumps are relative to the following instruction: the VM advances the
+    // instruction pointer before applying the offset.
+    if listing.is_jump() {
+        let target = offset as i64 + 1 + listing.operands[0];
+        listing.jump_target = usize::try_from(target).ok();
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{compile_source, CompileOptions};
+
+    fn disassemble_source(source: &str) -> Disassembly {
+        let compiled = compile_source(source, &CompileOptions::default()).unwrap();
+        disassemble(&compiled.bytecode).unwrap()
+    }
+
+    fn opcodes(function: &FunctionListing) -> Vec<&str> {
+        function
+            .instructions
+            .iter()
+            .map(|i| i.opcode.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_disassemble_compiled() {
+        let compiled = compile_source("1 + 2", &CompileOptions::default()).unwrap();
+        let disasm = disassemble(&compiled.bytecode).unwrap();
+
+        assert_eq!(disasm.bytecode_bytes, compiled.bytecode.len());
+        assert_eq!(disasm.functions.len(), 1);
+        assert_eq!(
+            opcodes(disasm.main()),
+            ["LoadConst", "LoadConst", "Add", "Return"]
+        );
+
+        let main = disasm.main();
+        let first = main.instructions[0].int_operand(0).unwrap() as usize;
+        assert_eq!(main.constants[first].kind, "Int");
+        assert_eq!(main.constants[first].value, "1");
+
+        let text = disasm.to_string();
+        assert!(text.contains("== fn #0"));
+        assert!(text.contains("0002 Add"));
+    }
+
+    #[test]
+    fn test_source_lines_are_listed() {
+        let disasm = disassemble_source(
+            "let x = 1
+x + 2",
+        );
+        let main = disasm.main();
+
+        assert_eq!(main.instructions[0].line, Some(1));
+        assert_eq!(main.instructions.last().unwrap().line, Some(2));
+        assert!(disasm.to_string().contains("     2 "));
+    }
+
+    #[test]
+    fn test_function_prototypes_are_listed() {
+        let disasm = disassemble_source(
+            "let add a b = a + b
+add 1 2",
+        );
+
+        let add = disasm.function("add").unwrap();
+        assert_eq!(add.arity, Some(2));
+        assert_eq!(opcodes(add), ["LoadLocal", "LoadLocal", "Add", "Return"]);
+        assert_eq!(add.instructions[1].int_operand(0), Some(1));
+
+        let constant = disasm
+            .main()
+            .constants
+            .iter()
+            .find(|c| c.kind == "Function")
+            .unwrap();
+        assert_eq!(constant.function, Some(add.index));
+        assert_eq!(constant.value, "add");
+        assert_eq!(
+            disasm.instruction_count(),
+            disasm.main().instructions.len() + 4
+        );
+    }
+
+    #[test]
+    fn test_nested_prototypes_are_listed() {
+        let disasm = disassemble_source(
+            "let outer x = (let inner y = y * 2 in inner x)
+outer 1",
+        );
+
+        let outer = disasm.function("outer").unwrap();
+        let inner = disasm.function("inner").unwrap();
+        assert!(outer
+            .constants
+            .iter()
+            .any(|c| c.function == Some(inner.index)));
+        assert_eq!(inner.arity, Some(1));
+        assert!(opcodes(inner).contains(&"Mul"));
+    }
+
+    #[test]
+    fn test_jump_targets() {
+        let disasm = disassemble_source("if 1 < 2 then 10 else 20");
+        let main = disasm.main();
+
+        let branch = main
+            .instructions
+            .iter()
+            .find(|i| i.opcode == "JumpIfFalse")
+            .unwrap();
+        let target = branch.jump_target.unwrap();
+        let else_value = &main.instructions[target];
+        assert_eq!(else_value.opcode, "LoadConst");
+        assert_eq!(
+            main.constants[else_value.int_operand(0).unwrap() as usize].value,
+            "20"
+        );
+
+        let skip = main
+            .instructions
+            .iter()
+            .find(|i| i.opcode == "Jump")
+            .unwrap();
+        assert_eq!(
+            main.instructions[skip.jump_target.unwrap()].opcode,
+            "Return"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_rejects_garbage() {
+        assert!(matches!(
+            disassemble(b"not bytecode"),
+            Err(Error::InvalidBytecode(_))
+        ));
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_disassembly_json() {
+        let disasm = disassemble_source("42");
+
+        let json: serde_json::Value = serde_json::from_str(&disasm.to_json_string()).unwrap();
+        assert_eq!(
+            json["functions"][0]["instructions"][0]["opcode"],
+            "LoadConst"
+        );
+        assert_eq!(json["functions"][0]["constants"][0]["value"], "42");
+        assert_eq!(json["functions"][0]["instructions"][0]["line"], 1);
+    }
+}
@@ -1,16 +1,27 @@
 //! Fusabi engine wrapper with configuration and execution context.
 
 use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 
 use parking_lot::Mutex;
 
+use crate::bundle::Bundle;
 use crate::capabilities::Capabilities;
+use crate::compile::{compile_source, CompileOptions};
+use crate::decl::{self, HostFnDecl};
+use crate::deterministic::{self, Determinism, DeterministicConfig};
 use crate::error::{Error, Result};
+use crate::host_context::{DefaultHostContext, HostContext, LogLevel};
 use crate::limits::{LimitTracker, Limits};
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PrometheusMetrics;
+use crate::options::ExecOptions;
+use crate::safepoint::{self, SAFEPOINT};
 use crate::sandbox::{Sandbox, SandboxConfig};
+use crate::trace::{ReplayReport, Trace, TraceMode};
 use crate::value::Value;
+use crate::verify::verify_bytecode;
 
 /// Configuration for creating an Engine.
 #[derive(Debug, Clone)]
@@ -23,8 +34,17 @@ pub struct EngineConfig {
     pub sandbox: SandboxConfig,
     /// Whether to enable debug mode.
     pub debug: bool,
+    /// Whether to structurally verify bytecode before executing it.
+    pub verify_bytecode: bool,
     /// Custom metadata to attach to the engine.
     pub metadata: HashMap<String, String>,
+    /// Virtual clock and seeded randomness for repeatable executions.
+    pub deterministic: Option<DeterministicConfig>,
+    /// Whether to record host function calls into a [`Trace`].
+    pub record_host_calls: bool,
+    /// Prometheus metrics updated by every execution.
+    #[cfg(feature = "metrics-prometheus")]
+    pub metrics: Option<PrometheusMetrics>,
 }
 
 impl Default for EngineConfig {
@@ -34,7 +54,12 @@ impl Default for EngineConfig {
             capabilities: Capabilities::safe_defaults(),
             sandbox: SandboxConfig::default(),
             debug: false,
+            verify_bytecode: false,
             metadata: HashMap::new(),
+            deterministic: None,
+            record_host_calls: false,
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: None,
         }
     }
 }
@@ -69,12 +94,45 @@ impl EngineConfig {
         self
     }
 
+    /// Enable structural bytecode verification before execution.
+    ///
+    /// Use this when bytecode comes from untrusted sources.
+    pub fn with_bytecode_verification(mut self, verify: bool) -> Self {
+        self.verify_bytecode = verify;
+        self
+    }
+
     /// Add metadata.
     pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.metadata.insert(key.into(), value.into());
         self
     }
 
+    /// Run executions deterministically.
+    ///
+    /// Host functions reading time through [`ExecutionContext::now`] see the
+    /// virtual clock, and [`ExecutionContext::random_u64`] draws from a
+    /// generator seeded per execution.
+    pub fn with_deterministic(mut self, config: DeterministicConfig) -> Self {
+        self.deterministic = Some(config);
+        self
+    }
+
+    /// Record every host function call made through
+    /// [`HostRegistry::call`] into a [`Trace`], available afterwards from
+    /// [`Engine::last_trace`].
+    pub fn with_host_call_recording(mut self, record: bool) -> Self {
+        self.record_host_calls = record;
+        self
+    }
+
+    /// Record executions in Prometheus metrics.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn with_metrics(mut self, metrics: PrometheusMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Create a strict configuration for untrusted code.
     pub fn strict() -> Self {
         Self {
@@ -82,7 +140,8 @@ impl EngineConfig {
             capabilities: Capabilities::none(),
             sandbox: SandboxConfig::locked(),
             debug: false,
-            metadata: HashMap::new(),
+            verify_bytecode: true,
+            ..Self::default()
         }
     }
 
@@ -92,8 +151,7 @@ impl EngineConfig {
             limits: Limits::unlimited(),
             capabilities: Capabilities::all(),
             sandbox: SandboxConfig::permissive(),
-            debug: false,
-            metadata: HashMap::new(),
+            ..Self::default()
         }
     }
 }
@@ -101,11 +159,63 @@ impl EngineConfig {
 /// Host function signature.
 pub type HostFn = Arc<dyn Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync>;
 
+/// Declared argument count of a host function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFnSignature {
+    /// Minimum number of arguments.
+    pub min_args: usize,
+    /// Maximum number of arguments (`None` for variadic functions).
+    pub max_args: Option<usize>,
+}
+
+impl HostFnSignature {
+    /// A signature taking exactly `n` arguments.
+    pub fn fixed(n: usize) -> Self {
+        Self {
+            min_args: n,
+            max_args: Some(n),
+        }
+    }
+
+    /// A signature taking between `min` and `max` arguments.
+    pub fn range(min: usize, max: usize) -> Self {
+        Self {
+            min_args: min,
+            max_args: Some(max.max(min)),
+        }
+    }
+
+    /// A signature taking at least `min` arguments.
+    pub fn variadic(min: usize) -> Self {
+        Self {
+            min_args: min,
+            max_args: None,
+        }
+    }
+
+    /// Check if a call with `n` arguments is accepted.
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.min_args && self.max_args.map_or(true, |max| n <= max)
+    }
+}
+
+impl std::fmt::Display for HostFnSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max_args {
+            Some(max) if max == self.min_args => write!(f, "{}", max),
+            Some(max) => write!(f, "{}..={}", self.min_args, max),
+            None => write!(f, "{}..", self.min_args),
+        }
+    }
+}
+
 /// Host function registry.
 #[derive(Default, Clone)]
 pub struct HostRegistry {
     functions: HashMap<String, HostFn>,
     modules: HashMap<String, HashMap<String, HostFn>>,
+    signatures: HashMap<String, HostFnSignature>,
+    declarations: HashMap<String, HostFnDecl>,
 }
 
 impl HostRegistry {
@@ -120,7 +230,10 @@ impl HostRegistry {
         S: Into<String>,
         F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
     {
-        self.functions.insert(name.into(), Arc::new(f));
+        let name = name.into();
+        self.signatures.remove(&name);
+        self.declarations.remove(&name);
+        self.functions.insert(name, Arc::new(f));
     }
 
     /// Register a host function in a module namespace.
@@ -130,10 +243,125 @@ impl HostRegistry {
         N: Into<String>,
         F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
     {
+        let (module, name) = (module.into(), name.into());
+        let key = format!("{}.{}", module, name);
+        self.signatures.remove(&key);
+        self.declarations.remove(&key);
         self.modules
-            .entry(module.into())
+            .entry(module)
             .or_default()
-            .insert(name.into(), Arc::new(f));
+            .insert(name, Arc::new(f));
+    }
+
+    /// Register a global host function with a declared signature.
+    pub fn register_with_signature<S, F>(&mut self, name: S, signature: HostFnSignature, f: F)
+    where
+        S: Into<String>,
+        F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.register(name.clone(), f);
+        self.signatures.insert(name, signature);
+    }
+
+    /// Register a module host function with a declared signature.
+    pub fn register_module_with_signature<M, N, F>(
+        &mut self,
+        module: M,
+        name: N,
+        signature: HostFnSignature,
+        f: F,
+    ) where
+        M: Into<String>,
+        N: Into<String>,
+        F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
+    {
+        let (module, name) = (module.into(), name.into());
+        let key = format!("{}.{}", module, name);
+        self.register_module(module, name, f);
+        self.signatures.insert(key, signature);
+    }
+
+    /// Register a global host function with typed declaration metadata.
+    ///
+    /// The declaration's parameters also define its [`HostFnSignature`].
+    pub fn register_declared<S, F>(&mut self, name: S, decl: HostFnDecl, f: F)
+    where
+        S: Into<String>,
+        F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.register_with_signature(name.clone(), decl.signature(), f);
+        self.declarations.insert(name, decl);
+    }
+
+    /// Register a module host function with typed declaration metadata.
+    pub fn register_module_declared<M, N, F>(&mut self, module: M, name: N, decl: HostFnDecl, f: F)
+    where
+        M: Into<String>,
+        N: Into<String>,
+        F: Fn(&[Value], &ExecutionContext) -> Result<Value> + Send + Sync + 'static,
+    {
+        let (module, name) = (module.into(), name.into());
+        let key = format!("{}.{}", module, name);
+        self.register_module_with_signature(module, name, decl.signature(), f);
+        self.declarations.insert(key, decl);
+    }
+
+    /// Get the declaration of a global function.
+    pub fn declaration(&self, name: &str) -> Option<&HostFnDecl> {
+        self.declarations.get(name)
+    }
+
+    /// Get the declaration of a module function.
+    pub fn module_declaration(&self, module: &str, name: &str) -> Option<&HostFnDecl> {
+        self.declarations.get(&format!("{}.{}", module, name))
+    }
+
+    /// Emit a Fusabi declaration file covering every registered function.
+    ///
+    /// Functions registered without a [`HostFnDecl`] are declared with
+    /// untyped parameters derived from their signature, if any.
+    pub fn emit_declarations(&self) -> String {
+        let decl_for = |key: &str| {
+            self.declarations
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| decl::undeclared(self.signatures.get(key)))
+        };
+
+        let mut globals: Vec<(&str, HostFnDecl)> = self
+            .functions
+            .keys()
+            .map(|name| (name.as_str(), decl_for(name)))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut modules: Vec<(&str, Vec<(&str, HostFnDecl)>)> = self
+            .modules
+            .iter()
+            .map(|(module, funcs)| {
+                let mut funcs: Vec<(&str, HostFnDecl)> = funcs
+                    .keys()
+                    .map(|name| (name.as_str(), decl_for(&format!("{}.{}", module, name))))
+                    .collect();
+                funcs.sort_by(|a, b| a.0.cmp(b.0));
+                (module.as_str(), funcs)
+            })
+            .collect();
+        modules.sort_by(|a, b| a.0.cmp(b.0));
+
+        decl::render(&globals, &modules)
+    }
+
+    /// Get the declared signature of a global function.
+    pub fn signature(&self, name: &str) -> Option<&HostFnSignature> {
+        self.signatures.get(name)
+    }
+
+    /// Get the declared signature of a module function.
+    pub fn module_signature(&self, module: &str, name: &str) -> Option<&HostFnSignature> {
+        self.signatures.get(&format!("{}.{}", module, name))
     }
 
     /// Look up a global function.
@@ -156,9 +384,60 @@ impl HostRegistry {
         self.modules.keys()
     }
 
+    /// Names of every registered function, with module functions qualified
+    /// as `module.name`.
+    fn qualified_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        for (module, funcs) in &self.modules {
+            names.extend(funcs.keys().map(|name| format!("{}.{}", module, name)));
+        }
+        names
+    }
+
+    /// Call a host function by `name` or `module.name`.
+    ///
+    /// Scripts' calls to host functions come through here, as do calls made
+    /// with [`Engine::call_host`]. The arguments are checked against any
+    /// declared signature, and the call is recorded or served from a trace
+    /// when the execution is recording or replaying.
+    pub fn call(&self, name: &str, args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
+        if let Some(served) = ctx.trace.lock().as_mut().and_then(|t| t.enter(name, args)) {
+            return served;
+        }
+
+        let called = Instant::now();
+        let result = self.invoke(name, args, ctx);
+        if let Some(trace) = ctx.trace.lock().as_mut() {
+            trace.exit(name, args, &result, called);
+        }
+        result
+    }
+
+    fn invoke(&self, name: &str, args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
+        let function = match name.split_once('.') {
+            Some((module, function)) => self.get_module(module, function),
+            None => self.get(name),
+        }
+        .ok_or_else(|| Error::host_function(format!("unknown host function: {}", name)))?;
+
+        if let Some(signature) = self.signatures.get(name) {
+            if !signature.accepts(args.len()) {
+                return Err(Error::host_function(format!(
+                    "{} expects {} arguments, got {}",
+                    name,
+                    signature,
+                    args.len()
+                )));
+            }
+        }
+        function(args, ctx)
+    }
+
     /// Merge another registry into this one.
     pub fn merge(&mut self, other: HostRegistry) {
         self.functions.extend(other.functions);
+        self.signatures.extend(other.signatures);
+        self.declarations.extend(other.declarations);
         for (module, funcs) in other.modules {
             self.modules.entry(module).or_default().extend(funcs);
         }
@@ -174,8 +453,52 @@ impl std::fmt::Debug for HostRegistry {
     }
 }
 
+/// A script loaded into an engine ahead of every execution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prelude {
+    /// Fusabi source, compiled when loaded.
+    Source(String),
+    /// Compiled FZB bytecode.
+    Bytecode(Vec<u8>),
+}
+
+impl Prelude {
+    /// Get the prelude as bytecode, compiling source if needed.
+    pub fn to_bytecode(&self) -> Result<Vec<u8>> {
+        match self {
+            Prelude::Source(source) => {
+                Ok(compile_source(source, &CompileOptions::default())?.bytecode)
+            }
+            Prelude::Bytecode(bytecode) => Ok(bytecode.clone()),
+        }
+    }
+}
+
+impl From<&str> for Prelude {
+    fn from(source: &str) -> Self {
+        Prelude::Source(source.to_string())
+    }
+}
+
+impl From<String> for Prelude {
+    fn from(source: String) -> Self {
+        Prelude::Source(source)
+    }
+}
+
+impl From<&[u8]> for Prelude {
+    fn from(bytecode: &[u8]) -> Self {
+        Prelude::Bytecode(bytecode.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Prelude {
+    fn from(bytecode: Vec<u8>) -> Self {
+        Prelude::Bytecode(bytecode)
+    }
+}
+
 /// Execution context passed to host functions.
-#[derive(Debug)]
 pub struct ExecutionContext {
     /// Engine ID for tracking.
     pub engine_id: u64,
@@ -191,6 +514,14 @@ pub struct ExecutionContext {
     start_time: Instant,
     /// Whether execution has been cancelled.
     cancelled: std::sync::atomic::AtomicBool,
+    /// Host context of the current execution.
+    host: Mutex<Option<Arc<dyn HostContext>>>,
+    /// Capabilities the current execution is narrowed to.
+    restriction: Mutex<Option<Capabilities>>,
+    /// Clock and random state of the current deterministic execution.
+    determinism: Mutex<Option<Determinism>>,
+    /// Host call recording or replay of the current execution.
+    trace: Mutex<Option<TraceMode>>,
 }
 
 impl ExecutionContext {
@@ -209,17 +540,137 @@ impl ExecutionContext {
             custom: Mutex::new(HashMap::new()),
             start_time: Instant::now(),
             cancelled: std::sync::atomic::AtomicBool::new(false),
+            host: Mutex::new(None),
+            restriction: Mutex::new(None),
+            determinism: Mutex::new(None),
+            trace: Mutex::new(None),
+        }
+    }
+
+    /// Get the host context of the current execution, if any.
+    pub fn host_context(&self) -> Option<Arc<dyn HostContext>> {
+        self.host.lock().clone()
+    }
+
+    /// Attach the host context used by the current execution.
+    pub(crate) fn set_host_context(&self, host: Option<Arc<dyn HostContext>>) {
+        *self.host.lock() = host;
+    }
+
+    /// Log a script message through the host context.
+    ///
+    /// Host functions that print or log on behalf of a script should use
+    /// this. Without a host context the message goes to `tracing`.
+    pub fn log(&self, level: LogLevel, message: &str) {
+        match self.host_context() {
+            Some(host) => host.log(level, message),
+            None => DefaultHostContext.log(level, message),
         }
     }
 
-    /// Check if a capability is granted.
+    /// Record a metric through the host context, if any.
+    pub fn record_metric(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        if let Some(host) = self.host_context() {
+            host.record_metric(name, value, tags);
+        }
+    }
+
+    /// Stop at a safe point if the execution was cancelled or timed out.
+    ///
+    /// Cancellation comes from [`cancel`](Self::cancel) or the host
+    /// context's [`should_cancel`](HostContext::should_cancel).
+    pub fn checkpoint(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        self.check_timeout()
+    }
+
+    /// Check if a capability is granted to the current execution.
     pub fn has_capability(&self, cap: crate::Capability) -> bool {
         self.capabilities.has(cap)
+            && self
+                .restriction
+                .lock()
+                .as_ref()
+                .map_or(true, |r| r.has(cap))
     }
 
     /// Require a capability, returning an error if not granted.
     pub fn require_capability(&self, cap: crate::Capability) -> Result<()> {
-        self.capabilities.require(cap)
+        if self.has_capability(cap) {
+            Ok(())
+        } else {
+            Err(Error::capability_denied(cap.name()))
+        }
+    }
+
+    /// Get the capabilities granted to the current execution.
+    pub fn effective_capabilities(&self) -> Capabilities {
+        match self.restriction.lock().as_ref() {
+            Some(restriction) => self.capabilities.intersect(restriction),
+            None => self.capabilities.clone(),
+        }
+    }
+
+    /// Narrow the capabilities and sandbox of the current execution.
+    pub(crate) fn restrict(
+        &self,
+        capabilities: Option<Capabilities>,
+        sandbox: Option<SandboxConfig>,
+    ) {
+        *self.restriction.lock() = capabilities;
+        self.sandbox.restrict(sandbox);
+    }
+
+    /// Read the current time on behalf of the script.
+    ///
+    /// Requires [`Capability::TimeRead`](crate::Capability::TimeRead). In
+    /// deterministic mode this is the virtual clock.
+    pub fn now(&self) -> Result<SystemTime> {
+        self.require_capability(crate::Capability::TimeRead)?;
+        Ok(match self.determinism.lock().as_ref() {
+            Some(determinism) => determinism.clock.now(),
+            None => SystemTime::now(),
+        })
+    }
+
+    /// Draw a random number on behalf of the script.
+    ///
+    /// Requires [`Capability::Random`](crate::Capability::Random). In
+    /// deterministic mode the sequence is fixed by the execution's seed.
+    pub fn random_u64(&self) -> Result<u64> {
+        self.require_capability(crate::Capability::Random)?;
+        Ok(match self.determinism.lock().as_mut() {
+            Some(determinism) => determinism.next_u64(),
+            None => deterministic::random_u64(),
+        })
+    }
+
+    /// Draw a random float in `[0, 1)` on behalf of the script.
+    pub fn random_f64(&self) -> Result<f64> {
+        self.random_u64().map(deterministic::unit_f64)
+    }
+
+    /// Get the seed of the current execution, in deterministic mode.
+    pub fn seed(&self) -> Option<u64> {
+        self.determinism.lock().as_ref().map(|d| d.seed)
+    }
+
+    /// Set the clock and random state of the current execution.
+    pub(crate) fn set_determinism(&self, determinism: Option<Determinism>) -> Option<Determinism> {
+        std::mem::replace(&mut *self.determinism.lock(), determinism)
+    }
+
+    /// Start or stop host call recording or replay, returning the previous
+    /// mode.
+    pub(crate) fn set_trace(&self, trace: Option<TraceMode>) -> Option<TraceMode> {
+        std::mem::replace(&mut *self.trace.lock(), trace)
+    }
+
+    /// Get the number of instructions recorded by the current execution.
+    pub fn instructions_executed(&self) -> u64 {
+        self.limit_tracker.lock().instructions_executed()
     }
 
     /// Get the sandbox for permission checks.
@@ -268,9 +719,11 @@ impl ExecutionContext {
         self.start_time.elapsed()
     }
 
-    /// Check if execution has been cancelled.
+    /// Check if execution has been cancelled, by [`cancel`](Self::cancel)
+    /// or by the host context.
     pub fn is_cancelled(&self) -> bool {
         self.cancelled.load(std::sync::atomic::Ordering::Relaxed)
+            || self.host.lock().as_ref().is_some_and(|h| h.should_cancel())
     }
 
     /// Cancel execution.
@@ -298,6 +751,42 @@ impl ExecutionContext {
     }
 }
 
+impl std::fmt::Debug for ExecutionContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutionContext")
+            .field("engine_id", &self.engine_id)
+            .field("capabilities", &self.capabilities)
+            .field("limit_tracker", &self.limit_tracker)
+            .field("sandbox", &self.sandbox)
+            .field("custom", &self.custom)
+            .field("start_time", &self.start_time)
+            .field("cancelled", &self.cancelled)
+            .field("host", &self.host.lock().is_some())
+            .field("restriction", &self.restriction)
+            .field("determinism", &self.determinism)
+            .field("trace", &self.trace)
+            .finish()
+    }
+}
+
+/// Summary of an engine's most recent execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    /// Engine that ran the execution.
+    pub engine_id: u64,
+    /// Wall-clock time taken.
+    pub duration: Duration,
+    /// Instructions recorded against the limits.
+    pub instructions: u64,
+    /// [`Error::kind`] of the failure, if the execution failed.
+    pub error: Option<&'static str>,
+    /// Seed of the execution's random numbers, in deterministic mode.
+    pub seed: Option<u64>,
+    /// Virtual clock reading when the execution started, in deterministic
+    /// mode.
+    pub virtual_time: Option<SystemTime>,
+}
+
 /// A Fusabi execution engine.
 ///
 /// The engine provides a sandboxed environment for executing Fusabi scripts
@@ -305,11 +794,19 @@ impl ExecutionContext {
 pub struct Engine {
     id: u64,
     config: EngineConfig,
-    registry: HostRegistry,
-    context: ExecutionContext,
+    registry: Arc<HostRegistry>,
+    context: Arc<ExecutionContext>,
     /// Bytecode cache for compiled scripts.
     #[allow(dead_code)]
     bytecode_cache: Mutex<HashMap<String, Vec<u8>>>,
+    /// Compiled prelude scripts, in load order.
+    prelude: Vec<Arc<[u8]>>,
+    /// Host context used by executions that don't supply their own.
+    host_context: Option<Arc<dyn HostContext>>,
+    /// Report of the most recent execution.
+    last_report: Mutex<Option<ExecutionReport>>,
+    /// Host calls of the most recent recorded execution.
+    last_trace: Mutex<Option<Trace>>,
 }
 
 impl Engine {
@@ -319,19 +816,23 @@ impl Engine {
         let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
         let sandbox = Sandbox::new(config.sandbox.clone())?;
-        let context = ExecutionContext::new(
+        let context = Arc::new(ExecutionContext::new(
             id,
             config.capabilities.clone(),
             config.limits.clone(),
             sandbox,
-        );
+        ));
 
         Ok(Self {
             id,
             config,
-            registry: HostRegistry::new(),
+            registry: Arc::new(HostRegistry::new()),
             context,
             bytecode_cache: Mutex::new(HashMap::new()),
+            prelude: Vec::new(),
+            host_context: None,
+            last_report: Mutex::new(None),
+            last_trace: Mutex::new(None),
         })
     }
 
@@ -346,8 +847,16 @@ impl Engine {
     }
 
     /// Get mutable access to the host registry.
+    ///
+    /// If the registry is shared with other engines, it is copied first so
+    /// changes only affect this engine.
     pub fn registry_mut(&mut self) -> &mut HostRegistry {
-        &mut self.registry
+        Arc::make_mut(&mut self.registry)
+    }
+
+    /// Replace the host registry with a shared one.
+    pub fn set_registry(&mut self, registry: Arc<HostRegistry>) {
+        self.registry = registry;
     }
 
     /// Get the host registry.
@@ -355,41 +864,272 @@ impl Engine {
         &self.registry
     }
 
+    /// Attach a host context to every execution on this engine.
+    ///
+    /// Script log output and execution metrics go to it, and its
+    /// [`should_cancel`](HostContext::should_cancel) is polled at safe points.
+    pub fn set_host_context(&mut self, host: Arc<dyn HostContext>) {
+        self.host_context = Some(host);
+    }
+
+    /// Get the engine's host context.
+    pub fn host_context(&self) -> Option<&Arc<dyn HostContext>> {
+        self.host_context.as_ref()
+    }
+
+    /// Load a prelude script into the engine.
+    ///
+    /// The prelude runs once immediately, so a broken prelude fails here with
+    /// [`Error::Prelude`]. Its definitions are then visible to every later
+    /// execution on this engine.
+    ///
+    /// VM state does not outlive an execution, so the prelude is replayed
+    /// ahead of every script. The replay counts towards the execution's
+    /// timeout but not its instruction limit.
+    pub fn load_prelude(&mut self, prelude: &Prelude) -> Result<()> {
+        let bytecode = prelude
+            .to_bytecode()
+            .map_err(|e| Error::prelude(e.to_string()))?;
+
+        self.context.reset(self.config.limits.clone());
+        let mut chunks: Vec<&[u8]> = self.prelude.iter().map(|c| &c[..]).collect();
+        chunks.push(&bytecode);
+        self.run_chunks(&chunks, &[])
+            .map_err(|e| Error::prelude(e.to_string()))?;
+
+        self.prelude.push(bytecode.into());
+        Ok(())
+    }
+
+    /// Get the number of prelude scripts loaded.
+    pub fn prelude_len(&self) -> usize {
+        self.prelude.len()
+    }
+
     /// Get the execution context.
     pub fn context(&self) -> &ExecutionContext {
         &self.context
     }
 
+    /// Get the report of the most recent execution.
+    pub fn last_report(&self) -> Option<ExecutionReport> {
+        self.last_report.lock().clone()
+    }
+
+    /// Get the host calls of the most recent execution, when recording with
+    /// [`EngineConfig::with_host_call_recording`].
+    pub fn last_trace(&self) -> Option<Trace> {
+        self.last_trace.lock().clone()
+    }
+
+    /// Call a host function from the engine's registry in the current
+    /// execution context.
+    pub fn call_host(&self, name: &str, args: &[Value]) -> Result<Value> {
+        self.registry.call(name, args, &self.context)
+    }
+
+    /// Run `bytecode` again, serving the host calls recorded in `trace`
+    /// instead of calling the host functions.
+    ///
+    /// The execution reuses the trace's seed in deterministic mode. The
+    /// report carries the first point where the run diverged from the
+    /// recording: a different call or arguments, a call not recorded, calls
+    /// left unmade, or a different result.
+    pub fn replay(&self, bytecode: &[u8], trace: &Trace) -> ReplayReport {
+        let mut options = ExecOptions::new();
+        if let Some(seed) = trace.seed {
+            options = options.with_seed(seed);
+        }
+
+        self.context
+            .set_trace(Some(TraceMode::replay(Arc::new(trace.clone()))));
+        let result = self.execute_bytecode_with(bytecode, options);
+        let mode = self.context.set_trace(None);
+        match mode {
+            Some(mode) => mode.into_report(result),
+            None => ReplayReport {
+                result,
+                replayed: 0,
+                divergence: None,
+            },
+        }
+    }
+
     /// Execute a source string and return the result.
     pub fn execute(&self, source: &str) -> Result<Value> {
-        // Check for cancellation before starting (before reset clears it)
-        if self.context.is_cancelled() {
-            return Err(Error::Cancelled);
-        }
+        self.execute_with(source, ExecOptions::default())
+    }
 
-        self.context.reset(self.config.limits.clone());
+    /// Execute a source string with per-execution options.
+    ///
+    /// The options can only narrow the engine's limits, capabilities and
+    /// sandbox. Globals are bound ahead of the script and are also visible to
+    /// host functions through [`ExecutionContext::get_custom`].
+    pub fn execute_with(&self, source: &str, options: ExecOptions) -> Result<Value> {
+        self.observe(&options, || self.execute_source(source, &options))
+    }
 
-        // Simulate compilation and execution
-        // In a real implementation, this would call the actual Fusabi VM
-        self.simulate_execution(source)
+    /// Execute a source string with a host context for this execution only.
+    pub fn execute_with_host(&self, source: &str, host: Arc<dyn HostContext>) -> Result<Value> {
+        self.execute_with(source, ExecOptions::new().with_host_context(host))
+    }
+
+    fn execute_source(&self, source: &str, options: &ExecOptions) -> Result<Value> {
+        let globals = self.begin(options)?;
+
+        if self.prelude.is_empty() && globals.is_empty() {
+            if let Some(value) = simulate_literal(source) {
+                self.context.checkpoint()?;
+                self.context.record_instructions(source.len() as u64 * 10)?;
+                return Ok(value);
+            }
+        }
+
+        // Anything beyond a literal form, and any prelude definitions or
+        // globals, only exist in the VM, so run there.
+        let compiled = compile_source(source, &CompileOptions::default())?;
+        self.run_bytecode(&globals, &[&compiled.bytecode])
     }
 
     /// Execute compiled bytecode.
     pub fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
+        self.execute_bytecode_with(bytecode, ExecOptions::default())
+    }
+
+    /// Execute compiled bytecode with per-execution options.
+    pub fn execute_bytecode_with(&self, bytecode: &[u8], options: ExecOptions) -> Result<Value> {
+        self.observe(&options, || self.execute_chunk(bytecode, &options))
+    }
+
+    /// Execute compiled bytecode with a host context for this execution only.
+    pub fn execute_bytecode_with_host(
+        &self,
+        bytecode: &[u8],
+        host: Arc<dyn HostContext>,
+    ) -> Result<Value> {
+        self.execute_bytecode_with(bytecode, ExecOptions::new().with_host_context(host))
+    }
+
+    fn execute_chunk(&self, bytecode: &[u8], options: &ExecOptions) -> Result<Value> {
+        let globals = self.begin(options)?;
+        self.check_bytecode(bytecode)?;
+        self.run_bytecode(&globals, &[bytecode])
+    }
+
+    /// Validate a bytecode header, and verify it if the engine requires it.
+    fn check_bytecode(&self, bytecode: &[u8]) -> Result<()> {
+        // The Fusabi VM bytecode container starts with the `FZB\x01` magic
+        // emitted by `fusabi_vm::serialize_chunk`.
+        if bytecode.len() < 5 || &bytecode[0..4] != fusabi_vm::FZB_MAGIC {
+            return Err(Error::invalid_bytecode("invalid bytecode header"));
+        }
+
+        if self.config.verify_bytecode {
+            verify_bytecode(bytecode)?;
+        }
+        Ok(())
+    }
+
+    /// Execute a script bundle.
+    ///
+    /// The bundle's required capabilities are checked against the engine
+    /// configuration, then the modules reachable from the entrypoint run in
+    /// import order on one VM, so the entrypoint sees the definitions of the
+    /// modules it imports. Returns the value produced by the entrypoint.
+    pub fn execute_bundle(&self, bundle: &Bundle) -> Result<Value> {
+        let options = ExecOptions::default();
+        self.observe(&options, || {
+            bundle.check_capabilities(&self.config.capabilities)?;
+
+            let globals = self.begin(&options)?;
+            let modules = bundle.load_order()?;
+            for module in &modules {
+                self.check_bytecode(module.bytecode())?;
+            }
+            let scripts: Vec<&[u8]> = modules.iter().map(|module| module.bytecode()).collect();
+            self.run_bytecode(&globals, &scripts)
+        })
+    }
+
+    /// Reset the context for a new execution and compile its globals.
+    fn begin(&self, options: &ExecOptions) -> Result<Vec<Vec<u8>>> {
         // Check for cancellation before starting (before reset clears it)
         if self.context.is_cancelled() {
             return Err(Error::Cancelled);
         }
 
-        self.context.reset(self.config.limits.clone());
+        let limits = match &options.limits {
+            Some(limits) => self.config.limits.narrow(limits),
+            None => self.config.limits.clone(),
+        };
+        self.context.reset(limits);
 
-        // Validate bytecode header. The Fusabi VM bytecode container starts with
-        // the `FZB\x01` magic emitted by `fusabi_vm::serialize_chunk`.
-        if bytecode.len() < 5 || &bytecode[0..4] != fusabi_vm::FZB_MAGIC {
-            return Err(Error::invalid_bytecode("invalid bytecode header"));
+        for (name, value) in &options.globals {
+            self.context.set_custom(name.clone(), value.clone());
+        }
+        options
+            .global_bindings()?
+            .iter()
+            .map(|binding| Ok(compile_source(binding, &CompileOptions::default())?.bytecode))
+            .collect()
+    }
+
+    /// Run one execution under its options, recording it in the metrics.
+    fn observe(&self, options: &ExecOptions, f: impl FnOnce() -> Result<Value>) -> Result<Value> {
+        self.context.set_host_context(
+            options
+                .host_context
+                .clone()
+                .or_else(|| self.host_context.clone()),
+        );
+        self.context
+            .restrict(options.capabilities.clone(), options.sandbox.clone());
+        self.context
+            .set_determinism(self.config.deterministic.as_ref().map(|config| {
+                let seed = options
+                    .seed
+                    .or(config.seed)
+                    .unwrap_or_else(deterministic::random_u64);
+                Determinism::new(config.clock.clone(), seed)
+            }));
+        let recording = self.config.record_host_calls && self.context.trace.lock().is_none();
+        if recording {
+            self.context.set_trace(Some(TraceMode::record()));
         }
 
-        self.run_bytecode(bytecode)
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        self.record_execution(elapsed, result.as_ref().err());
+
+        let determinism = self.context.set_determinism(None);
+        if recording {
+            *self.last_trace.lock() = self.context.set_trace(None).and_then(|trace| {
+                trace.into_trace(&result, determinism.as_ref().map(|d| d.seed), elapsed)
+            });
+        }
+        *self.last_report.lock() = Some(ExecutionReport {
+            engine_id: self.id,
+            duration: elapsed,
+            instructions: self.context.instructions_executed(),
+            error: result.as_ref().err().map(Error::kind),
+            seed: determinism.as_ref().map(|d| d.seed),
+            virtual_time: determinism.as_ref().map(|d| d.started_at),
+        });
+        self.context.restrict(None, None);
+        self.context.set_host_context(self.host_context.clone());
+        result
+    }
+
+    /// Report a finished execution to the metrics and host context.
+    pub(crate) fn record_execution(&self, elapsed: Duration, error: Option<&Error>) {
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = &self.config.metrics {
+            metrics.record_execution(elapsed, error);
+        }
+        if let Some(host) = self.context.host_context() {
+            record_execution_metrics(host.as_ref(), self.id, elapsed, error);
+        }
     }
 
     /// Cancel any ongoing execution.
@@ -402,70 +1142,324 @@ impl Engine {
         !self.context.is_cancelled()
     }
 
-    // Internal simulation methods - would be replaced with actual VM calls
+    /// Execute compiled scripts on the real Fusabi VM, after the prelude and
+    /// globals, and convert the last value produced into a host [`Value`].
+    fn run_bytecode(&self, globals: &[Vec<u8>], scripts: &[&[u8]]) -> Result<Value> {
+        let mut setup: Vec<&[u8]> = self.prelude.iter().map(|c| &c[..]).collect();
+        setup.extend(globals.iter().map(Vec::as_slice));
+        self.run_chunks(&setup, scripts)
+    }
 
-    fn simulate_execution(&self, source: &str) -> Result<Value> {
-        // Check timeout periodically during "execution"
-        self.context.check_timeout()?;
+    /// Run `setup` then `scripts` in order on one VM and return the last
+    /// chunk's value.
+    ///
+    /// VM state cannot follow a pooled engine between threads, so the prelude
+    /// and globals are replayed as `setup` ahead of each script to bring
+    /// their definitions into scope. Only `scripts` count towards the
+    /// instruction limit.
+    fn run_chunks(&self, setup: &[&[u8]], scripts: &[&[u8]]) -> Result<Value> {
+        self.context.checkpoint()?;
+
+        let failure = HostFailure::default();
+        let mut vm = self.new_vm(&failure);
+        let mut vm_value = fusabi_vm::Value::Unit;
+        for bytecode in setup.iter().chain(scripts) {
+            // Each chunk boundary is a safe point.
+            self.context.checkpoint()?;
+            let mut chunk = fusabi_vm::deserialize_chunk(bytecode)
+                .map_err(|e| Error::invalid_bytecode(e.to_string()))?;
+            safepoint::instrument(&mut chunk)?;
+            vm_value = vm.execute(chunk).map_err(|e| {
+                // A failing host function keeps its own error.
+                failure
+                    .lock()
+                    .take()
+                    .unwrap_or_else(|| Error::runtime(e.to_string()))
+            })?;
+        }
 
-        // Record some instructions
-        self.context.record_instructions(source.len() as u64 * 10)?;
+        // Account for the work performed. We don't have an exact instruction
+        // count from the VM here, so record a conservative figure proportional
+        // to the bytecode size.
+        if !scripts.is_empty() {
+            let size: usize = scripts.iter().map(|c| c.len()).sum();
+            self.context.record_instructions((size as u64).max(1))?;
+        }
 
-        // Simple expression evaluation simulation
-        let trimmed = source.trim();
+        Ok(vm_value_to_host(vm_value))
+    }
 
-        // Handle simple numeric expressions
-        if let Ok(n) = trimmed.parse::<i64>() {
-            return Ok(Value::Int(n));
-        }
+    /// Create a VM with the standard library and the engine's host functions.
+    ///
+    /// Every registered function becomes a VM native under its name; module
+    /// functions are fields of a record named after the module, so both
+    /// `log "hi"` and `math.add 1 2` call into the registry. The VM also gets
+    /// the safe point native, and with a host context `print` and `printfn`
+    /// write to its log instead of stdout.
+    fn new_vm(&self, failure: &HostFailure) -> fusabi_vm::Vm {
+        let mut vm = fusabi_vm::Vm::new();
+        fusabi_vm::stdlib::register_stdlib(&mut vm);
+
+        let context = Arc::clone(&self.context);
+        let stash = Arc::clone(failure);
+        self.install_native(&mut vm, SAFEPOINT, move |_vm, _args| {
+            context.checkpoint().map_err(|e| {
+                let message = e.to_string();
+                *stash.lock() = Some(e);
+                fusabi_vm::VmError::Runtime(message)
+            })?;
+            Ok(fusabi_vm::Value::Unit)
+        });
 
-        if let Ok(f) = trimmed.parse::<f64>() {
-            return Ok(Value::Float(f));
+        if self.context.host_context().is_some() {
+            for name in ["print", "printfn"] {
+                let context = Arc::clone(&self.context);
+                self.install_native(&mut vm, name, move |_vm, args| {
+                    let message = match args.first().cloned().map(vm_value_to_host) {
+                        Some(Value::String(s)) => s,
+                        Some(value) => value.to_string(),
+                        None => String::new(),
+                    };
+                    context.log(LogLevel::Info, &message);
+                    Ok(fusabi_vm::Value::Unit)
+                });
+            }
         }
 
-        // Handle simple string literals
-        if trimmed.starts_with('"') && trimmed.ends_with('"') && trimmed.len() > 1 {
-            return Ok(Value::String(trimmed[1..trimmed.len() - 1].to_string()));
+        for (name, native) in self.script_natives(failure) {
+            bind_native(&mut vm, name, 0);
+            vm.host_registry
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .register(name, native);
         }
 
-        // Handle simple addition
-        if let Some(pos) = trimmed.find('+') {
-            let left = trimmed[..pos].trim();
-            let right = trimmed[pos + 1..].trim();
-            if let (Ok(l), Ok(r)) = (left.parse::<i64>(), right.parse::<i64>()) {
-                return Ok(Value::Int(l + r));
-            }
+        for name in self.registry.qualified_names() {
+            let arity = self
+                .registry
+                .signatures
+                .get(&name)
+                .map_or(0, |signature| signature.min_args);
+            bind_native(&mut vm, &name, u8::try_from(arity).unwrap_or(u8::MAX));
+
+            let native = self.host_native(name.clone(), Arc::clone(failure));
+            vm.host_registry
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .register(&name, native);
         }
+        vm
+    }
 
-        // Handle boolean literals
-        match trimmed {
-            "true" => return Ok(Value::Bool(true)),
-            "false" => return Ok(Value::Bool(false)),
-            "null" | "nil" => return Ok(Value::Null),
-            _ => {}
-        }
+    /// Natives giving scripts the execution's clock and random numbers,
+    /// named as in [`SCRIPT_NATIVES`].
+    ///
+    /// They read through [`ExecutionContext::now`] and
+    /// [`ExecutionContext::random_u64`], so in deterministic mode scripts see
+    /// the virtual clock and the seeded generator.
+    fn script_natives(&self, failure: &HostFailure) -> [(&'static str, VmNative); 3] {
+        let native = |read: fn(&ExecutionContext) -> Result<fusabi_vm::Value>| -> VmNative {
+            let context = Arc::clone(&self.context);
+            let failure = Arc::clone(failure);
+            Box::new(move |_vm, _args| {
+                read(&context).map_err(|e| {
+                    let message = e.to_string();
+                    *failure.lock() = Some(e);
+                    fusabi_vm::VmError::Runtime(message)
+                })
+            })
+        };
+        [
+            (
+                SCRIPT_NATIVES[0],
+                native(|context| {
+                    let since_epoch = context
+                        .now()?
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default();
+                    let millis = i64::try_from(since_epoch.as_millis()).unwrap_or(i64::MAX);
+                    Ok(fusabi_vm::Value::Int(millis))
+                }),
+            ),
+            (
+                SCRIPT_NATIVES[1],
+                native(|context| Ok(fusabi_vm::Value::Int((context.random_u64()? >> 1) as i64))),
+            ),
+            (
+                SCRIPT_NATIVES[2],
+                native(|context| Ok(fusabi_vm::Value::Float(context.random_f64()?))),
+            ),
+        ]
+    }
 
-        // Default: return null for unrecognized input
-        Ok(Value::Null)
+    /// Register a native with the VM and bind it as a global of arity 0.
+    fn install_native(
+        &self,
+        vm: &mut fusabi_vm::Vm,
+        name: &str,
+        native: impl Fn(
+                &mut fusabi_vm::Vm,
+                &[fusabi_vm::Value],
+            ) -> std::result::Result<fusabi_vm::Value, fusabi_vm::VmError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        vm.host_registry
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .register(name, native);
+        vm.globals.insert(
+            name.to_string(),
+            fusabi_vm::Value::NativeFn {
+                name: name.to_string(),
+                arity: 0,
+                args: Vec::new(),
+            },
+        );
     }
 
-    /// Execute compiled bytecode on the real Fusabi VM and convert the produced
-    /// value into a host [`Value`].
-    fn run_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
-        self.context.check_timeout()?;
+    /// Wrap a registered host function as a VM native calling it through
+    /// [`HostRegistry::call`], so script calls are recorded and replayed.
+    ///
+    /// Each call is a safe point. A failure is stashed in `failure` so the
+    /// execution reports the host function's own error rather than the VM's.
+    fn host_native(&self, name: String, failure: HostFailure) -> VmNative {
+        let registry = Arc::clone(&self.registry);
+        let context = Arc::clone(&self.context);
+        // `f ()` passes unit to a function declared without parameters.
+        let nullary = registry
+            .signatures
+            .get(&name)
+            .is_some_and(|signature| signature.max_args == Some(0));
+
+        Box::new(move |_vm, args| {
+            let args: Vec<Value> = match args {
+                [fusabi_vm::Value::Unit] if nullary => Vec::new(),
+                _ => args.iter().cloned().map(vm_value_to_host).collect(),
+            };
+            context
+                .checkpoint()
+                .and_then(|()| registry.call(&name, &args, &context))
+                .and_then(host_value_to_vm)
+                .map_err(|e| {
+                    let message = e.to_string();
+                    *failure.lock() = Some(e);
+                    fusabi_vm::VmError::Runtime(message)
+                })
+        })
+    }
+}
 
-        // Deserialize the chunk and execute it on the VM. `Vm::execute_bytecode`
-        // registers the standard library and runs the top-level chunk.
-        let vm_value =
-            fusabi_vm::Vm::execute_bytecode(bytecode).map_err(|e| Error::runtime(e.to_string()))?;
+/// Evaluate the literal forms the engine has always answered without the
+/// VM: numbers, string literals, the sum of two integers, booleans and
+/// `null`/`nil`. Returns `None` for anything else.
+fn simulate_literal(source: &str) -> Option<Value> {
+    let trimmed = source.trim();
 
-        // Account for the work performed. We don't have an exact instruction
-        // count from the VM here, so record a conservative figure proportional
-        // to the bytecode size.
-        self.context
-            .record_instructions((bytecode.len() as u64).max(1))?;
+    if let Ok(n) = trimmed.parse::<i64>() {
+        return Some(Value::Int(n));
+    }
 
-        Ok(vm_value_to_host(vm_value))
+    if let Ok(f) = trimmed.parse::<f64>() {
+        return Some(Value::Float(f));
+    }
+
+    if trimmed.starts_with('"') && trimmed.ends_with('"') && trimmed.len() > 1 {
+        let inner = &trimmed[1..trimmed.len() - 1];
+        if !inner.contains('"') {
+            return Some(Value::String(inner.to_string()));
+        }
+    }
+
+    if let Some(pos) = trimmed.find('+') {
+        let left = trimmed[..pos].trim();
+        let right = trimmed[pos + 1..].trim();
+        if let (Ok(l), Ok(r)) = (left.parse::<i64>(), right.parse::<i64>()) {
+            return l.checked_add(r).map(Value::Int);
+        }
+    }
+
+    match trimmed {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        "null" | "nil" => Some(Value::Null),
+        _ => None,
+    }
+}
+
+/// Natives every VM defines for scripts: `Time.now ()` returns milliseconds
+/// since the Unix epoch, `Random.next ()` a non-negative integer and
+/// `Random.float ()` a float in `[0, 1)`.
+pub(crate) const SCRIPT_NATIVES: [&str; 3] = ["Time.now", "Random.next", "Random.float"];
+
+/// Bind a native as a global, or as a field of its module's record global
+/// when `name` is qualified.
+fn bind_native(vm: &mut fusabi_vm::Vm, name: &str, arity: u8) {
+    let native = fusabi_vm::Value::NativeFn {
+        name: name.to_string(),
+        arity,
+        args: Vec::new(),
+    };
+    match name.split_once('.') {
+        Some((module, function)) => {
+            let record = vm.globals.entry(module.to_string()).or_insert_with(|| {
+                fusabi_vm::Value::Record(Arc::new(std::sync::Mutex::new(HashMap::new())))
+            });
+            if let fusabi_vm::Value::Record(fields) = record {
+                fields
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(function.to_string(), native);
+            }
+        }
+        None => {
+            vm.globals.insert(name.to_string(), native);
+        }
+    }
+}
+
+/// Error raised by a host function during the current VM run.
+type HostFailure = Arc<Mutex<Option<Error>>>;
+
+/// A host function installed in a VM.
+type VmNative = Box<
+    dyn Fn(
+            &mut fusabi_vm::Vm,
+            &[fusabi_vm::Value],
+        ) -> std::result::Result<fusabi_vm::Value, fusabi_vm::VmError>
+        + Send
+        + Sync,
+>;
+
+/// Report an execution to a host context's metrics.
+fn record_execution_metrics(
+    host: &dyn HostContext,
+    engine_id: u64,
+    elapsed: Duration,
+    error: Option<&Error>,
+) {
+    let engine = engine_id.to_string();
+    let tags = [("engine", engine.as_str())];
+    host.counter("fusabi_executions_total", 1, &tags);
+    host.histogram(
+        "fusabi_execution_duration_seconds",
+        elapsed.as_secs_f64(),
+        &tags,
+    );
+    if let Some(error) = error {
+        host.counter(
+            "fusabi_execution_errors_total",
+            1,
+            &[("engine", engine.as_str()), ("kind", error.kind())],
+        );
+        if let Error::LimitViolation(violation) = error {
+            host.counter(
+                "fusabi_limit_violations_total",
+                1,
+                &[("engine", engine.as_str()), ("kind", violation.kind())],
+            );
+        }
     }
 }
 
@@ -535,12 +1529,54 @@ fn vm_value_to_host(value: fusabi_vm::Value) -> Value {
     }
 }
 
+/// Convert a host [`Value`] into a [`fusabi_vm::Value`] for a script.
+///
+/// Lists become cons lists, maps become records, and bytes become arrays of
+/// integers. Host function references cannot be passed to scripts.
+fn host_value_to_vm(value: Value) -> Result<fusabi_vm::Value> {
+    use fusabi_vm::Value as VmValue;
+
+    Ok(match value {
+        Value::Null => VmValue::Unit,
+        Value::Bool(b) => VmValue::Bool(b),
+        Value::Int(n) => VmValue::Int(n),
+        Value::Float(f) => VmValue::Float(f),
+        Value::String(s) | Value::Error(s) => VmValue::Str(s),
+        Value::List(items) => {
+            let mut list = VmValue::Nil;
+            for item in items.into_iter().rev() {
+                list = VmValue::Cons {
+                    head: Box::new(host_value_to_vm(item)?),
+                    tail: Box::new(list),
+                };
+            }
+            list
+        }
+        Value::Map(map) => {
+            let fields = map
+                .into_iter()
+                .map(|(k, v)| Ok((k, host_value_to_vm(v)?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            VmValue::Record(Arc::new(std::sync::Mutex::new(fields)))
+        }
+        Value::Bytes(bytes) => VmValue::Array(Arc::new(std::sync::Mutex::new(
+            bytes.into_iter().map(|b| VmValue::Int(b.into())).collect(),
+        ))),
+        Value::Function(_) => {
+            return Err(Error::host_function(
+                "host function references cannot be passed to scripts",
+            ))
+        }
+    })
+}
+
 impl std::fmt::Debug for Engine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Engine")
             .field("id", &self.id)
             .field("config", &self.config)
             .field("registry", &self.registry)
+            .field("host_context", &self.host_context.is_some())
             .finish()
     }
 }
@@ -550,6 +1586,49 @@ impl std::fmt::Debug for Engine {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_prelude_definitions_visible() {
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine
+            .load_prelude(&Prelude::from("let base = 40"))
+            .unwrap();
+        assert_eq!(engine.prelude_len(), 1);
+
+        let compiled = compile_source("base + 2", &CompileOptions::default()).unwrap();
+        assert_eq!(
+            engine.execute_bytecode(&compiled.bytecode).unwrap(),
+            Value::Int(42)
+        );
+        assert_eq!(engine.execute("base + 1").unwrap(), Value::Int(41));
+    }
+
+    #[test]
+    fn test_prelude_excluded_from_instruction_limit() {
+        let script = compile_source("base + 2", &CompileOptions::default()).unwrap();
+        let limits = Limits::default().with_max_instructions(script.bytecode.len() as u64);
+        let mut engine = Engine::new(EngineConfig::new().with_limits(limits)).unwrap();
+
+        let helpers: String = (0..20)
+            .map(|i| format!("let helper{} x = x + {}\n", i, i))
+            .collect();
+        engine
+            .load_prelude(&Prelude::from(format!("{}let base = 40", helpers).as_str()))
+            .unwrap();
+        assert_eq!(
+            engine.execute_bytecode(&script.bytecode).unwrap(),
+            Value::Int(42)
+        );
+    }
+
+    #[test]
+    fn test_failed_prelude_not_loaded() {
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+
+        let result = engine.load_prelude(&Prelude::from("let base = missing + 40"));
+        assert!(matches!(result, Err(Error::Prelude(_))));
+        assert_eq!(engine.prelude_len(), 0);
+    }
+
     #[test]
     fn test_engine_creation() {
         let engine = Engine::new(EngineConfig::default()).unwrap();
@@ -589,6 +1668,20 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_strict_engine_verifies_bytecode() {
+        use crate::compile::{compile_source, CompileOptions};
+
+        let engine = Engine::new(EngineConfig::strict()).unwrap();
+        assert!(engine.config().verify_bytecode);
+
+        let compiled = compile_source("1 + 2", &CompileOptions::default()).unwrap();
+        assert_eq!(
+            engine.execute_bytecode(&compiled.bytecode).unwrap(),
+            Value::Int(3)
+        );
+    }
+
     #[test]
     fn test_engine_execute_numbers() {
         let engine = Engine::new(EngineConfig::default()).unwrap();
@@ -658,6 +1751,268 @@ mod tests {
         assert!(registry.get("nonexistent").is_none());
     }
 
+    #[test]
+    fn test_host_registry_call() {
+        let mut registry = HostRegistry::new();
+        registry.register_with_signature("echo", HostFnSignature::fixed(1), |args, _ctx| {
+            Ok(args[0].clone())
+        });
+        registry.register_module("math", "neg", |args, _ctx| {
+            Ok(Value::Int(-args[0].as_int().unwrap_or(0)))
+        });
+        let engine = Engine::new(EngineConfig::default()).unwrap();
+        let ctx = engine.context();
+
+        assert_eq!(
+            registry.call("echo", &[Value::Int(1)], ctx).unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(
+            registry.call("math.neg", &[Value::Int(2)], ctx).unwrap(),
+            Value::Int(-2)
+        );
+        assert!(matches!(
+            registry.call("echo", &[], ctx),
+            Err(Error::HostFunction(_))
+        ));
+        assert!(matches!(
+            registry.call("missing", &[], ctx),
+            Err(Error::HostFunction(_))
+        ));
+    }
+
+    #[test]
+    fn test_record_and_replay_host_calls() {
+        use crate::compile::{compile_source, CompileOptions};
+        use crate::trace::Divergence;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut engine =
+            Engine::new(EngineConfig::default().with_host_call_recording(true)).unwrap();
+        engine.registry_mut().register("next", move |_args, _ctx| {
+            Ok(Value::Int(counter.fetch_add(1, Ordering::SeqCst) as i64))
+        });
+
+        let compiled = compile_source("1 + 2", &CompileOptions::default()).unwrap();
+        engine.execute_bytecode(&compiled.bytecode).unwrap();
+        let mut trace = engine.last_trace().unwrap();
+        assert!(trace.calls.is_empty());
+
+        // Host calls made while recording are logged in order.
+        engine.context().set_trace(Some(TraceMode::record()));
+        engine.call_host("next", &[]).unwrap();
+        engine.call_host("next", &[Value::Int(9)]).unwrap();
+        trace.calls = engine
+            .context()
+            .set_trace(None)
+            .and_then(|t| t.into_trace(&Ok(Value::Null), None, Duration::ZERO))
+            .unwrap()
+            .calls;
+        assert_eq!(trace.calls.len(), 2);
+        assert_eq!(trace.calls[1].args, vec![Value::Int(9)]);
+
+        // Replayed calls are served from the trace, not the host.
+        engine
+            .context()
+            .set_trace(Some(TraceMode::replay(Arc::new(trace.clone()))));
+        assert_eq!(engine.call_host("next", &[]).unwrap(), Value::Int(0));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        engine.context().set_trace(None);
+
+        // The script makes no host calls, so the replay stops short.
+        let report = engine.replay(&compiled.bytecode, &trace);
+        assert_eq!(report.result.unwrap(), Value::Int(3));
+        assert_eq!(
+            report.divergence,
+            Some(Divergence::MissingCalls {
+                index: 0,
+                remaining: 2
+            })
+        );
+
+        trace.calls.clear();
+        assert!(engine.replay(&compiled.bytecode, &trace).is_faithful());
+        let other = compile_source("1 + 3", &CompileOptions::default()).unwrap();
+        let report = engine.replay(&other.bytecode, &trace);
+        assert!(matches!(report.divergence, Some(Divergence::Result { .. })));
+    }
+
+    #[test]
+    fn test_script_host_calls_are_recorded_and_replayed() {
+        use crate::compile::{compile_source, CompileOptions};
+        use crate::trace::{CallOutcome, Divergence};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut engine =
+            Engine::new(EngineConfig::default().with_host_call_recording(true)).unwrap();
+        engine.registry_mut().register("next", move |args, _ctx| {
+            let step = args[0].as_int().unwrap_or(0);
+            Ok(Value::Int(
+                counter.fetch_add(1, Ordering::SeqCst) as i64 + step,
+            ))
+        });
+
+        let compiled = compile_source("next 10 + next 20", &CompileOptions::default()).unwrap();
+        assert_eq!(
+            engine.execute_bytecode(&compiled.bytecode).unwrap(),
+            Value::Int(31)
+        );
+        let mut trace = engine.last_trace().unwrap();
+        assert_eq!(trace.calls.len(), 2);
+        assert_eq!(trace.calls[0].function, "next");
+        assert_eq!(trace.calls[1].args, vec![Value::Int(20)]);
+        assert_eq!(
+            trace.calls[1].outcome,
+            CallOutcome::Returned(Value::Int(21))
+        );
+
+        // The recorded results are served without calling the host again.
+        let report = engine.replay(&compiled.bytecode, &trace);
+        assert!(report.is_faithful(), "{:?}", report.divergence);
+        assert_eq!(report.replayed, 2);
+        assert_eq!(report.result.unwrap(), Value::Int(31));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        trace.calls[0].outcome = CallOutcome::Returned(Value::Int(0));
+        let report = engine.replay(&compiled.bytecode, &trace);
+        assert_eq!(report.result.unwrap(), Value::Int(21));
+        assert!(matches!(report.divergence, Some(Divergence::Result { .. })));
+
+        let other = compile_source("next 10 + next 30", &CompileOptions::default()).unwrap();
+        let report = engine.replay(&other.bytecode, &trace);
+        assert!(matches!(report.result, Err(Error::ReplayDiverged(_))));
+        assert!(matches!(
+            report.divergence,
+            Some(Divergence::Call { index: 1, .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_replayed_host_error_is_faithful() {
+        use crate::compile::{compile_source, CompileOptions};
+
+        let mut engine =
+            Engine::new(EngineConfig::default().with_host_call_recording(true)).unwrap();
+        engine
+            .registry_mut()
+            .register("fail", |_args, _ctx| Err(Error::host_function("boom")));
+
+        let compiled = compile_source("fail 1", &CompileOptions::default()).unwrap();
+        let recorded = engine.execute_bytecode(&compiled.bytecode).unwrap_err();
+        assert_eq!(recorded.to_string(), "host function error: boom");
+        let trace = engine.last_trace().unwrap();
+
+        let report = engine.replay(&compiled.bytecode, &trace);
+        assert!(report.is_faithful(), "{:?}", report.divergence);
+        assert_eq!(
+            report.result.unwrap_err().to_string(),
+            "host function error: boom"
+        );
+    }
+
+    #[test]
+    fn test_emit_declarations() {
+        use crate::value::ValueType;
+
+        let mut registry = HostRegistry::new();
+        registry.register_declared(
+            "log",
+            HostFnDecl::new()
+                .with_param("message", ValueType::String)
+                .with_returns(ValueType::Null)
+                .with_doc("Write a message to the host log."),
+            |_args, _ctx| Ok(Value::Null),
+        );
+        registry.register_module_declared(
+            "math",
+            "add",
+            HostFnDecl::new()
+                .with_param("a", ValueType::Int)
+                .with_param("b", ValueType::Int)
+                .with_returns(ValueType::Int),
+            |_args, _ctx| Ok(Value::Null),
+        );
+        registry.register("raw", |_args, _ctx| Ok(Value::Null));
+
+        assert_eq!(registry.signature("log"), Some(&HostFnSignature::fixed(1)));
+
+        let out = registry.emit_declarations();
+        assert!(
+            out.contains("/// Write a message to the host log.\nval log : message: string -> unit")
+        );
+        assert!(out.contains("val raw : args: obj list -> obj"));
+        assert!(out.contains("module math =\n    val add : a: int -> b: int -> int"));
+        assert!(out.find("val log").unwrap() < out.find("val raw").unwrap());
+    }
+
+    #[test]
+    fn test_shared_registry_copy_on_write() {
+        let mut shared = HostRegistry::new();
+        shared.register("shared_fn", |_args, _ctx| Ok(Value::Null));
+        let shared = Arc::new(shared);
+
+        let mut a = Engine::new(EngineConfig::default()).unwrap();
+        let mut b = Engine::new(EngineConfig::default()).unwrap();
+        a.set_registry(Arc::clone(&shared));
+        b.set_registry(Arc::clone(&shared));
+
+        a.registry_mut()
+            .register("local_fn", |_args, _ctx| Ok(Value::Null));
+
+        assert!(a.registry().get("shared_fn").is_some());
+        assert!(a.registry().get("local_fn").is_some());
+        assert!(b.registry().get("local_fn").is_none());
+        assert!(shared.get("local_fn").is_none());
+    }
+
+    #[test]
+    fn test_scripts_call_host_functions() {
+        use crate::compile::{compile_source, CompileOptions};
+
+        let mut shared = HostRegistry::new();
+        shared.register_with_signature("add", HostFnSignature::fixed(2), |args, _ctx| {
+            Ok(Value::Int(
+                args[0].as_int().unwrap() + args[1].as_int().unwrap(),
+            ))
+        });
+        shared.register_module_with_signature(
+            "math",
+            "mul",
+            HostFnSignature::fixed(2),
+            |args, _ctx| {
+                Ok(Value::Int(
+                    args[0].as_int().unwrap() * args[1].as_int().unwrap(),
+                ))
+            },
+        );
+        shared.register_with_signature("answer", HostFnSignature::fixed(0), |_args, _ctx| {
+            Ok(Value::Int(42))
+        });
+        shared.register("fail", |_args, _ctx| {
+            Err(Error::capability_denied("network"))
+        });
+
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_registry(Arc::new(shared));
+
+        let run = |source: &str| {
+            let compiled = compile_source(source, &CompileOptions::default()).unwrap();
+            engine.execute_bytecode(&compiled.bytecode)
+        };
+        assert_eq!(run("add 2 3").unwrap(), Value::Int(5));
+        assert_eq!(run("math.mul 4 5").unwrap(), Value::Int(20));
+        assert_eq!(run("answer () + 1").unwrap(), Value::Int(43));
+
+        // The host function's own error reaches the caller.
+        let err = run("fail 1").unwrap_err();
+        assert_eq!(err.kind(), "capability_denied");
+    }
+
     #[test]
     fn test_execution_context_capabilities() {
         use crate::Capability;
@@ -694,4 +2049,309 @@ mod tests {
             Some(&"test-engine".to_string())
         );
     }
+
+    /// Host context that records what it is given.
+    #[derive(Default)]
+    struct RecordingHost {
+        logs: Mutex<Vec<(LogLevel, String)>>,
+        metrics: Mutex<Vec<String>>,
+        cancel: std::sync::atomic::AtomicBool,
+    }
+
+    impl HostContext for RecordingHost {
+        fn log(&self, level: LogLevel, message: &str) {
+            self.logs.lock().push((level, message.to_string()));
+        }
+
+        fn record_metric(&self, name: &str, _value: f64, _tags: &[(&str, &str)]) {
+            self.metrics.lock().push(name.to_string());
+        }
+
+        fn should_cancel(&self) -> bool {
+            self.cancel.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn test_host_context_logs_and_metrics() {
+        let host = Arc::new(RecordingHost::default());
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_host_context(host.clone());
+
+        assert_eq!(engine.execute("1 + 2").unwrap(), Value::Int(3));
+        engine.context().log(LogLevel::Info, "from script");
+
+        assert_eq!(
+            *host.logs.lock(),
+            vec![(LogLevel::Info, "from script".to_string())]
+        );
+        let metrics = host.metrics.lock();
+        assert!(metrics.contains(&"fusabi_executions_total".to_string()));
+        assert!(metrics.contains(&"fusabi_execution_duration_seconds".to_string()));
+    }
+
+    #[test]
+    fn test_host_context_cancellation() {
+        let host = Arc::new(RecordingHost::default());
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_host_context(host.clone());
+
+        host.cancel
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        assert!(matches!(engine.execute("1"), Err(Error::Cancelled)));
+        assert!(host
+            .metrics
+            .lock()
+            .contains(&"fusabi_execution_errors_total".to_string()));
+
+        host.cancel
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(engine.execute("1").unwrap(), Value::Int(1));
+    }
+
+    const ENDLESS: &str = "let rec count n = if n = 0 then 0 else count (n - 1)\ncount 1000000000";
+
+    #[test]
+    fn test_host_context_cancels_running_script() {
+        let host = Arc::new(RecordingHost::default());
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_host_context(host.clone());
+        let compiled = compile_source(ENDLESS, &CompileOptions::default()).unwrap();
+
+        let canceller = {
+            let host = Arc::clone(&host);
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                host.cancel
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            })
+        };
+        let result = engine.execute_bytecode(&compiled.bytecode);
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_timeout_stops_running_script() {
+        let limits = Limits::default().with_timeout(Duration::from_millis(50));
+        let engine = Engine::new(EngineConfig::default().with_limits(limits)).unwrap();
+        let compiled = compile_source(ENDLESS, &CompileOptions::default()).unwrap();
+
+        let start = Instant::now();
+        let result = engine.execute_bytecode(&compiled.bytecode);
+        assert!(matches!(result, Err(Error::LimitViolation(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_print_goes_to_host_log() {
+        let host = Arc::new(RecordingHost::default());
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_host_context(host.clone());
+
+        let compiled = compile_source("printfn \"hello\"", &CompileOptions::default()).unwrap();
+        engine.execute_bytecode(&compiled.bytecode).unwrap();
+        assert_eq!(
+            *host.logs.lock(),
+            vec![(LogLevel::Info, "hello".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_per_execution_host_context() {
+        let default = Arc::new(RecordingHost::default());
+        let cancelling = Arc::new(RecordingHost::default());
+        cancelling
+            .cancel
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+
+        let mut engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.set_host_context(default.clone());
+
+        let result = engine.execute_with_host("1", cancelling.clone());
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(default.metrics.lock().is_empty());
+
+        // The engine's own host context is restored afterwards.
+        assert_eq!(engine.execute("1").unwrap(), Value::Int(1));
+        assert!(!default.metrics.lock().is_empty());
+        assert!(!engine.context().is_cancelled());
+    }
+
+    #[test]
+    fn test_execute_with_limits_only_narrow() {
+        let engine = Engine::new(EngineConfig::default()).unwrap();
+        let tight = ExecOptions::new().with_limits(Limits::default().with_max_instructions(1));
+        assert!(matches!(
+            engine.execute_with("1", tight),
+            Err(Error::LimitViolation(_))
+        ));
+
+        let strict =
+            EngineConfig::default().with_limits(Limits::default().with_max_instructions(1));
+        let engine = Engine::new(strict).unwrap();
+        let loose = ExecOptions::new().with_limits(Limits::unlimited());
+        assert!(matches!(
+            engine.execute_with("1", loose),
+            Err(Error::LimitViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_capability_restriction() {
+        use crate::Capability;
+
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        let ctx =
+            ExecutionContext::new(1, Capabilities::safe_defaults(), Limits::default(), sandbox);
+
+        ctx.restrict(Some(Capabilities::all()), None);
+        assert!(!ctx.has_capability(Capability::FsWrite));
+        assert!(ctx.has_capability(Capability::TimeRead));
+
+        ctx.restrict(Some(Capabilities::none().with(Capability::Logging)), None);
+        assert!(!ctx.has_capability(Capability::TimeRead));
+        assert!(ctx.require_capability(Capability::TimeRead).is_err());
+        assert_eq!(ctx.effective_capabilities().to_names(), vec!["logging"]);
+
+        ctx.restrict(None, None);
+        assert!(ctx.has_capability(Capability::TimeRead));
+    }
+
+    #[test]
+    fn test_execute_with_globals() {
+        let engine = Engine::new(EngineConfig::default()).unwrap();
+        let options = ExecOptions::new().with_global("x", 41i64);
+
+        assert_eq!(
+            engine.execute_with("x + 1", options).unwrap(),
+            Value::Int(42)
+        );
+        assert_eq!(engine.context().get_custom("x"), Some(Value::Int(41)));
+
+        let big = ExecOptions::new().with_global("x", 1e300);
+        assert_eq!(engine.execute_with("x", big).unwrap(), Value::Float(1e300));
+
+        // Scripts run on the VM with or without globals.
+        let source = "let twice f x = f (f x)\ntwice (fun n -> n * 3) 2";
+        let with = ExecOptions::new().with_global("unused", 0i64);
+        assert_eq!(engine.execute(source).unwrap(), Value::Int(18));
+        assert_eq!(engine.execute_with(source, with).unwrap(), Value::Int(18));
+
+        let bad = ExecOptions::new().with_global("x", Value::Bytes(vec![]));
+        assert!(matches!(
+            engine.execute_with("x", bad),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_deterministic_execution() {
+        use crate::deterministic::VirtualClock;
+        use std::time::UNIX_EPOCH;
+
+        let clock = VirtualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let config = DeterministicConfig::new()
+            .with_clock(clock.clone())
+            .with_seed(42);
+        let engine = Engine::new(EngineConfig::default().with_deterministic(config)).unwrap();
+
+        engine.execute("1").unwrap();
+        let report = engine.last_report().unwrap();
+        assert_eq!(report.seed, Some(42));
+        assert_eq!(report.virtual_time, Some(clock.now()));
+        assert_eq!(report.error, None);
+        assert_eq!(engine.context().seed(), None);
+
+        engine
+            .execute_with("1", ExecOptions::new().with_seed(7))
+            .unwrap();
+        assert_eq!(engine.last_report().unwrap().seed, Some(7));
+
+        let ctx = engine.context();
+        let draws = |seed| {
+            ctx.set_determinism(Some(Determinism::new(clock.clone(), seed)));
+            (0..4)
+                .map(|_| ctx.random_u64().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(
+            ctx.now().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_005)
+        );
+    }
+
+    #[test]
+    fn test_fresh_seed_per_execution() {
+        let engine =
+            Engine::new(EngineConfig::default().with_deterministic(DeterministicConfig::new()))
+                .unwrap();
+        engine.execute("1").unwrap();
+        let first = engine.last_report().unwrap().seed.unwrap();
+        engine.execute("1").unwrap();
+        assert_ne!(engine.last_report().unwrap().seed, Some(first));
+
+        let engine = Engine::new(EngineConfig::default()).unwrap();
+        engine.execute("1").unwrap();
+        let report = engine.last_report().unwrap();
+        assert_eq!(report.seed, None);
+        assert_eq!(report.virtual_time, None);
+        assert!(report.instructions > 0);
+    }
+
+    #[test]
+    fn test_scripts_read_virtual_clock_and_seeded_random() {
+        use crate::deterministic::VirtualClock;
+        use std::time::UNIX_EPOCH;
+
+        let clock = VirtualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let config = DeterministicConfig::new().with_clock(clock).with_seed(42);
+        let engine = Engine::new(EngineConfig::default().with_deterministic(config)).unwrap();
+
+        assert_eq!(
+            engine.execute("Time.now ()").unwrap(),
+            Value::Int(1_700_000_000_000)
+        );
+        let first = engine.execute("Random.next ()").unwrap();
+        assert_eq!(engine.execute("Random.next ()").unwrap(), first);
+        assert_ne!(
+            engine
+                .execute_with("Random.next ()", ExecOptions::new().with_seed(7))
+                .unwrap(),
+            first
+        );
+        let Value::Float(f) = engine.execute("Random.float ()").unwrap() else {
+            panic!("expected a float");
+        };
+        assert!((0.0..1.0).contains(&f));
+
+        let engine =
+            Engine::new(EngineConfig::default().with_capabilities(Capabilities::none())).unwrap();
+        assert!(matches!(
+            engine.execute("Time.now ()"),
+            Err(Error::CapabilityDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_time_and_random_require_capabilities() {
+        let engine = Engine::new(EngineConfig::default()).unwrap();
+        assert!(engine.context().now().is_ok());
+        assert!(engine.context().random_f64().unwrap() < 1.0);
+
+        let engine =
+            Engine::new(EngineConfig::default().with_capabilities(Capabilities::none())).unwrap();
+        assert!(matches!(
+            engine.context().now(),
+            Err(Error::CapabilityDenied { .. })
+        ));
+        assert!(matches!(
+            engine.context().random_u64(),
+            Err(Error::CapabilityDenied { .. })
+        ));
+    }
 }
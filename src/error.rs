@@ -4,6 +4,7 @@ use thiserror::Error;
 
 use crate::convert::ValueConversionError;
 use crate::limits::LimitViolation;
+use crate::link::LinkError;
 
 /// Result type alias using [`enum@Error`].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -53,6 +54,13 @@ pub enum Error {
     #[error("engine pool has been shut down")]
     PoolShutdown,
 
+    /// Job queue is full.
+    #[error("job queue full, {capacity} jobs already queued")]
+    Overloaded {
+        /// Capacity of the job queue.
+        capacity: usize,
+    },
+
     /// Engine was poisoned (panicked during execution).
     #[error("engine poisoned: {0}")]
     EnginePoisoned(String),
@@ -78,10 +86,26 @@ pub enum Error {
     #[error("host function error: {0}")]
     HostFunction(String),
 
+    /// A script references host functions the registry cannot satisfy.
+    #[error("link failed: {}", join_link_errors(.0))]
+    Link(Vec<LinkError>),
+
     /// Bytecode validation failed.
     #[error("invalid bytecode: {0}")]
     InvalidBytecode(String),
 
+    /// Script bundle was malformed or failed an integrity check.
+    #[error("invalid bundle: {0}")]
+    InvalidBundle(String),
+
+    /// A prelude script failed to load into an engine.
+    #[error("prelude failed: {0}")]
+    Prelude(String),
+
+    /// A replayed execution stopped matching its recorded trace.
+    #[error("replay diverged: {0}")]
+    ReplayDiverged(String),
+
     /// Timeout during execution.
     #[error("execution timeout after {0:?}")]
     Timeout(std::time::Duration),
@@ -141,11 +165,53 @@ impl Error {
         Self::InvalidBytecode(msg.into())
     }
 
+    /// Create an invalid bundle error.
+    pub fn invalid_bundle(msg: impl Into<String>) -> Self {
+        Self::InvalidBundle(msg.into())
+    }
+
+    /// Create a prelude error.
+    pub fn prelude(msg: impl Into<String>) -> Self {
+        Self::Prelude(msg.into())
+    }
+
+    /// Short name of the error variant, such as `"runtime"`, for metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Compilation(_) => "compilation",
+            Self::Runtime(_) => "runtime",
+            Self::LimitViolation(_) => "limit_violation",
+            Self::ValueConversion(_) => "value_conversion",
+            Self::CapabilityDenied { .. } => "capability_denied",
+            Self::SandboxViolation(_) => "sandbox_violation",
+            Self::PoolExhausted { .. } => "pool_exhausted",
+            Self::PoolTimeout => "pool_timeout",
+            Self::PoolShutdown => "pool_shutdown",
+            Self::Overloaded { .. } => "overloaded",
+            Self::EnginePoisoned(_) => "engine_poisoned",
+            Self::Io(_) => "io",
+            Self::InvalidConfig(_) => "invalid_config",
+            Self::VersionMismatch { .. } => "version_mismatch",
+            Self::HostFunction(_) => "host_function",
+            Self::Link(_) => "link",
+            Self::InvalidBytecode(_) => "invalid_bytecode",
+            Self::InvalidBundle(_) => "invalid_bundle",
+            Self::Prelude(_) => "prelude",
+            Self::ReplayDiverged(_) => "replay_diverged",
+            Self::Timeout(_) => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Internal(_) => "internal",
+        }
+    }
+
     /// Returns true if this is a transient error that may succeed on retry.
     pub fn is_transient(&self) -> bool {
         matches!(
             self,
-            Self::PoolExhausted { .. } | Self::PoolTimeout | Self::Timeout(_)
+            Self::PoolExhausted { .. }
+                | Self::PoolTimeout
+                | Self::Overloaded { .. }
+                | Self::Timeout(_)
         )
     }
 
@@ -158,6 +224,14 @@ impl Error {
     }
 }
 
+fn join_link_errors(errors: &[LinkError]) -> String {
+    errors
+        .iter()
+        .map(LinkError::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,10 +249,18 @@ mod tests {
     fn test_error_classification() {
         assert!(Error::PoolTimeout.is_transient());
         assert!(Error::PoolExhausted { count: 4 }.is_transient());
+        assert!(Error::Overloaded { capacity: 8 }.is_transient());
         assert!(!Error::Compilation("test".into()).is_transient());
 
         assert!(Error::EnginePoisoned("panic".into()).is_fatal());
         assert!(Error::PoolShutdown.is_fatal());
         assert!(!Error::PoolTimeout.is_fatal());
     }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(Error::runtime("boom").kind(), "runtime");
+        assert_eq!(Error::PoolTimeout.kind(), "pool_timeout");
+        assert_eq!(Error::Cancelled.kind(), "cancelled");
+    }
 }
@@ -0,0 +1,236 @@
+//! Lock-free latency histograms for pool metrics.
+//!
+//! Samples are counted in log-linear buckets in the style of HDR histograms:
+//! each power of two is split into 32 equal buckets, so any
+//! recorded value is reported within about 3% of its true value while the
+//! histogram covers the whole `u64` nanosecond range in fixed memory.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const SUB_BUCKET_BITS: u32 = 5;
+/// Buckets per power of two.
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+/// Bucket holding a value.
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+    let exp = 63 - value.leading_zeros();
+    let shift = exp - SUB_BUCKET_BITS;
+    let sub = (value >> shift) as usize & (SUB_BUCKETS - 1);
+    (shift as usize + 1) * SUB_BUCKETS + sub
+}
+
+/// Largest value that falls in a bucket.
+fn bucket_upper(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let shift = (index / SUB_BUCKETS - 1) as u32;
+    let base = ((SUB_BUCKETS + index % SUB_BUCKETS) as u64) << shift;
+    base + ((1u64 << shift) - 1)
+}
+
+/// Concurrent recorder of durations.
+pub(crate) struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    sum_nanos: AtomicU64,
+    min_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            sum_nanos: AtomicU64::new(0),
+            min_nanos: AtomicU64::new(u64::MAX),
+            max_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one sample.
+    pub(crate) fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.min_nanos.fetch_min(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Copy the current distribution.
+    pub(crate) fn snapshot(&self) -> LatencySnapshot {
+        let buckets = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| {
+                let n = b.load(Ordering::Relaxed);
+                (n > 0).then_some((i, n))
+            })
+            .collect();
+        LatencySnapshot::new(
+            buckets,
+            self.sum_nanos.load(Ordering::Relaxed),
+            self.min_nanos.load(Ordering::Relaxed),
+            self.max_nanos.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Clear all samples.
+    pub(crate) fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.sum_nanos.store(0, Ordering::Relaxed);
+        self.min_nanos.store(u64::MAX, Ordering::Relaxed);
+        self.max_nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+/// A point-in-time copy of a latency distribution.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencySnapshot {
+    /// Non-empty buckets as `(index, count)`, in ascending order.
+    buckets: Vec<(usize, u64)>,
+    count: u64,
+    sum_nanos: u64,
+    min_nanos: u64,
+    max_nanos: u64,
+}
+
+impl LatencySnapshot {
+    fn new(buckets: Vec<(usize, u64)>, sum_nanos: u64, min_nanos: u64, max_nanos: u64) -> Self {
+        let count = buckets.iter().map(|(_, n)| n).sum();
+        if count == 0 {
+            return Self::default();
+        }
+        Self {
+            buckets,
+            count,
+            sum_nanos,
+            min_nanos,
+            max_nanos,
+        }
+    }
+
+    /// Number of samples.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Sum of all samples.
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos)
+    }
+
+    /// Smallest sample.
+    pub fn min(&self) -> Duration {
+        Duration::from_nanos(self.min_nanos)
+    }
+
+    /// Largest sample.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos)
+    }
+
+    /// Average sample.
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.checked_div(self.count).unwrap_or(0))
+    }
+
+    /// Value at or below which the given fraction of samples fall.
+    ///
+    /// `quantile` is clamped to `0.0..=1.0`, so `percentile(0.99)` is p99.
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for &(index, n) in &self.buckets {
+            seen += n;
+            if seen >= rank {
+                let nanos = bucket_upper(index).clamp(self.min_nanos, self.max_nanos);
+                return Duration::from_nanos(nanos);
+            }
+        }
+        self.max()
+    }
+
+    /// Median sample.
+    pub fn p50(&self) -> Duration {
+        self.percentile(0.5)
+    }
+
+    /// 90th percentile sample.
+    pub fn p90(&self) -> Duration {
+        self.percentile(0.9)
+    }
+
+    /// 99th percentile sample.
+    pub fn p99(&self) -> Duration {
+        self.percentile(0.99)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bounds() {
+        for value in [0, 1, 31, 32, 33, 63, 64, 1000, 123_456_789, u64::MAX] {
+            let index = bucket_index(value);
+            assert!(index < BUCKETS);
+            assert!(bucket_upper(index) >= value);
+            if index > 0 {
+                assert!(bucket_upper(index - 1) < value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = LatencyHistogram::new();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 100);
+        assert_eq!(snapshot.min(), Duration::from_millis(1));
+        assert_eq!(snapshot.max(), Duration::from_millis(100));
+        assert_eq!(snapshot.mean(), Duration::from_micros(50_500));
+
+        // Within the bucket resolution of the true value.
+        for (actual, expected) in [
+            (snapshot.p50(), 50.0),
+            (snapshot.p90(), 90.0),
+            (snapshot.p99(), 99.0),
+        ] {
+            let ms = actual.as_secs_f64() * 1000.0;
+            assert!(
+                (ms - expected).abs() / expected < 0.04,
+                "{} vs {}",
+                ms,
+                expected
+            );
+        }
+        assert_eq!(snapshot.percentile(1.0), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_reset() {
+        let histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_micros(5));
+        histogram.reset();
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot, LatencySnapshot::default());
+        assert_eq!(snapshot.p99(), Duration::ZERO);
+    }
+}
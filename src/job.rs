@@ -0,0 +1,480 @@
+//! Units of work submitted to an engine pool, and handles to their results.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::engine::Engine;
+use crate::error::{Error, Result};
+use crate::options::ExecOptions;
+use crate::priority::Priority;
+use crate::value::Value;
+
+/// Script run by a [`Job`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Script {
+    /// Fusabi source.
+    Source(Arc<str>),
+    /// Compiled FZB bytecode.
+    Bytecode(Arc<[u8]>),
+}
+
+/// A script to run on a pooled engine with its execution options.
+///
+/// Scripts are reference counted, so running one script over many inputs
+/// only clones a pointer per job.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Script to run.
+    pub script: Script,
+    /// Per-execution options, including input globals.
+    pub options: ExecOptions,
+    /// Priority class used to acquire the engine.
+    pub priority: Priority,
+}
+
+impl Job {
+    /// Create a job running source code.
+    pub fn source(source: impl Into<Arc<str>>) -> Self {
+        Self::new(Script::Source(source.into()))
+    }
+
+    /// Create a job running compiled bytecode.
+    pub fn bytecode(bytecode: impl Into<Arc<[u8]>>) -> Self {
+        Self::new(Script::Bytecode(bytecode.into()))
+    }
+
+    fn new(script: Script) -> Self {
+        Self {
+            script,
+            options: ExecOptions::default(),
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Set the execution options.
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Bind a global input for the script.
+    pub fn with_global(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.options = self.options.with_global(name, value);
+        self
+    }
+
+    /// Set the priority class used to acquire the engine.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// Lifecycle of a submitted job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting in the queue.
+    Queued,
+    /// Running on an engine.
+    Running,
+    /// Finished with a value.
+    Succeeded,
+    /// Finished with an error.
+    Failed,
+    /// Cancelled before or while running.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Check if the job has finished.
+    pub fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+struct JobState {
+    status: JobStatus,
+    result: Option<Result<Value>>,
+    /// Engine running the job.
+    engine: Option<Arc<Engine>>,
+    /// Count of queued jobs in the pool, held until the job leaves the queue.
+    queue_slot: Option<Arc<AtomicUsize>>,
+    waker: Option<Waker>,
+    /// Called once the job finishes.
+    on_finish: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl JobState {
+    /// Give up the job's place in the queue, if it still holds one.
+    fn leave_queue(&mut self) {
+        if let Some(slot) = self.queue_slot.take() {
+            slot.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// State shared between a [`JobHandle`] and the worker running the job.
+pub(crate) struct JobShared {
+    id: u64,
+    state: Mutex<JobState>,
+    finished: Condvar,
+}
+
+impl JobShared {
+    /// Create a queued job holding one of the slots counted by `queue_slot`.
+    pub(crate) fn new(queue_slot: Arc<AtomicUsize>) -> Arc<Self> {
+        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+        Arc::new(Self {
+            id: NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            state: Mutex::new(JobState {
+                status: JobStatus::Queued,
+                result: None,
+                engine: None,
+                queue_slot: Some(queue_slot),
+                waker: None,
+                on_finish: None,
+            }),
+            finished: Condvar::new(),
+        })
+    }
+
+    /// Mark the job as running on `engine`.
+    ///
+    /// Returns false if the job was cancelled while queued.
+    pub(crate) fn start(&self, engine: &Arc<Engine>) -> bool {
+        let mut state = self.state.lock();
+        if state.status != JobStatus::Queued {
+            return false;
+        }
+        state.leave_queue();
+        state.status = JobStatus::Running;
+        state.engine = Some(Arc::clone(engine));
+        true
+    }
+
+    /// Check if the job is still waiting to run.
+    pub(crate) fn is_queued(&self) -> bool {
+        self.state.lock().status == JobStatus::Queued
+    }
+
+    /// Record the job's result and wake anyone waiting for it.
+    ///
+    /// Must be called before the engine is released, so a late
+    /// [`JobHandle::cancel`] cannot reach the next user of the engine.
+    pub(crate) fn finish(&self, result: Result<Value>) {
+        let mut state = self.state.lock();
+        if state.status.is_finished() {
+            return;
+        }
+        state.leave_queue();
+        state.engine = None;
+        state.status = match &result {
+            Ok(_) => JobStatus::Succeeded,
+            Err(Error::Cancelled) => JobStatus::Cancelled,
+            Err(_) => JobStatus::Failed,
+        };
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.finished.notify_all();
+        let on_finish = state.on_finish.take();
+        drop(state);
+        if let Some(on_finish) = on_finish {
+            on_finish();
+        }
+    }
+}
+
+/// A job queued with [`EnginePool::submit`](crate::EnginePool::submit).
+pub(crate) struct QueuedJob {
+    pub(crate) job: Job,
+    pub(crate) shared: Arc<JobShared>,
+}
+
+/// Handle to a submitted job.
+///
+/// The result can be taken once, by [`wait`](Self::wait),
+/// [`try_result`](Self::try_result), or by awaiting the handle. Dropping the
+/// handle does not cancel the job.
+pub struct JobHandle {
+    shared: Arc<JobShared>,
+}
+
+impl JobHandle {
+    pub(crate) fn new(shared: Arc<JobShared>) -> Self {
+        Self { shared }
+    }
+
+    /// Get the job ID.
+    pub fn id(&self) -> u64 {
+        self.shared.id
+    }
+
+    /// Get the job's current status.
+    pub fn status(&self) -> JobStatus {
+        self.shared.state.lock().status
+    }
+
+    /// Check if the job has finished.
+    pub fn is_finished(&self) -> bool {
+        self.status().is_finished()
+    }
+
+    /// Call `f` when the job finishes. Does nothing if it already has.
+    ///
+    /// `f` runs on the thread finishing the job, without the job's lock held.
+    pub(crate) fn on_finish(&self, f: impl FnOnce() + Send + 'static) {
+        let mut state = self.shared.state.lock();
+        if !state.status.is_finished() {
+            state.on_finish = Some(Box::new(f));
+        }
+    }
+
+    /// Take the result if the job has finished.
+    pub fn try_result(&self) -> Option<Result<Value>> {
+        self.shared.state.lock().result.take()
+    }
+
+    /// Block until the job finishes and take its result.
+    pub fn wait(self) -> Result<Value> {
+        let mut state = self.shared.state.lock();
+        while !state.status.is_finished() {
+            self.shared.finished.wait(&mut state);
+        }
+        take_result(&mut state)
+    }
+
+    /// Block until the job finishes or `timeout` elapses.
+    ///
+    /// Returns `None` if the job is still queued or running.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<Value>> {
+        let until = Instant::now() + timeout;
+        let mut state = self.shared.state.lock();
+        while !state.status.is_finished() {
+            if self
+                .shared
+                .finished
+                .wait_until(&mut state, until)
+                .timed_out()
+            {
+                return None;
+            }
+        }
+        Some(take_result(&mut state))
+    }
+
+    /// Cancel the job.
+    ///
+    /// A queued job gives up its queue slot at once and is skipped without
+    /// taking an engine; a running job has its engine cancelled at the next
+    /// safe point. Returns false if the job had
+    /// already finished.
+    pub fn cancel(&self) -> bool {
+        let state = self.shared.state.lock();
+        match state.status {
+            JobStatus::Queued => {
+                drop(state);
+                self.shared.finish(Err(Error::Cancelled));
+                true
+            }
+            JobStatus::Running => {
+                if let Some(engine) = &state.engine {
+                    engine.cancel();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn take_result(state: &mut JobState) -> Result<Value> {
+    state
+        .result
+        .take()
+        .unwrap_or_else(|| Err(Error::Internal("job result already taken".into())))
+}
+
+impl Future for JobHandle {
+    type Output = Result<Value>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.state.lock();
+        if state.status.is_finished() {
+            return Poll::Ready(take_result(&mut state));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl std::fmt::Debug for JobHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobHandle")
+            .field("id", &self.id())
+            .field("status", &self.status())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_context::{HostContext, LogLevel};
+    use crate::pool::{EnginePool, PoolConfig};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use std::thread;
+
+    /// Holds executions at their cancellation check until opened.
+    #[derive(Default)]
+    struct Gate {
+        open: AtomicBool,
+    }
+
+    impl HostContext for Gate {
+        fn log(&self, _level: LogLevel, _message: &str) {}
+
+        fn record_metric(&self, _name: &str, _value: f64, _tags: &[(&str, &str)]) {}
+
+        fn should_cancel(&self) -> bool {
+            while !self.open.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            false
+        }
+    }
+
+    fn gated(gate: &Arc<Gate>) -> Job {
+        Job::source("1").with_options(ExecOptions::new().with_host_context(gate.clone()))
+    }
+
+    fn wait_for(handle: &JobHandle, status: JobStatus) {
+        let until = Instant::now() + Duration::from_secs(5);
+        while handle.status() != status {
+            assert!(Instant::now() < until, "job never reached {:?}", status);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_submit_and_wait() {
+        let pool = EnginePool::new(PoolConfig::new(2)).unwrap();
+        let handles: Vec<_> = (0..10i64)
+            .map(|i| {
+                pool.submit(Job::source("x + 1").with_global("x", i))
+                    .unwrap()
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.wait().unwrap(), Value::Int(i as i64 + 1));
+        }
+    }
+
+    #[test]
+    fn test_try_result_takes_once() {
+        let pool = EnginePool::new(PoolConfig::new(1)).unwrap();
+        let handle = pool.submit(Job::source("7")).unwrap();
+        assert_eq!(
+            handle
+                .wait_timeout(Duration::from_secs(5))
+                .unwrap()
+                .unwrap(),
+            Value::Int(7)
+        );
+        assert_eq!(handle.status(), JobStatus::Succeeded);
+        assert!(handle.try_result().is_none());
+        assert!(matches!(handle.wait(), Err(Error::Internal(_))));
+    }
+
+    #[test]
+    fn test_queue_full_and_cancel() {
+        let pool = EnginePool::new(
+            PoolConfig::new(1)
+                .with_job_workers(1)
+                .with_job_queue_capacity(1),
+        )
+        .unwrap();
+        let gate = Arc::new(Gate::default());
+
+        let running = pool.submit(gated(&gate)).unwrap();
+        wait_for(&running, JobStatus::Running);
+        let queued = pool.submit(gated(&gate)).unwrap();
+        assert_eq!(queued.status(), JobStatus::Queued);
+        assert!(matches!(
+            pool.submit(Job::source("1")),
+            Err(Error::Overloaded { capacity: 1 })
+        ));
+        assert_eq!(pool.stats().rejected_jobs, 1);
+        assert_eq!(pool.stats().queued_jobs, 1);
+
+        assert!(queued.cancel());
+        assert_eq!(queued.status(), JobStatus::Cancelled);
+        assert!(running.wait_timeout(Duration::from_millis(10)).is_none());
+
+        // The cancelled job's slot is free before the worker reaches it.
+        assert_eq!(pool.stats().queued_jobs, 0);
+        let next = pool.submit(Job::source("2")).unwrap();
+
+        gate.open.store(true, Ordering::SeqCst);
+        assert_eq!(running.wait().unwrap(), Value::Int(1));
+        assert!(matches!(queued.wait(), Err(Error::Cancelled)));
+        assert_eq!(next.wait().unwrap(), Value::Int(2));
+        // Only the jobs that ran took an engine.
+        assert_eq!(pool.stats().acquisitions, 2);
+    }
+
+    #[test]
+    fn test_handle_is_a_future() {
+        struct Unpark(thread::Thread);
+
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let pool = EnginePool::new(PoolConfig::new(1)).unwrap();
+        let gate = Arc::new(Gate::default());
+        let mut handle = pool.submit(gated(&gate)).unwrap();
+
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut handle).poll(&mut cx).is_pending());
+
+        gate.open.store(true, Ordering::SeqCst);
+        let result = loop {
+            match Pin::new(&mut handle).poll(&mut cx) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => thread::park_timeout(Duration::from_millis(100)),
+            }
+        };
+        assert_eq!(result.unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_shutdown_fails_queued_jobs() {
+        let pool = EnginePool::new(PoolConfig::new(1).with_job_workers(1)).unwrap();
+        let gate = Arc::new(Gate::default());
+        let running = pool.submit(gated(&gate)).unwrap();
+        wait_for(&running, JobStatus::Running);
+        let queued = pool.submit(Job::source("1")).unwrap();
+
+        pool.shutdown();
+        assert!(matches!(
+            pool.submit(Job::source("1")),
+            Err(Error::PoolShutdown)
+        ));
+        gate.open.store(true, Ordering::SeqCst);
+        assert!(running.wait().is_ok());
+        assert!(matches!(queued.wait(), Err(Error::PoolShutdown)));
+    }
+}
@@ -8,6 +8,7 @@
 //! - **Typed host function macros** for ergonomic host function registration
 //! - **Sandbox and capability configuration** for secure script execution
 //! - **Stable compile/run APIs** for consistent host integration
+//! - **Script bundles** for shipping multi-module plugins as a single file
 //!
 //! ## Quick Start
 //!
@@ -35,35 +36,88 @@
 #![warn(missing_docs)]
 #![warn(rust_2018_idioms)]
 
+mod autoscale;
+mod batch;
+mod bundle;
 mod capabilities;
 mod compile;
 mod convert;
+mod cron;
+mod decl;
+mod deterministic;
+mod disasm;
 mod engine;
 mod error;
+mod histogram;
 mod host_context;
+mod job;
 mod limits;
+mod link;
 pub mod macros;
+mod memo;
+#[cfg(feature = "metrics-prometheus")]
+mod metrics;
+mod options;
 mod pool;
+mod priority;
+mod safepoint;
 mod sandbox;
+mod schedule;
+mod tenant;
+mod trace;
 mod value;
+mod verify;
 
+pub use autoscale::{AutoscaleConfig, ScaleEvent, ScaleReason};
+pub use batch::{BatchOptions, BatchProgress, ProgressFn};
+pub use bundle::{
+    compile_project, Bundle, BundleAsset, BundleBuilder, BundleManifest, BundleModule,
+    BUNDLE_FORMAT_VERSION, BUNDLE_MAGIC,
+};
 pub use capabilities::{Capabilities, Capability};
 pub use compile::{
     compile_file, compile_source, extract_bytecode_metadata, validate_bytecode, CompileOptions,
-    CompileResult, Metadata,
+    CompileResult, HostReference, Metadata,
 };
 pub use convert::{FromValue, IntoValue, ValueConversionError};
+pub use cron::Cron;
+pub use decl::{HostFnDecl, HostParam};
+pub use deterministic::{DeterministicConfig, VirtualClock};
+pub use disasm::{disassemble, ConstantListing, Disassembly, FunctionListing, InstructionListing};
 
 #[cfg(feature = "serde-support")]
 pub use convert::{from_value_serde, to_value_serde};
-pub use engine::{Engine, EngineConfig, ExecutionContext, HostFn, HostRegistry};
+pub use engine::{
+    Engine, EngineConfig, ExecutionContext, ExecutionReport, HostFn, HostFnSignature, HostRegistry,
+    Prelude,
+};
 pub use error::{Error, Result};
+pub use histogram::LatencySnapshot;
 pub use host_context::{DefaultHostContext, HostContext, LogLevel, NoopHostContext};
+pub use job::{Job, JobHandle, JobStatus, Script};
 pub use limits::{LimitViolation, Limits};
+pub use link::{link_check, LinkError, LinkInput};
 pub use macros::typed_host_fn_2;
-pub use pool::{EnginePool, PoolConfig, PoolHandle, PoolStats};
+pub use memo::{CacheStats, ResultCacheConfig};
+#[cfg(feature = "metrics-prometheus")]
+pub use metrics::{encode_metrics, PrometheusMetrics};
+pub use options::ExecOptions;
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+pub use pool::{AsyncEnginePool, AsyncRuntime};
+#[cfg(feature = "async-runtime-async-std")]
+pub use pool::{AsyncStdEnginePool, AsyncStdRuntime};
+pub use pool::{
+    EngineInitializer, EnginePool, PoolConfig, PoolHandle, PoolStats, ShutdownReport, WarmupReport,
+};
+#[cfg(feature = "async-runtime-tokio")]
+pub use pool::{TokioEnginePool, TokioRuntime};
+pub use priority::{Priority, PriorityClass, PriorityConfig, PriorityStats};
 pub use sandbox::{NetPolicy, PathPolicy, Sandbox, SandboxConfig};
+pub use schedule::{Backoff, RunOutcome, RunRecord, Schedule, ScheduledTask, Scheduler, TaskStats};
+pub use tenant::{TenantConfig, TenantHandle, TenantPool, TenantStats};
+pub use trace::{CallOutcome, Divergence, HostCall, ReplayReport, Trace};
 pub use value::{Value, ValueType};
+pub use verify::{verify_bytecode, verify_disassembly, MAX_ARITY};
 
 /// Crate version for compatibility checks
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -66,6 +66,21 @@ pub enum LimitViolation {
     },
 }
 
+impl LimitViolation {
+    /// Short name of the violated limit, such as `"memory"`, for metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TimeExceeded { .. } => "time",
+            Self::MemoryExceeded { .. } => "memory",
+            Self::InstructionsExceeded { .. } => "instructions",
+            Self::StackDepthExceeded { .. } => "stack_depth",
+            Self::OutputSizeExceeded { .. } => "output_size",
+            Self::FsOpsExceeded { .. } => "fs_ops",
+            Self::NetOpsExceeded { .. } => "net_ops",
+        }
+    }
+}
+
 /// Resource limits for script execution.
 ///
 /// These limits control how much resources a script can consume.
@@ -141,6 +156,26 @@ impl Limits {
         }
     }
 
+    /// Combine with `other`, keeping the tighter of each limit.
+    pub fn narrow(&self, other: &Limits) -> Limits {
+        fn tighter<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        Limits {
+            timeout: tighter(self.timeout, other.timeout),
+            memory_bytes: tighter(self.memory_bytes, other.memory_bytes),
+            max_instructions: tighter(self.max_instructions, other.max_instructions),
+            max_stack_depth: tighter(self.max_stack_depth, other.max_stack_depth),
+            max_output_bytes: tighter(self.max_output_bytes, other.max_output_bytes),
+            max_fs_ops: tighter(self.max_fs_ops, other.max_fs_ops),
+            max_net_ops: tighter(self.max_net_ops, other.max_net_ops),
+            max_concurrent_tasks: tighter(self.max_concurrent_tasks, other.max_concurrent_tasks),
+        }
+    }
+
     /// Set the timeout.
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
@@ -427,4 +462,22 @@ mod tests {
         assert!(tracker.record_instructions(50).is_ok());
         assert!(tracker.record_instructions(60).is_err());
     }
+
+    #[test]
+    fn test_narrow_keeps_tighter_limits() {
+        let baseline = Limits::unlimited()
+            .with_max_instructions(100)
+            .with_timeout(Duration::from_secs(5));
+        let narrowed = baseline.narrow(
+            &Limits::unlimited()
+                .with_max_instructions(1000)
+                .with_timeout(Duration::from_secs(1))
+                .with_max_fs_ops(0),
+        );
+
+        assert_eq!(narrowed.max_instructions, Some(100));
+        assert_eq!(narrowed.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(narrowed.max_fs_ops, Some(0));
+        assert_eq!(narrowed.max_net_ops, None);
+    }
 }
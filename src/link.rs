@@ -0,0 +1,436 @@
+//! Static host-function linkage checks.
+//!
+//! Compiled scripts reach host functions through global loads. A missing
+//! registration only surfaces when the call executes; [`link_check`] finds
+//! those references up front and compares them against a [`HostRegistry`].
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::compile::{extract_bytecode_metadata, HostReference, Metadata};
+use crate::disasm::{Disassembly, InstructionListing};
+use crate::engine::{HostFnSignature, HostRegistry, SCRIPT_NATIVES};
+use crate::verify::stack_effect;
+
+/// A host-function reference that the registry cannot satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkError {
+    /// A global function is not registered.
+    UnresolvedFunction {
+        /// Function name.
+        name: String,
+        /// Index of the function holding the reference.
+        function: usize,
+        /// Offset of the reference.
+        offset: usize,
+    },
+
+    /// A module function is not registered.
+    UnresolvedModuleFunction {
+        /// Module name.
+        module: String,
+        /// Function name.
+        name: String,
+        /// Index of the function holding the reference.
+        function: usize,
+        /// Offset of the reference.
+        offset: usize,
+    },
+
+    /// A call passes a number of arguments the function does not accept.
+    ArityMismatch {
+        /// Qualified function name.
+        name: String,
+        /// Declared signature.
+        expected: HostFnSignature,
+        /// Arguments at the call site.
+        actual: usize,
+        /// Index of the function holding the reference.
+        function: usize,
+        /// Offset of the reference.
+        offset: usize,
+    },
+
+    /// The input could not be analyzed.
+    InvalidInput(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::UnresolvedFunction {
+                name,
+                function,
+                offset,
+            } => write!(
+                f,
+                "unresolved host function {} at fn #{} offset {}",
+                name, function, offset
+            ),
+            LinkError::UnresolvedModuleFunction {
+                module,
+                name,
+                function,
+                offset,
+            } => write!(
+                f,
+                "unresolved host function {}.{} at fn #{} offset {}",
+                module, name, function, offset
+            ),
+            LinkError::ArityMismatch {
+                name,
+                expected,
+                actual,
+                function,
+                offset,
+            } => write!(
+                f,
+                "{} expects {} arguments, called with {} at fn #{} offset {}",
+                name, expected, actual, function, offset
+            ),
+            LinkError::InvalidInput(msg) => write!(f, "cannot link: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+impl From<LinkError> for crate::Error {
+    fn from(err: LinkError) -> Self {
+        crate::Error::Link(vec![err])
+    }
+}
+
+/// Input accepted by [`link_check`]: raw bytecode or extracted metadata.
+#[derive(Debug, Clone, Copy)]
+pub enum LinkInput<'a> {
+    /// FZB bytecode.
+    Bytecode(&'a [u8]),
+    /// Metadata carrying host references.
+    Metadata(&'a Metadata),
+}
+
+impl<'a> From<&'a [u8]> for LinkInput<'a> {
+    fn from(bytecode: &'a [u8]) -> Self {
+        LinkInput::Bytecode(bytecode)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for LinkInput<'a> {
+    fn from(bytecode: &'a Vec<u8>) -> Self {
+        LinkInput::Bytecode(bytecode)
+    }
+}
+
+impl<'a> From<&'a Metadata> for LinkInput<'a> {
+    fn from(metadata: &'a Metadata) -> Self {
+        LinkInput::Metadata(metadata)
+    }
+}
+
+/// Check a script's host-function references against a registry.
+///
+/// Returns every unresolved global or module reference and every call whose
+/// argument count does not match the registered [`HostFnSignature`]. An empty
+/// list means the script links.
+pub fn link_check<'a>(input: impl Into<LinkInput<'a>>, registry: &HostRegistry) -> Vec<LinkError> {
+    let extracted;
+    let metadata = match input.into() {
+        LinkInput::Metadata(metadata) => metadata,
+        LinkInput::Bytecode(bytecode) => match extract_bytecode_metadata(bytecode) {
+            Ok(metadata) => {
+                extracted = metadata;
+                &extracted
+            }
+            Err(e) => return vec![LinkError::InvalidInput(e.to_string())],
+        },
+    };
+
+    let mut errors = Vec::new();
+    for reference in &metadata.host_references {
+        let signature = match &reference.module {
+            None => {
+                if registry.get(&reference.name).is_none() {
+                    errors.push(LinkError::UnresolvedFunction {
+                        name: reference.name.clone(),
+                        function: reference.function,
+                        offset: reference.offset,
+                    });
+                    continue;
+                }
+                registry.signature(&reference.name)
+            }
+            Some(module) => {
+                if registry.get_module(module, &reference.name).is_none() {
+                    errors.push(LinkError::UnresolvedModuleFunction {
+                        module: module.clone(),
+                        name: reference.name.clone(),
+                        function: reference.function,
+                        offset: reference.offset,
+                    });
+                    continue;
+                }
+                registry.module_signature(module, &reference.name)
+            }
+        };
+
+        if let (Some(signature), Some(actual)) = (signature, reference.arg_count) {
+            if !signature.accepts(actual) {
+                errors.push(LinkError::ArityMismatch {
+                    name: reference.qualified_name(),
+                    expected: *signature,
+                    actual,
+                    function: reference.function,
+                    offset: reference.offset,
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// Collect host references from every function of a disassembly.
+///
+/// Every `LoadGlobal` of a name that no function stores and that the VM does
+/// not define itself (see [`builtin_names`]) is a host reference; dotted
+/// names (`math.add`) refer to module functions. Argument counts come from a
+/// per-block simulation of the operand stack that tracks which loaded global
+/// each `Call` consumes.
+pub(crate) fn host_references(disassembly: &Disassembly) -> Vec<HostReference> {
+    let defined: HashSet<String> = disassembly
+        .functions
+        .iter()
+        .flat_map(|function| {
+            function
+                .instructions
+                .iter()
+                .filter(|i| i.opcode == "StoreGlobal")
+                .filter_map(|i| global_name(i, &function.constants))
+        })
+        .collect();
+
+    let mut references: Vec<HostReference> = Vec::new();
+    for function in &disassembly.functions {
+        let leaders: HashSet<usize> = function
+            .instructions
+            .iter()
+            .filter_map(|i| i.jump_target)
+            .collect();
+
+        // Each slot holds the index into `references` of the global it came
+        // from.
+        let mut stack: Vec<Option<usize>> = Vec::new();
+
+        for instr in &function.instructions {
+            if leaders.contains(&instr.offset) {
+                stack.clear();
+            }
+
+            if instr.opcode == "LoadGlobal" {
+                let slot = global_name(instr, &function.constants)
+                    .filter(|name| !defined.contains(name) && !builtin_names().contains(name))
+                    .map(|name| {
+                        let (module, name) = match name.rsplit_once('.') {
+                            Some((module, name)) => (Some(module.to_string()), name.to_string()),
+                            None => (None, name),
+                        };
+                        references.push(HostReference {
+                            module,
+                            name,
+                            arg_count: None,
+                            function: function.index,
+                            offset: instr.offset,
+                        });
+                        references.len() - 1
+                    });
+                stack.push(slot);
+                continue;
+            }
+
+            if matches!(instr.opcode.as_str(), "Call" | "TailCall") {
+                if let Some(argc) = instr.int_operand(0).map(|n| n.max(0) as usize) {
+                    if let Some(Some(callee)) = stack.len().checked_sub(argc + 1).map(|i| stack[i])
+                    {
+                        references[callee].arg_count.get_or_insert(argc);
+                    }
+                }
+            }
+
+            match stack_effect(instr) {
+                Some((pops, pushes)) if pops <= stack.len() => {
+                    stack.truncate(stack.len() - pops);
+                    stack.extend(std::iter::repeat(None).take(pushes));
+                }
+                _ => stack.clear(),
+            }
+        }
+    }
+
+    references
+}
+
+/// Global names the VM defines before a script runs: the standard library's
+/// globals, the members of its modules (`List.length`), and the engine's own
+/// script natives (`Time.now`).
+fn builtin_names() -> &'static HashSet<String> {
+    static NAMES: OnceLock<HashSet<String>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut vm = fusabi_vm::Vm::new();
+        fusabi_vm::stdlib::register_stdlib(&mut vm);
+
+        let mut names: HashSet<String> = vm
+            .host_registry
+            .lock()
+            .map(|registry| {
+                registry
+                    .function_names()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for (name, value) in &vm.globals {
+            names.insert(name.clone());
+            if let fusabi_vm::Value::Record(fields) = value {
+                if let Ok(fields) = fields.lock() {
+                    names.extend(fields.keys().map(|field| format!("{}.{}", name, field)));
+                }
+            }
+        }
+        for name in SCRIPT_NATIVES {
+            names.insert(name.to_string());
+            if let Some((module, _)) = name.split_once('.') {
+                names.insert(module.to_string());
+            }
+        }
+        names
+    })
+}
+
+/// Resolve the global name an instruction refers to through its string
+/// constant.
+fn global_name(
+    instr: &InstructionListing,
+    constants: &[crate::disasm::ConstantListing],
+) -> Option<String> {
+    let index = usize::try_from(instr.int_operand(0)?).ok()?;
+    constants
+        .get(index)
+        .filter(|constant| constant.kind == "Str")
+        .map(|constant| constant.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{compile_source, CompileOptions};
+    use crate::value::Value;
+
+    fn metadata(source: &str) -> Metadata {
+        compile_source(source, &CompileOptions::default())
+            .unwrap()
+            .metadata
+    }
+
+    fn registry() -> HostRegistry {
+        let mut registry = HostRegistry::new();
+        registry.register_with_signature("log", HostFnSignature::fixed(1), |_args, _ctx| {
+            Ok(Value::Null)
+        });
+        registry.register_module_with_signature(
+            "math",
+            "add",
+            HostFnSignature::fixed(2),
+            |_args, _ctx| Ok(Value::Null),
+        );
+        registry
+    }
+
+    #[test]
+    fn test_host_references() {
+        let refs = metadata("math.add 1 2").host_references;
+
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].module.as_deref(), Some("math"));
+        assert_eq!(refs[0].name, "add");
+        assert_eq!(refs[0].arg_count, Some(2));
+        assert_eq!(refs[0].qualified_name(), "math.add");
+        assert_eq!(refs[0].offset, 0);
+    }
+
+    #[test]
+    fn test_stdlib_names_are_skipped() {
+        let refs = metadata("printfn (List.length [1; 2])").host_references;
+        assert!(refs.is_empty(), "{:?}", refs);
+
+        let refs = metadata("Time.now () + Random.next ()").host_references;
+        assert!(refs.is_empty(), "{:?}", refs);
+    }
+
+    #[test]
+    fn test_references_inside_functions() {
+        let refs = metadata("let shout s = log s\nshout \"hi\"").host_references;
+
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].name, "log");
+        assert_eq!(refs[0].arg_count, Some(1));
+        assert_eq!(refs[0].function, 1);
+        assert_eq!(refs[0].offset, 0);
+    }
+
+    #[test]
+    fn test_uncalled_reference() {
+        let refs = metadata("log").host_references;
+        assert_eq!(refs[0].name, "log");
+        assert_eq!(refs[0].arg_count, None);
+    }
+
+    #[test]
+    fn test_script_defined_globals_are_skipped() {
+        let refs = metadata("let helper x = x\nhelper 1").host_references;
+        assert!(refs.is_empty());
+    }
+
+    #[test]
+    fn test_link_ok() {
+        let meta = metadata("log \"x\"");
+        assert!(link_check(&meta, &registry()).is_empty());
+    }
+
+    #[test]
+    fn test_link_unresolved() {
+        let meta = metadata("let a = missing 1\nmath.sub 2");
+        let errors = link_check(&meta, &registry());
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            LinkError::UnresolvedFunction { name, .. } if name == "missing"
+        ));
+        assert!(matches!(
+            &errors[1],
+            LinkError::UnresolvedModuleFunction { module, name, .. }
+                if module == "math" && name == "sub"
+        ));
+    }
+
+    #[test]
+    fn test_link_arity_mismatch() {
+        let meta = metadata("math.add 1");
+        let errors = link_check(&meta, &registry());
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            LinkError::ArityMismatch { actual: 1, .. }
+        ));
+        assert!(errors[0].to_string().contains("math.add expects 2"));
+    }
+
+    #[test]
+    fn test_link_invalid_bytecode() {
+        let errors = link_check(&b"garbage"[..], &registry());
+        assert!(matches!(errors[0], LinkError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,406 @@
+//! Memoization of pure script results.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::capabilities::{Capabilities, Capability};
+use crate::compile::extract_bytecode_metadata;
+use crate::value::Value;
+
+/// Capabilities a script may hold and still have its result cached.
+const PURE_CAPABILITIES: [Capability; 2] = [Capability::Serialize, Capability::Logging];
+
+/// Configuration of a pool's result cache.
+///
+/// Results are cached only for executions whose effective capabilities are
+/// limited to [`Capability::Serialize`] and [`Capability::Logging`] and whose
+/// bytecode calls no host functions, so the script cannot observe anything
+/// beyond its bytecode and globals. Host functions are not gated by
+/// capabilities, so any script that references one is run every time.
+#[derive(Debug, Clone)]
+pub struct ResultCacheConfig {
+    /// Most results held at once; the least recently used is evicted first.
+    pub max_entries: usize,
+    /// How long a result stays valid (`None` keeps it until evicted).
+    pub ttl: Option<Duration>,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            ttl: Some(Duration::from_secs(300)),
+        }
+    }
+}
+
+impl ResultCacheConfig {
+    /// Create a configuration holding 1024 results for five minutes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the most results held at once.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Set how long a result stays valid.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Statistics of a pool's result cache.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    /// Results currently cached.
+    pub entries: usize,
+    /// Executions served from the cache.
+    pub hits: u64,
+    /// Cacheable executions that ran the script.
+    pub misses: u64,
+    /// Executions not cacheable because of their capabilities.
+    pub bypassed: u64,
+    /// Results dropped to stay within `max_entries`.
+    pub evictions: u64,
+    /// Results dropped after their TTL.
+    pub expirations: u64,
+}
+
+impl CacheStats {
+    /// Fraction of cacheable executions served from the cache.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Identity of a pure execution: its bytecode and input globals.
+///
+/// The inputs are kept in full and compared on lookup, so distinct inputs
+/// never share a result even if their hashes collide.
+#[derive(Debug, Clone)]
+pub(crate) struct CacheKey {
+    bytecode: Arc<[u8]>,
+    globals: BTreeMap<String, Value>,
+    hash: u64,
+}
+
+impl CacheKey {
+    pub(crate) fn new(bytecode: &[u8], globals: &BTreeMap<String, Value>) -> Self {
+        // Globals are already in key order, so equal inputs hash equally.
+        let mut hasher = DefaultHasher::new();
+        bytecode.hash(&mut hasher);
+        globals.len().hash(&mut hasher);
+        for (name, value) in globals {
+            name.hash(&mut hasher);
+            hash_value(value, &mut hasher);
+        }
+        Self {
+            bytecode: bytecode.into(),
+            globals: globals.clone(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+impl PartialEq for CacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+            && self.bytecode == other.bytecode
+            && self.globals.len() == other.globals.len()
+            && self
+                .globals
+                .iter()
+                .zip(&other.globals)
+                .all(|((a, x), (b, y))| a == b && same_value(x, y))
+    }
+}
+
+impl Eq for CacheKey {}
+
+impl Hash for CacheKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Compare values the way [`hash_value`] hashes them, so every NaN matches.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(x), Value::Float(y)) => x == y || (x.is_nan() && y.is_nan()),
+        (Value::List(xs), Value::List(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_value(x, y))
+        }
+        (Value::Map(xs), Value::Map(ys)) => {
+            xs.len() == ys.len()
+                && xs
+                    .iter()
+                    .all(|(key, x)| ys.get(key).is_some_and(|y| same_value(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Hash a value canonically, tagging each variant.
+fn hash_value(value: &Value, hasher: &mut impl Hasher) {
+    std::mem::discriminant(value).hash(hasher);
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => b.hash(hasher),
+        Value::Int(i) => i.hash(hasher),
+        // Treat -0.0 as 0.0 and every NaN alike, matching `==` where possible.
+        Value::Float(f) if *f == 0.0 => 0u64.hash(hasher),
+        Value::Float(f) if f.is_nan() => f64::NAN.to_bits().hash(hasher),
+        Value::Float(f) => f.to_bits().hash(hasher),
+        Value::String(s) | Value::Error(s) => s.hash(hasher),
+        Value::List(items) => {
+            items.len().hash(hasher);
+            for item in items {
+                hash_value(item, hasher);
+            }
+        }
+        Value::Map(map) => {
+            // Map iteration order varies between equal maps.
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            entries.len().hash(hasher);
+            for (key, value) in entries {
+                key.hash(hasher);
+                hash_value(value, hasher);
+            }
+        }
+        Value::Function(f) => {
+            f.id.hash(hasher);
+            f.name.hash(hasher);
+        }
+        Value::Bytes(bytes) => bytes.hash(hasher),
+    }
+}
+
+/// Check if an execution with `capabilities` may be cached.
+pub(crate) fn is_pure(capabilities: &Capabilities) -> bool {
+    capabilities
+        .granted()
+        .all(|cap| PURE_CAPABILITIES.contains(cap))
+}
+
+/// Check if `bytecode` references any host function.
+///
+/// Bytecode that cannot be inspected is treated as calling the host.
+pub(crate) fn calls_host(bytecode: &[u8]) -> bool {
+    extract_bytecode_metadata(bytecode)
+        .map_or(true, |metadata| !metadata.host_references.is_empty())
+}
+
+struct Entry {
+    value: Value,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+/// Bounded cache of pure execution results.
+pub(crate) struct ResultCache {
+    config: ResultCacheConfig,
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bypassed: AtomicU64,
+    evictions: AtomicU64,
+    expirations: AtomicU64,
+}
+
+impl ResultCache {
+    pub(crate) fn new(config: ResultCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            bypassed: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            expirations: AtomicU64::new(0),
+        }
+    }
+
+    /// Look up a cached result, counting a hit or miss.
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<Value> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let value = match entries.get_mut(key) {
+            Some(entry) if self.is_expired(entry, now) => {
+                entries.remove(key);
+                self.expirations.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Some(entry) => {
+                entry.last_used = now;
+                Some(entry.value.clone())
+            }
+            None => None,
+        };
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Cache a result, evicting expired results and then the least recently
+    /// used one if the cache is full.
+    pub(crate) fn insert(&self, key: CacheKey, value: Value) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        if !entries.contains_key(&key) && entries.len() >= self.config.max_entries {
+            let before = entries.len();
+            entries.retain(|_, entry| !self.is_expired(entry, now));
+            self.expirations
+                .fetch_add((before - entries.len()) as u64, Ordering::Relaxed);
+
+            if entries.len() >= self.config.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                value,
+                inserted: now,
+                last_used: now,
+            },
+        );
+    }
+
+    /// Count an execution that could not be cached.
+    pub(crate) fn bypass(&self) {
+        self.bypassed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drop every cached result.
+    pub(crate) fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.lock().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            bypassed: self.bypassed.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            expirations: self.expirations.load(Ordering::Relaxed),
+        }
+    }
+
+    fn is_expired(&self, entry: &Entry, now: Instant) -> bool {
+        self.config
+            .ttl
+            .is_some_and(|ttl| now.saturating_duration_since(entry.inserted) >= ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(n: i64) -> CacheKey {
+        let globals = BTreeMap::from([("n".to_string(), Value::Int(n))]);
+        CacheKey::new(b"FZB\x01", &globals)
+    }
+
+    #[test]
+    fn test_key_is_canonical() {
+        let mut a = BTreeMap::new();
+        a.insert("x".to_string(), Value::Float(0.0));
+        a.insert("y".to_string(), Value::List(vec![Value::Int(1)]));
+        let mut b = BTreeMap::new();
+        b.insert("y".to_string(), Value::List(vec![Value::Int(1)]));
+        b.insert("x".to_string(), Value::Float(-0.0));
+        assert_eq!(CacheKey::new(b"code", &a), CacheKey::new(b"code", &b));
+
+        assert_ne!(CacheKey::new(b"code", &a), CacheKey::new(b"other", &a));
+        b.insert("x".to_string(), Value::Int(0));
+        assert_ne!(CacheKey::new(b"code", &a), CacheKey::new(b"code", &b));
+
+        let nan = BTreeMap::from([("x".to_string(), Value::Float(f64::NAN))]);
+        assert_eq!(CacheKey::new(b"code", &nan), CacheKey::new(b"code", &nan));
+    }
+
+    #[test]
+    fn test_colliding_hashes_do_not_match() {
+        let globals = BTreeMap::new();
+        let a = CacheKey::new(b"code", &globals);
+        let mut b = CacheKey::new(b"other", &globals);
+        b.hash = a.hash;
+        assert_ne!(a, b);
+
+        let cache = ResultCache::new(ResultCacheConfig::new());
+        cache.insert(a.clone(), Value::Int(1));
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&a), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_pure_capabilities() {
+        assert!(is_pure(&Capabilities::none()));
+        assert!(is_pure(
+            &Capabilities::none()
+                .with(Capability::Serialize)
+                .with(Capability::Logging)
+        ));
+        assert!(!is_pure(&Capabilities::none().with(Capability::TimeRead)));
+        assert!(!is_pure(&Capabilities::safe_defaults()));
+    }
+
+    #[test]
+    fn test_size_bound_evicts_least_recently_used() {
+        let cache = ResultCache::new(ResultCacheConfig::new().with_max_entries(2));
+        cache.insert(key(1), Value::Int(1));
+        cache.insert(key(2), Value::Int(2));
+        assert_eq!(cache.get(&key(1)), Some(Value::Int(1)));
+
+        cache.insert(key(3), Value::Int(3));
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(1)), Some(Value::Int(1)));
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert!((stats.hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ttl_expires_results() {
+        let cache = ResultCache::new(ResultCacheConfig::new().with_ttl(Some(Duration::ZERO)));
+        cache.insert(key(1), Value::Int(1));
+        assert_eq!(cache.get(&key(1)), None);
+
+        let stats = cache.stats();
+        assert_eq!(stats.expirations, 1);
+        assert_eq!(stats.entries, 0);
+        assert_eq!(CacheStats::default().hit_rate(), 0.0);
+    }
+}
@@ -0,0 +1,290 @@
+//! Prometheus metrics for engines and pools.
+//!
+//! A [`PrometheusMetrics`] collector registers its metrics with a
+//! [`prometheus::Registry`]. Engines given it through
+//! [`EngineConfig::with_metrics`](crate::EngineConfig::with_metrics) record
+//! executions, errors and limit violations. Pools given it through
+//! [`PoolConfig::with_metrics`](crate::PoolConfig::with_metrics) pass it to
+//! their engines and also record acquisitions, acquire waits and engine
+//! counts.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use prometheus::core::Collector;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+
+use crate::error::{Error, Result};
+
+/// Prefix of every metric name.
+const NAMESPACE: &str = "fusabi";
+
+/// Prometheus collector updated by engines and pools.
+///
+/// Cloning is cheap and clones share the same metrics. To tell several pools
+/// apart, give each its own collector created with
+/// [`with_const_labels`](Self::with_const_labels), such as `pool="jobs"`.
+#[derive(Clone)]
+pub struct PrometheusMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+struct MetricsInner {
+    engines: IntGauge,
+    engines_available: IntGauge,
+    engines_in_use: IntGauge,
+    acquisitions: IntCounter,
+    timeouts: IntCounter,
+    acquire_wait: Histogram,
+    executions: IntCounter,
+    errors: IntCounterVec,
+    limit_violations: IntCounterVec,
+    execution_time: Histogram,
+}
+
+impl MetricsInner {
+    fn collectors(&self) -> Vec<Box<dyn Collector>> {
+        vec![
+            Box::new(self.engines.clone()),
+            Box::new(self.engines_available.clone()),
+            Box::new(self.engines_in_use.clone()),
+            Box::new(self.acquisitions.clone()),
+            Box::new(self.timeouts.clone()),
+            Box::new(self.acquire_wait.clone()),
+            Box::new(self.executions.clone()),
+            Box::new(self.errors.clone()),
+            Box::new(self.limit_violations.clone()),
+            Box::new(self.execution_time.clone()),
+        ]
+    }
+}
+
+impl PrometheusMetrics {
+    /// Create the metrics and register them with `registry`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the registry already holds
+    /// metrics with the same names and labels, leaving the registry as it
+    /// was.
+    pub fn new(registry: &Registry) -> Result<Self> {
+        Self::with_const_labels(registry, HashMap::new())
+    }
+
+    /// Create the metrics with labels attached to every series and register
+    /// them with `registry`.
+    pub fn with_const_labels(registry: &Registry, labels: HashMap<String, String>) -> Result<Self> {
+        let opts = |name: &str, help: &str| {
+            Opts::new(name, help)
+                .namespace(NAMESPACE)
+                .const_labels(labels.clone())
+        };
+        // 100µs up to about 26s.
+        let buckets = exponential_buckets(0.0001, 4.0, 10).map_err(metrics_error)?;
+        let histogram_opts = |name: &str, help: &str| {
+            HistogramOpts::new(name, help)
+                .namespace(NAMESPACE)
+                .const_labels(labels.clone())
+                .buckets(buckets.clone())
+        };
+
+        let inner = MetricsInner {
+            engines: IntGauge::with_opts(opts("pool_engines", "Engines created by the pool."))
+                .map_err(metrics_error)?,
+            engines_available: IntGauge::with_opts(opts(
+                "pool_engines_available",
+                "Idle engines ready to be acquired.",
+            ))
+            .map_err(metrics_error)?,
+            engines_in_use: IntGauge::with_opts(opts(
+                "pool_engines_in_use",
+                "Engines currently checked out.",
+            ))
+            .map_err(metrics_error)?,
+            acquisitions: IntCounter::with_opts(opts(
+                "pool_acquisitions_total",
+                "Attempts to acquire an engine.",
+            ))
+            .map_err(metrics_error)?,
+            timeouts: IntCounter::with_opts(opts(
+                "pool_timeouts_total",
+                "Acquire attempts that timed out.",
+            ))
+            .map_err(metrics_error)?,
+            acquire_wait: Histogram::with_opts(histogram_opts(
+                "pool_acquire_wait_seconds",
+                "Time spent waiting to acquire an engine.",
+            ))
+            .map_err(metrics_error)?,
+            executions: IntCounter::with_opts(opts("executions_total", "Script executions."))
+                .map_err(metrics_error)?,
+            errors: IntCounterVec::new(
+                opts(
+                    "execution_errors_total",
+                    "Failed script executions by error kind.",
+                ),
+                &["kind"],
+            )
+            .map_err(metrics_error)?,
+            limit_violations: IntCounterVec::new(
+                opts(
+                    "limit_violations_total",
+                    "Executions stopped by a resource limit, by limit.",
+                ),
+                &["kind"],
+            )
+            .map_err(metrics_error)?,
+            execution_time: Histogram::with_opts(histogram_opts(
+                "execution_duration_seconds",
+                "Time spent running scripts.",
+            ))
+            .map_err(metrics_error)?,
+        };
+
+        // Register all or nothing, so a failed attempt can be retried.
+        for (registered, collector) in inner.collectors().into_iter().enumerate() {
+            if let Err(e) = registry.register(collector) {
+                for collector in inner.collectors().into_iter().take(registered) {
+                    let _ = registry.unregister(collector);
+                }
+                return Err(metrics_error(e));
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Record one script execution.
+    pub(crate) fn record_execution(&self, duration: Duration, error: Option<&Error>) {
+        let inner = &self.inner;
+        inner.executions.inc();
+        inner.execution_time.observe(duration.as_secs_f64());
+        if let Some(error) = error {
+            inner.errors.with_label_values(&[error.kind()]).inc();
+            if let Error::LimitViolation(violation) = error {
+                inner
+                    .limit_violations
+                    .with_label_values(&[violation.kind()])
+                    .inc();
+            }
+        }
+    }
+
+    /// Record an acquire attempt.
+    pub(crate) fn record_acquisition(&self) {
+        self.inner.acquisitions.inc();
+    }
+
+    /// Record how long an acquire waited and whether it timed out.
+    pub(crate) fn record_wait(&self, duration: Duration, timed_out: bool) {
+        self.inner.acquire_wait.observe(duration.as_secs_f64());
+        if timed_out {
+            self.inner.timeouts.inc();
+        }
+    }
+
+    /// Set the pool engine gauges.
+    pub(crate) fn set_engines(&self, total: usize, available: usize, in_use: usize) {
+        self.inner.engines.set(total as i64);
+        self.inner.engines_available.set(available as i64);
+        self.inner.engines_in_use.set(in_use as i64);
+    }
+}
+
+impl std::fmt::Debug for PrometheusMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrometheusMetrics")
+            .field("executions", &self.inner.executions.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Render every metric in `registry` in the Prometheus text exposition format.
+pub fn encode_metrics(registry: &Registry) -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&registry.gather(), &mut buffer)
+        .map_err(metrics_error)?;
+    String::from_utf8(buffer).map_err(|e| Error::Internal(e.to_string()))
+}
+
+fn metrics_error(e: prometheus::Error) -> Error {
+    Error::invalid_config(format!("prometheus metrics: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::LimitViolation;
+
+    #[test]
+    fn test_text_exposition() {
+        let registry = Registry::new();
+        let metrics = PrometheusMetrics::new(&registry).unwrap();
+
+        metrics.record_acquisition();
+        metrics.record_wait(Duration::from_millis(2), false);
+        metrics.record_execution(Duration::from_millis(1), None);
+        metrics.record_execution(
+            Duration::from_millis(1),
+            Some(&Error::from(LimitViolation::FsOpsExceeded { limit: 1 })),
+        );
+        metrics.record_execution(Duration::from_millis(1), Some(&Error::runtime("boom")));
+        metrics.set_engines(4, 3, 1);
+
+        let text = encode_metrics(&registry).unwrap();
+        for line in [
+            "fusabi_pool_engines 4",
+            "fusabi_pool_engines_available 3",
+            "fusabi_pool_engines_in_use 1",
+            "fusabi_pool_acquisitions_total 1",
+            "fusabi_pool_timeouts_total 0",
+            "fusabi_pool_acquire_wait_seconds_count 1",
+            "fusabi_executions_total 3",
+            "fusabi_execution_errors_total{kind=\"limit_violation\"} 1",
+            "fusabi_execution_errors_total{kind=\"runtime\"} 1",
+            "fusabi_limit_violations_total{kind=\"fs_ops\"} 1",
+            "fusabi_execution_duration_seconds_count 3",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {:?} in\n{}",
+                line,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_const_labels_and_duplicate_registration() {
+        let registry = Registry::new();
+        let labels = |pool: &str| HashMap::from([("pool".to_string(), pool.to_string())]);
+        PrometheusMetrics::with_const_labels(&registry, labels("a")).unwrap();
+        PrometheusMetrics::with_const_labels(&registry, labels("b")).unwrap();
+
+        let result = PrometheusMetrics::with_const_labels(&registry, labels("a"));
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+
+        let text = encode_metrics(&registry).unwrap();
+        assert!(text.contains("fusabi_executions_total{pool=\"a\"} 0"));
+        assert!(text.contains("fusabi_executions_total{pool=\"b\"} 0"));
+    }
+
+    #[test]
+    fn test_failed_registration_is_rolled_back() {
+        let registry = Registry::new();
+        let clash = IntCounter::new("fusabi_executions_total", "Script executions.").unwrap();
+        registry.register(Box::new(clash.clone())).unwrap();
+
+        let result = PrometheusMetrics::new(&registry);
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+        assert_eq!(registry.gather().len(), 1);
+
+        registry.unregister(Box::new(clash)).unwrap();
+        PrometheusMetrics::new(&registry).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+//! Per-execution overrides of an engine's configuration.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::capabilities::Capabilities;
+use crate::error::{Error, Result};
+use crate::host_context::HostContext;
+use crate::limits::Limits;
+use crate::sandbox::SandboxConfig;
+use crate::value::Value;
+
+/// Options for a single execution.
+///
+/// Overrides can only narrow the engine's baseline configuration: limits are
+/// combined keeping the tighter bound, capabilities are intersected, and a
+/// sandbox override is enforced in addition to the engine's sandbox. This lets
+/// trusted and untrusted scripts share one pool.
+#[derive(Clone, Default)]
+pub struct ExecOptions {
+    /// Limits combined with the engine's limits.
+    pub limits: Option<Limits>,
+    /// Capabilities intersected with the engine's capabilities.
+    pub capabilities: Option<Capabilities>,
+    /// Sandbox policy enforced alongside the engine's sandbox.
+    pub sandbox: Option<SandboxConfig>,
+    /// Global bindings visible to the script and to host functions.
+    pub globals: BTreeMap<String, Value>,
+    /// Host context replacing the engine's for this execution.
+    pub host_context: Option<Arc<dyn HostContext>>,
+    /// Random seed for this execution, in deterministic mode.
+    pub seed: Option<u64>,
+}
+
+impl ExecOptions {
+    /// Create options that leave the engine's configuration unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Narrow the resource limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Narrow the granted capabilities.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Narrow the sandbox policy.
+    ///
+    /// Only the access policies apply; the working directory and temp
+    /// isolation stay as configured on the engine.
+    pub fn with_sandbox(mut self, sandbox: SandboxConfig) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Bind a global for the script.
+    pub fn with_global(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.globals.insert(name.into(), value.into());
+        self
+    }
+
+    /// Use a host context for this execution.
+    pub fn with_host_context(mut self, host: Arc<dyn HostContext>) -> Self {
+        self.host_context = Some(host);
+        self
+    }
+
+    /// Seed random numbers for this execution.
+    ///
+    /// Only applies to engines in deterministic mode; pass the seed from an
+    /// [`ExecutionReport`](crate::ExecutionReport) to repeat that execution.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Apply `options` on top of these, keeping the narrower of each.
+    ///
+    /// Limits keep the tighter bound and capabilities are intersected. Only
+    /// one sandbox restriction can be enforced, so both setting one is an
+    /// error. Globals, host context and seed come from `options`.
+    pub(crate) fn narrow(&self, options: ExecOptions) -> Result<ExecOptions> {
+        let sandbox = match (&self.sandbox, options.sandbox) {
+            (Some(_), Some(_)) => {
+                return Err(Error::invalid_config(
+                    "a sandbox restriction is already in force",
+                ))
+            }
+            (ours, theirs) => theirs.or_else(|| ours.clone()),
+        };
+        Ok(ExecOptions {
+            limits: match (&self.limits, options.limits) {
+                (Some(ours), Some(theirs)) => Some(ours.narrow(&theirs)),
+                (ours, theirs) => theirs.or_else(|| ours.clone()),
+            },
+            capabilities: match (&self.capabilities, options.capabilities) {
+                (Some(ours), Some(theirs)) => Some(ours.intersect(&theirs)),
+                (ours, theirs) => theirs.or_else(|| ours.clone()),
+            },
+            sandbox,
+            ..options
+        })
+    }
+
+    /// Render each global as a top-level `let` binding.
+    pub(crate) fn global_bindings(&self) -> Result<Vec<String>> {
+        self.globals
+            .iter()
+            .map(|(name, value)| {
+                if !is_identifier(name) {
+                    return Err(Error::invalid_config(format!(
+                        "invalid global name: {:?}",
+                        name
+                    )));
+                }
+                let literal = literal(value).ok_or_else(|| {
+                    Error::invalid_config(format!(
+                        "global `{}` of type {} cannot be bound in a script",
+                        name,
+                        value.value_type()
+                    ))
+                })?;
+                Ok(format!("let {} = {}", name, literal))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for ExecOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecOptions")
+            .field("limits", &self.limits)
+            .field("capabilities", &self.capabilities)
+            .field("sandbox", &self.sandbox)
+            .field("globals", &self.globals)
+            .field("host_context", &self.host_context.is_some())
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
+
+/// Words a global cannot be named after.
+const RESERVED: &[&str] = &[
+    "and", "as", "do", "done", "downto", "else", "false", "for", "fun", "function", "if", "in",
+    "let", "match", "module", "mutable", "not", "of", "open", "or", "rec", "then", "to", "true",
+    "type", "when", "while", "with", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED.contains(&name)
+}
+
+/// Float literal that always reads back as a float, e.g. `1.0e300`.
+fn float_literal(f: f64) -> String {
+    let text = format!("{:?}", f);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => {
+            format!("{}.0e{}", mantissa, exponent)
+        }
+        _ => text,
+    }
+}
+
+/// Fusabi literal for a value, if it has one.
+fn literal(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => "()".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(n) => n.to_string(),
+        Value::Float(f) if f.is_finite() => float_literal(*f),
+        Value::String(s) => {
+            let mut out = String::with_capacity(s.len() + 2);
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+            out
+        }
+        Value::List(items) => {
+            let items = items.iter().map(literal).collect::<Option<Vec<_>>>()?;
+            format!("[{}]", items.join("; "))
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_bindings() {
+        let options = ExecOptions::new()
+            .with_global("n", 42i64)
+            .with_global("name", "a \"b\"\n")
+            .with_global("xs", Value::List(vec![Value::Int(1), Value::Float(2.0)]))
+            .with_global("flag", true);
+
+        assert_eq!(
+            options.global_bindings().unwrap(),
+            vec![
+                "let flag = true",
+                "let n = 42",
+                "let name = \"a \\\"b\\\"\\n\"",
+                "let xs = [1; 2.0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        assert_eq!(float_literal(2.0), "2.0");
+        assert_eq!(float_literal(1e300), "1.0e300");
+        assert_eq!(float_literal(-2.5e-10), "-2.5e-10");
+        assert_eq!(float_literal(1e-7), "1.0e-7");
+    }
+
+    #[test]
+    fn test_invalid_globals() {
+        for name in ["1x", "let", "if", "fun"] {
+            let bad_name = ExecOptions::new().with_global(name, 1i64);
+            assert!(matches!(
+                bad_name.global_bindings(),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+
+        let bad_value = ExecOptions::new().with_global("b", Value::Bytes(vec![1]));
+        assert!(matches!(
+            bad_value.global_bindings(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_narrow_keeps_tighter_options() {
+        use crate::capabilities::Capability;
+
+        let base = ExecOptions::new()
+            .with_limits(Limits::default().with_max_instructions(100))
+            .with_capabilities(Capabilities::none().with(Capability::Logging));
+        let narrowed = base
+            .narrow(
+                ExecOptions::new()
+                    .with_limits(Limits::default().with_max_instructions(1_000))
+                    .with_capabilities(Capabilities::all())
+                    .with_global("x", 1i64),
+            )
+            .unwrap();
+        assert_eq!(narrowed.limits.unwrap().max_instructions, Some(100));
+        assert_eq!(narrowed.capabilities.unwrap().to_names(), ["logging"]);
+        assert_eq!(narrowed.globals["x"], Value::Int(1));
+
+        let sandboxed = ExecOptions::new().with_sandbox(SandboxConfig::locked());
+        assert!(sandboxed
+            .narrow(ExecOptions::new())
+            .unwrap()
+            .sandbox
+            .is_some());
+        assert!(matches!(
+            sandboxed.narrow(ExecOptions::new().with_sandbox(SandboxConfig::locked())),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
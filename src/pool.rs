@@ -1,25 +1,52 @@
 //! Engine pool for concurrent Fusabi execution.
 
-// TEMP: AsyncEnginePool scaffolding pending wire-up in M1 harness-skills; see ISSUE-REF. Remove these #[allow(dead_code)] once used.
-
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError};
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::{Condvar, Mutex};
 
+use crate::autoscale::{AutoscaleConfig, Autoscaler, PoolSample, ScaleEvent};
+use crate::batch::{self, BatchOptions};
+use crate::bundle::Bundle;
 use crate::capabilities::Capabilities;
-use crate::engine::{Engine, EngineConfig};
+use crate::compile::{compile_source, CompileOptions};
+use crate::engine::{Engine, EngineConfig, HostRegistry, Prelude};
 use crate::error::{Error, Result};
+use crate::histogram::{LatencyHistogram, LatencySnapshot};
+use crate::host_context::HostContext;
+use crate::job::{Job, JobHandle, JobShared, QueuedJob, Script};
 use crate::limits::Limits;
+use crate::link::link_check;
+use crate::memo::{self, CacheKey, CacheStats, ResultCache, ResultCacheConfig};
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PrometheusMetrics;
+use crate::options::ExecOptions;
+use crate::priority::{Priority, PriorityConfig, PriorityStats, WaitQueue};
 use crate::sandbox::SandboxConfig;
 use crate::value::Value;
 
+/// Hook run on every engine the pool creates.
+pub type EngineInitializer = Arc<dyn Fn(&mut Engine) -> Result<()> + Send + Sync>;
+
 /// Configuration for an engine pool.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PoolConfig {
-    /// Number of engines in the pool.
+    /// Number of engines created when the pool starts.
     pub size: usize,
+    /// Fewest engines the autoscaler will shrink the pool to.
+    pub min_size: usize,
+    /// Most engines the pool will hold.
+    pub max_size: usize,
+    /// Autoscaling policy (`None` keeps the pool size fixed).
+    pub autoscale: Option<AutoscaleConfig>,
     /// Engine configuration template.
     pub engine_config: EngineConfig,
     /// Maximum time to wait for an engine.
@@ -28,16 +55,94 @@ pub struct PoolConfig {
     pub lazy_init: bool,
     /// Maximum idle time before an engine is recycled.
     pub max_idle_time: Option<Duration>,
+    /// Maximum number of uses before an engine is recycled.
+    pub max_uses: Option<u64>,
+    /// Maximum age before an engine is recycled.
+    pub max_lifetime: Option<Duration>,
+    /// How often a reaper thread recycles expired idle engines (`None`
+    /// recycles lazily on acquire only).
+    pub reaper_interval: Option<Duration>,
+    /// Whether to link-check each script against the host registry once
+    /// before its first dispatch.
+    pub link_check: bool,
+    /// Host registry shared by all engines.
+    pub registry: Option<Arc<HostRegistry>>,
+    /// Initializer run on each engine after it is created.
+    pub on_engine_create: Option<EngineInitializer>,
+    /// Prelude scripts loaded into each engine after it is created.
+    pub prelude: Vec<Prelude>,
+    /// Host context attached to every engine, which also receives pool
+    /// metrics.
+    pub host_context: Option<Arc<dyn HostContext>>,
+    /// Weights and timeouts of the acquisition priority classes.
+    pub priorities: PriorityConfig,
+    /// Worker threads running submitted jobs (`None` uses `max_size`).
+    pub job_workers: Option<usize>,
+    /// Most submitted jobs waiting for a worker before submission is
+    /// rejected.
+    pub job_queue_capacity: usize,
+    /// Cache of pure bytecode results (`None` disables caching).
+    pub result_cache: Option<ResultCacheConfig>,
+    /// Prometheus metrics updated by the pool and its engines.
+    #[cfg(feature = "metrics-prometheus")]
+    pub metrics: Option<PrometheusMetrics>,
+}
+
+impl std::fmt::Debug for PoolConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_struct("PoolConfig");
+        f.field("size", &self.size)
+            .field("min_size", &self.min_size)
+            .field("max_size", &self.max_size)
+            .field("autoscale", &self.autoscale)
+            .field("engine_config", &self.engine_config)
+            .field("acquire_timeout", &self.acquire_timeout)
+            .field("lazy_init", &self.lazy_init)
+            .field("max_idle_time", &self.max_idle_time)
+            .field("max_uses", &self.max_uses)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("reaper_interval", &self.reaper_interval)
+            .field("link_check", &self.link_check)
+            .field("registry", &self.registry)
+            .field("on_engine_create", &self.on_engine_create.is_some())
+            .field("prelude", &self.prelude.len())
+            .field("host_context", &self.host_context.is_some())
+            .field("priorities", &self.priorities)
+            .field("job_workers", &self.job_workers)
+            .field("job_queue_capacity", &self.job_queue_capacity)
+            .field("result_cache", &self.result_cache);
+        #[cfg(feature = "metrics-prometheus")]
+        f.field("metrics", &self.metrics);
+        f.finish()
+    }
 }
 
 impl Default for PoolConfig {
     fn default() -> Self {
+        let size = num_cpus::get().max(2);
         Self {
-            size: num_cpus::get().max(2),
+            size,
+            min_size: size,
+            max_size: size,
+            autoscale: None,
             engine_config: EngineConfig::default(),
             acquire_timeout: Duration::from_secs(30),
             lazy_init: false,
             max_idle_time: Some(Duration::from_secs(300)),
+            max_uses: None,
+            max_lifetime: None,
+            reaper_interval: None,
+            link_check: false,
+            registry: None,
+            on_engine_create: None,
+            prelude: Vec::new(),
+            host_context: None,
+            priorities: PriorityConfig::default(),
+            job_workers: None,
+            job_queue_capacity: 1024,
+            result_cache: None,
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: None,
         }
     }
 }
@@ -45,12 +150,48 @@ impl Default for PoolConfig {
 impl PoolConfig {
     /// Create a new pool configuration with the specified size.
     pub fn new(size: usize) -> Self {
+        let size = size.max(1);
         Self {
-            size: size.max(1),
+            size,
+            min_size: size,
+            max_size: size,
             ..Default::default()
         }
     }
 
+    /// Create an elastic pool configuration that starts with `min` engines,
+    /// grows up to `max` under load, and autoscales with the default policy.
+    pub fn elastic(min: usize, max: usize) -> Self {
+        Self::new(min)
+            .with_min_size(min)
+            .with_max_size(max)
+            .with_autoscale(AutoscaleConfig::default())
+    }
+
+    /// Set the fewest engines the autoscaler will shrink the pool to.
+    ///
+    /// A pool starts with at least `min` engines, raising `size` if needed.
+    pub fn with_min_size(mut self, min: usize) -> Self {
+        self.min_size = min;
+        self
+    }
+
+    /// Set the most engines the pool will hold.
+    ///
+    /// The pool grows past `size` through the autoscaler, or on demand when
+    /// engines are created lazily: an acquire that finds no idle engine
+    /// creates one immediately rather than waiting for a release.
+    pub fn with_max_size(mut self, max: usize) -> Self {
+        self.max_size = max.max(1);
+        self
+    }
+
+    /// Enable autoscaling between `min_size` and `max_size`.
+    pub fn with_autoscale(mut self, policy: AutoscaleConfig) -> Self {
+        self.autoscale = Some(policy);
+        self
+    }
+
     /// Set the engine configuration.
     pub fn with_engine_config(mut self, config: EngineConfig) -> Self {
         self.engine_config = config;
@@ -75,6 +216,12 @@ impl PoolConfig {
         self
     }
 
+    /// Enable structural bytecode verification for all engines.
+    pub fn with_bytecode_verification(mut self, verify: bool) -> Self {
+        self.engine_config.verify_bytecode = verify;
+        self
+    }
+
     /// Set the acquire timeout.
     pub fn with_acquire_timeout(mut self, timeout: Duration) -> Self {
         self.acquire_timeout = timeout;
@@ -92,6 +239,138 @@ impl PoolConfig {
         self.max_idle_time = time;
         self
     }
+
+    /// Set the maximum number of uses before an engine is recycled.
+    pub fn with_max_uses(mut self, uses: Option<u64>) -> Self {
+        self.max_uses = uses;
+        self
+    }
+
+    /// Set the maximum engine age before it is recycled.
+    pub fn with_max_lifetime(mut self, lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = lifetime;
+        self
+    }
+
+    /// Run a reaper thread that recycles expired idle engines periodically.
+    pub fn with_reaper_interval(mut self, interval: Option<Duration>) -> Self {
+        self.reaper_interval = interval;
+        self
+    }
+
+    /// Share a host registry with all engines in the pool.
+    pub fn with_registry(mut self, registry: Arc<HostRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set an initializer run on each engine the pool creates.
+    ///
+    /// The initializer runs after the shared registry is installed, for both
+    /// eagerly and lazily created engines. An error fails engine creation.
+    pub fn on_engine_create<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Engine) -> Result<()> + Send + Sync + 'static,
+    {
+        self.on_engine_create = Some(Arc::new(f));
+        self
+    }
+
+    /// Add a prelude script loaded into every engine the pool creates.
+    ///
+    /// Accepts source or compiled bytecode. Preludes load in the order added,
+    /// after the initializer, and their definitions are visible to every
+    /// execution on the engine. An engine whose prelude fails is discarded.
+    pub fn with_prelude(mut self, prelude: impl Into<Prelude>) -> Self {
+        self.prelude.push(prelude.into());
+        self
+    }
+
+    /// Record pool and engine activity in Prometheus metrics.
+    ///
+    /// Engines the pool creates also record their executions, unless the
+    /// engine configuration sets its own metrics.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn with_metrics(mut self, metrics: PrometheusMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Attach a host context to every engine the pool creates.
+    ///
+    /// Script logs and execution metrics go to it, its cancellation is
+    /// honored by every execution, and the pool reports its own metrics to it.
+    pub fn with_host_context(mut self, host: Arc<dyn HostContext>) -> Self {
+        self.host_context = Some(host);
+        self
+    }
+
+    /// Create an engine from this configuration.
+    fn create_engine(&self) -> Result<Engine> {
+        #[allow(unused_mut)]
+        let mut config = self.engine_config.clone();
+        #[cfg(feature = "metrics-prometheus")]
+        if config.metrics.is_none() {
+            config.metrics = self.metrics.clone();
+        }
+        let mut engine = Engine::new(config)?;
+        if let Some(registry) = &self.registry {
+            engine.set_registry(Arc::clone(registry));
+        }
+        if let Some(host) = &self.host_context {
+            engine.set_host_context(Arc::clone(host));
+        }
+        if let Some(init) = &self.on_engine_create {
+            init(&mut engine)?;
+        }
+        for prelude in &self.prelude {
+            engine.load_prelude(prelude)?;
+        }
+        Ok(engine)
+    }
+
+    /// Set the share of released engines handed to a priority class.
+    pub fn with_priority_weight(mut self, priority: Priority, weight: u32) -> Self {
+        self.priorities.class_mut(priority).weight = weight.max(1);
+        self
+    }
+
+    /// Set the acquire timeout for a priority class.
+    pub fn with_priority_timeout(mut self, priority: Priority, timeout: Duration) -> Self {
+        self.priorities.class_mut(priority).acquire_timeout = Some(timeout);
+        self
+    }
+
+    /// Enable host-function link checking before first dispatch.
+    pub fn with_link_check(mut self, link_check: bool) -> Self {
+        self.link_check = link_check;
+        self
+    }
+
+    /// Set the number of worker threads running submitted jobs.
+    pub fn with_job_workers(mut self, workers: usize) -> Self {
+        self.job_workers = Some(workers.max(1));
+        self
+    }
+
+    /// Set how many submitted jobs may wait for a worker.
+    pub fn with_job_queue_capacity(mut self, capacity: usize) -> Self {
+        self.job_queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Memoize results of pure bytecode executions.
+    ///
+    /// Results are keyed by the bytecode and the execution's globals, and
+    /// only cached when the effective capabilities are at most
+    /// [`Capability::Serialize`](crate::Capability::Serialize) and
+    /// [`Capability::Logging`](crate::Capability::Logging). Scripts that call
+    /// host functions, which capabilities do not gate, and executions with
+    /// their own [`ExecOptions::limits`] are never cached.
+    pub fn with_result_cache(mut self, config: ResultCacheConfig) -> Self {
+        self.result_cache = Some(config);
+        self
+    }
 }
 
 /// Statistics about pool usage.
@@ -99,6 +378,14 @@ impl PoolConfig {
 pub struct PoolStats {
     /// Total number of engines.
     pub total: usize,
+    /// Configured minimum number of engines.
+    pub min_size: usize,
+    /// Configured maximum number of engines.
+    pub max_size: usize,
+    /// Number of callers currently waiting for an engine.
+    pub waiting: usize,
+    /// Most callers waiting at once since the last reset.
+    pub peak_waiting: usize,
     /// Number of available engines.
     pub available: usize,
     /// Number of engines currently in use.
@@ -111,8 +398,40 @@ pub struct PoolStats {
     pub timeouts: u64,
     /// Total execution count.
     pub executions: u64,
-    /// Total execution time.
+    /// Total time spent executing scripts.
     pub total_execution_time: Duration,
+    /// Time callers waited to acquire an engine.
+    pub acquire_wait: LatencySnapshot,
+    /// Time spent executing scripts.
+    pub execution_time: LatencySnapshot,
+    /// Time engines were held by a handle, from acquire to release.
+    pub hold_time: LatencySnapshot,
+    /// Failed executions by [`Error::kind`].
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Number of times the autoscaler grew the pool.
+    pub scale_ups: u64,
+    /// Number of times the autoscaler shrank the pool.
+    pub scale_downs: u64,
+    /// Most recent autoscaler resize.
+    pub last_scale_event: Option<ScaleEvent>,
+    /// Engines recycled for exceeding `max_idle_time`.
+    pub idle_evictions: u64,
+    /// Engines recycled for exceeding `max_uses`.
+    pub use_evictions: u64,
+    /// Engines recycled for exceeding `max_lifetime`.
+    pub lifetime_evictions: u64,
+    /// Engines discarded after panicking.
+    pub poisoned: u64,
+    /// Engines discarded because a prelude failed to load.
+    pub prelude_failures: u64,
+    /// Wait statistics per priority class, highest first.
+    pub priorities: Vec<PriorityStats>,
+    /// Submitted jobs waiting for a worker.
+    pub queued_jobs: usize,
+    /// Jobs rejected because the job queue was full.
+    pub rejected_jobs: u64,
+    /// Result cache statistics, when caching is enabled.
+    pub result_cache: Option<CacheStats>,
 }
 
 impl PoolStats {
@@ -124,12 +443,66 @@ impl PoolStats {
             self.total_execution_time / self.executions as u32
         }
     }
+
+    /// Total number of recycled engines.
+    pub fn evictions(&self) -> u64 {
+        self.idle_evictions + self.use_evictions + self.lifetime_evictions
+    }
+
+    /// Get wait statistics for a priority class.
+    pub fn priority(&self, priority: Priority) -> Option<&PriorityStats> {
+        self.priorities.iter().find(|s| s.priority == priority)
+    }
+
+    /// Total number of failed executions.
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Summary of a pool warmup.
+#[derive(Debug, Clone, Default)]
+pub struct WarmupReport {
+    /// Engines ready in the pool.
+    pub ready: usize,
+    /// Engines the pool should start with (`PoolConfig::size`).
+    pub target: usize,
+    /// Errors from engines that failed to initialize and were discarded.
+    pub failures: Vec<String>,
+    /// Time taken to warm up.
+    pub elapsed: Duration,
+}
+
+impl WarmupReport {
+    /// Check if the pool holds all the engines it should start with.
+    pub fn is_ready(&self) -> bool {
+        self.ready >= self.target
+    }
+}
+
+/// Summary of a graceful pool shutdown.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// In-flight executions that finished before the deadline.
+    pub completed: usize,
+    /// IDs of engines still running at the deadline, which were cancelled.
+    pub cancelled: Vec<u64>,
+    /// Idle engines dropped.
+    pub engines_dropped: usize,
+    /// Time taken to shut down.
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    /// Check if every in-flight execution finished before the deadline.
+    pub fn is_clean(&self) -> bool {
+        self.cancelled.is_empty()
+    }
 }
 
 /// Internal wrapper for pooled engines.
 struct PooledEngine {
-    engine: Engine,
-    #[allow(dead_code)]
+    engine: Arc<Engine>,
     created_at: Instant,
     last_used: Instant,
     use_count: u64,
@@ -139,7 +512,7 @@ impl PooledEngine {
     fn new(engine: Engine) -> Self {
         let now = Instant::now();
         Self {
-            engine,
+            engine: Arc::new(engine),
             created_at: now,
             last_used: now,
             use_count: 0,
@@ -151,10 +524,36 @@ impl PooledEngine {
         self.use_count += 1;
     }
 
-    #[allow(dead_code)]
     fn idle_time(&self) -> Duration {
         self.last_used.elapsed()
     }
+
+    /// Check whether the engine has outlived the pool's recycling limits.
+    fn expiry(&self, config: &PoolConfig) -> Option<Eviction> {
+        if config.max_uses.is_some_and(|max| self.use_count >= max) {
+            Some(Eviction::MaxUses)
+        } else if config
+            .max_lifetime
+            .is_some_and(|max| self.created_at.elapsed() >= max)
+        {
+            Some(Eviction::MaxLifetime)
+        } else if config
+            .max_idle_time
+            .is_some_and(|max| self.idle_time() >= max)
+        {
+            Some(Eviction::Idle)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why an engine was recycled.
+#[derive(Debug, Clone, Copy)]
+enum Eviction {
+    Idle,
+    MaxUses,
+    MaxLifetime,
 }
 
 /// A handle to a pooled engine.
@@ -162,33 +561,102 @@ impl PooledEngine {
 /// When dropped, the engine is returned to the pool.
 pub struct PoolHandle {
     engine: Option<PooledEngine>,
-    return_tx: Sender<PooledEngine>,
-    stats: Arc<PoolStatsInner>,
+    pool: Arc<PoolInner>,
+    poisoned: AtomicBool,
     start_time: Instant,
 }
 
 impl PoolHandle {
     /// Execute source code with the pooled engine.
     pub fn execute(&self, source: &str) -> Result<Value> {
-        let engine = self
-            .engine
-            .as_ref()
-            .ok_or(Error::Internal("pool handle has no engine".into()))?;
-        engine.engine.execute(source)
+        self.run(|engine| engine.execute(source))
     }
 
     /// Execute bytecode with the pooled engine.
     pub fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
+        self.run(|engine| engine.execute_bytecode(bytecode))
+    }
+
+    /// Execute a script bundle with the pooled engine.
+    pub fn execute_bundle(&self, bundle: &Bundle) -> Result<Value> {
+        self.run(|engine| engine.execute_bundle(bundle))
+    }
+
+    /// Execute source code with per-execution options.
+    ///
+    /// The options can only narrow the engine's configuration.
+    pub fn execute_with(&self, source: &str, options: ExecOptions) -> Result<Value> {
+        self.run(|engine| engine.execute_with(source, options))
+    }
+
+    /// Execute bytecode with per-execution options.
+    pub fn execute_bytecode_with(&self, bytecode: &[u8], options: ExecOptions) -> Result<Value> {
+        self.run(|engine| engine.execute_bytecode_with(bytecode, options))
+    }
+
+    /// Execute source code with a host context for this execution only.
+    pub fn execute_with_host(&self, source: &str, host: Arc<dyn HostContext>) -> Result<Value> {
+        self.run(|engine| engine.execute_with_host(source, host))
+    }
+
+    /// Execute bytecode with a host context for this execution only.
+    pub fn execute_bytecode_with_host(
+        &self,
+        bytecode: &[u8],
+        host: Arc<dyn HostContext>,
+    ) -> Result<Value> {
+        self.run(|engine| engine.execute_bytecode_with_host(bytecode, host))
+    }
+
+    /// Run `f` on the engine, converting a panic into [`Error::EnginePoisoned`].
+    ///
+    /// A poisoned engine is discarded instead of being returned to the pool.
+    fn run<T>(&self, f: impl FnOnce(&Engine) -> Result<T>) -> Result<T> {
         let engine = self
             .engine
             .as_ref()
             .ok_or(Error::Internal("pool handle has no engine".into()))?;
-        engine.engine.execute_bytecode(bytecode)
+        if self.is_poisoned() {
+            return Err(Error::EnginePoisoned(
+                "engine panicked during a previous execution".into(),
+            ));
+        }
+
+        let start = Instant::now();
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| f(&engine.engine))).unwrap_or_else(|payload| {
+                self.poisoned.store(true, Ordering::Relaxed);
+                let message = panic_message(payload.as_ref());
+                tracing::error!(
+                    engine_id = engine.engine.id(),
+                    panic = %message,
+                    "pooled engine panicked"
+                );
+                let error = Error::EnginePoisoned(message);
+                // The engine unwound before it could record the execution.
+                engine
+                    .engine
+                    .record_execution(start.elapsed(), Some(&error));
+                Err(error)
+            });
+        self.pool
+            .stats
+            .record_execution(start.elapsed(), result.as_ref().err());
+        result
+    }
+
+    /// Check if the engine panicked while held by this handle.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
     }
 
     /// Get a reference to the underlying engine.
     pub fn engine(&self) -> &Engine {
-        &self.engine.as_ref().unwrap().engine
+        &self.pooled().engine
+    }
+
+    fn pooled(&self) -> &PooledEngine {
+        self.engine.as_ref().expect("pool handle has no engine")
     }
 
     /// Cancel the current execution.
@@ -202,19 +670,45 @@ impl PoolHandle {
 impl Drop for PoolHandle {
     fn drop(&mut self) {
         if let Some(mut engine) = self.engine.take() {
-            // Update stats
-            let elapsed = self.start_time.elapsed();
-            self.stats.releases.fetch_add(1, Ordering::Relaxed);
-            self.stats.add_execution_time(elapsed);
+            let pool = &self.pool;
 
-            engine.mark_used();
+            // Update stats
+            pool.stats.releases.fetch_add(1, Ordering::Relaxed);
+            pool.stats.hold_time.record(self.start_time.elapsed());
+            pool.finish(&engine);
+
+            if self.is_poisoned() {
+                pool.stats.poisoned.fetch_add(1, Ordering::Relaxed);
+                drop(engine);
+                pool.replace_engine();
+            } else {
+                engine.mark_used();
+
+                // A cancelled engine would refuse every later execution.
+                let context = engine.engine.context();
+                if context.is_cancelled() {
+                    context.reset(engine.engine.config().limits.clone());
+                }
 
-            // Return engine to pool
-            let _ = self.return_tx.try_send(engine);
+                // Return engine to pool
+                pool.release(engine);
+            }
+            pool.publish_engines();
         }
     }
 }
 
+/// Extract the message from a panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
 /// Internal stats tracking.
 struct PoolStatsInner {
     acquisitions: AtomicU64,
@@ -222,6 +716,23 @@ struct PoolStatsInner {
     timeouts: AtomicU64,
     executions: AtomicU64,
     execution_time_nanos: AtomicU64,
+    scale_ups: AtomicU64,
+    scale_downs: AtomicU64,
+    idle_evictions: AtomicU64,
+    use_evictions: AtomicU64,
+    lifetime_evictions: AtomicU64,
+    poisoned: AtomicU64,
+    prelude_failures: AtomicU64,
+    rejected_jobs: AtomicU64,
+    acquire_wait: LatencyHistogram,
+    execution_time: LatencyHistogram,
+    hold_time: LatencyHistogram,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    classes: [ClassCounters; 3],
+    /// Acquire wait time since the autoscaler's last sample.
+    window_wait_nanos: AtomicU64,
+    /// Acquires since the autoscaler's last sample.
+    window_waits: AtomicU64,
 }
 
 impl PoolStatsInner {
@@ -232,278 +743,1658 @@ impl PoolStatsInner {
             timeouts: AtomicU64::new(0),
             executions: AtomicU64::new(0),
             execution_time_nanos: AtomicU64::new(0),
+            scale_ups: AtomicU64::new(0),
+            scale_downs: AtomicU64::new(0),
+            idle_evictions: AtomicU64::new(0),
+            use_evictions: AtomicU64::new(0),
+            lifetime_evictions: AtomicU64::new(0),
+            poisoned: AtomicU64::new(0),
+            prelude_failures: AtomicU64::new(0),
+            rejected_jobs: AtomicU64::new(0),
+            acquire_wait: LatencyHistogram::new(),
+            execution_time: LatencyHistogram::new(),
+            hold_time: LatencyHistogram::new(),
+            errors: Mutex::new(BTreeMap::new()),
+            classes: Default::default(),
+            window_wait_nanos: AtomicU64::new(0),
+            window_waits: AtomicU64::new(0),
         }
     }
 
-    fn add_execution_time(&self, duration: Duration) {
+    fn record_execution(&self, duration: Duration, error: Option<&Error>) {
         self.executions.fetch_add(1, Ordering::Relaxed);
         self.execution_time_nanos
             .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+        self.execution_time.record(duration);
+        if let Some(error) = error {
+            *self.errors.lock().entry(error.kind()).or_insert(0) += 1;
+        }
+    }
+
+    fn record_wait(&self, duration: Duration) {
+        self.acquire_wait.record(duration);
+        self.window_waits.fetch_add(1, Ordering::Relaxed);
+        self.window_wait_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Clear the latency histograms and error counts.
+    fn reset_latency(&self) {
+        self.acquire_wait.reset();
+        self.execution_time.reset();
+        self.hold_time.reset();
+        self.errors.lock().clear();
+    }
+
+    /// Take the average wait since the previous call.
+    fn take_avg_wait(&self) -> Option<Duration> {
+        let waits = self.window_waits.swap(0, Ordering::Relaxed);
+        let nanos = self.window_wait_nanos.swap(0, Ordering::Relaxed);
+        (waits > 0).then(|| Duration::from_nanos(nanos / waits))
     }
 }
 
-/// A pool of Fusabi engines for concurrent execution.
-///
-/// The pool manages a fixed number of engines and provides thread-safe
-/// access to them for parallel script execution.
-pub struct EnginePool {
-    config: PoolConfig,
-    engine_rx: Receiver<PooledEngine>,
-    engine_tx: Sender<PooledEngine>,
-    stats: Arc<PoolStatsInner>,
-    shutdown: AtomicBool,
-    created: AtomicUsize,
+/// Per-priority-class wait counters.
+#[derive(Default)]
+struct ClassCounters {
+    acquisitions: AtomicU64,
+    timeouts: AtomicU64,
+    wait_nanos: AtomicU64,
+    max_wait_nanos: AtomicU64,
 }
 
-impl EnginePool {
-    /// Create a new engine pool with the given configuration.
-    pub fn new(config: PoolConfig) -> Result<Self> {
-        let (tx, rx) = bounded(config.size);
+impl ClassCounters {
+    fn record_wait(&self, duration: Duration) {
+        let nanos = duration.as_nanos() as u64;
+        self.wait_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_wait_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
 
-        let pool = Self {
-            config: config.clone(),
-            engine_rx: rx,
-            engine_tx: tx.clone(),
-            stats: Arc::new(PoolStatsInner::new()),
-            shutdown: AtomicBool::new(false),
-            created: AtomicUsize::new(0),
-        };
+    fn snapshot(&self, priority: Priority, waiting: usize) -> PriorityStats {
+        PriorityStats {
+            priority,
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            waiting,
+            total_wait: Duration::from_nanos(self.wait_nanos.load(Ordering::Relaxed)),
+            max_wait: Duration::from_nanos(self.max_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
 
-        // Pre-create engines if not lazy
-        if !config.lazy_init {
-            for _ in 0..config.size {
-                let engine = Engine::new(config.engine_config.clone())?;
-                tx.send(PooledEngine::new(engine))
-                    .map_err(|_| Error::Internal("failed to initialize pool".into()))?;
-                pool.created.fetch_add(1, Ordering::Relaxed);
+/// Most scripts remembered as linked before the oldest is forgotten.
+const LINKED_CAPACITY: usize = 4096;
+
+/// Bounded set of scripts that passed the link check, keyed by a hash of
+/// their source or bytecode. The oldest entry is forgotten first.
+#[derive(Default)]
+struct LinkedScripts {
+    keys: HashSet<u64>,
+    order: VecDeque<u64>,
+}
+
+impl LinkedScripts {
+    fn key(kind: &str, script: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        script.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn contains(&self, key: u64) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn insert(&mut self, key: u64) {
+        if self.keys.insert(key) {
+            self.order.push_back(key);
+            if self.order.len() > LINKED_CAPACITY {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.keys.remove(&oldest);
+                }
             }
         }
+    }
 
-        Ok(pool)
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.keys.len()
     }
+}
 
-    /// Acquire an engine from the pool.
+/// Result cache lookup for a bytecode execution.
+enum Memo {
+    /// A cached result.
+    Hit(Value),
+    /// Not cached yet; store the result under this key.
+    Miss(CacheKey),
+    /// Not cacheable, or caching is disabled.
+    Uncached,
+}
+
+/// State shared between the pool and its background threads.
+struct PoolInner {
+    config: PoolConfig,
+    idle: WaitQueue<PooledEngine>,
+    stats: Arc<PoolStatsInner>,
+    shutdown: AtomicBool,
+    created: AtomicUsize,
+    linked: Mutex<LinkedScripts>,
+    /// Scripts known to call no host functions, so their results may be cached.
+    host_free: Mutex<LinkedScripts>,
+    /// Engines currently checked out, by engine ID.
+    in_flight: Mutex<HashMap<u64, Arc<Engine>>>,
+    /// Signalled whenever an in-flight engine is released.
+    drained: Condvar,
+    last_scale_event: Mutex<Option<ScaleEvent>>,
+    /// Queue feeding the job workers, started on first submission.
+    jobs: Mutex<Option<Sender<QueuedJob>>>,
+    /// Submitted jobs not yet started, cancelled, or failed.
+    queued_jobs: Arc<AtomicUsize>,
+    /// Memoized results of pure executions.
+    result_cache: Option<ResultCache>,
+}
+
+impl PoolInner {
+    /// Hand out an acquired engine, or release it if the pool has shut down.
     ///
-    /// Blocks until an engine is available or the timeout expires.
-    pub fn acquire(&self) -> Result<PoolHandle> {
+    /// The check is made under the in-flight lock, so a graceful shutdown
+    /// either sees the engine in flight or the acquisition fails.
+    fn handle(self: &Arc<Self>, engine: PooledEngine) -> Result<PoolHandle> {
+        let mut in_flight = self.in_flight.lock();
         if self.shutdown.load(Ordering::Relaxed) {
+            drop(in_flight);
+            self.release(engine);
             return Err(Error::PoolShutdown);
         }
+        in_flight.insert(engine.engine.id(), Arc::clone(&engine.engine));
+        drop(in_flight);
+        self.publish_engines();
+        Ok(PoolHandle {
+            engine: Some(engine),
+            pool: Arc::clone(self),
+            poisoned: AtomicBool::new(false),
+            start_time: Instant::now(),
+        })
+    }
 
-        self.stats.acquisitions.fetch_add(1, Ordering::Relaxed);
+    /// Update the engine count gauges of the configured metrics.
+    fn publish_engines(&self) {
+        let total = self.created.load(Ordering::Relaxed);
+        let available = self.idle.idle_len();
+        let in_use = total.saturating_sub(available);
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = &self.config.metrics {
+            metrics.set_engines(total, available, in_use);
+        }
+        if let Some(host) = &self.config.host_context {
+            host.gauge("fusabi_pool_engines", total as f64, &[]);
+            host.gauge("fusabi_pool_engines_available", available as f64, &[]);
+            host.gauge("fusabi_pool_engines_in_use", in_use as f64, &[]);
+        }
+    }
 
-        // Try to get an existing engine
-        match self.engine_rx.recv_timeout(self.config.acquire_timeout) {
-            Ok(engine) => Ok(PoolHandle {
-                engine: Some(engine),
-                return_tx: self.engine_tx.clone(),
-                stats: self.stats.clone(),
-                start_time: Instant::now(),
-            }),
-            Err(_) => {
-                // Try lazy creation if we haven't reached capacity
-                if self.config.lazy_init {
-                    let created = self.created.load(Ordering::Relaxed);
-                    if created < self.config.size
-                        && self
-                            .created
-                            .compare_exchange(
-                                created,
-                                created + 1,
-                                Ordering::SeqCst,
-                                Ordering::Relaxed,
-                            )
-                            .is_ok()
-                    {
-                        let engine = Engine::new(self.config.engine_config.clone())?;
-                        return Ok(PoolHandle {
-                            engine: Some(PooledEngine::new(engine)),
-                            return_tx: self.engine_tx.clone(),
-                            stats: self.stats.clone(),
-                            start_time: Instant::now(),
-                        });
-                    }
-                }
+    /// Count an acquire attempt.
+    fn count_acquisition(&self) {
+        self.stats.acquisitions.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = &self.config.metrics {
+            metrics.record_acquisition();
+        }
+        if let Some(host) = &self.config.host_context {
+            host.counter("fusabi_pool_acquisitions_total", 1, &[]);
+        }
+    }
 
-                self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
-                Err(Error::PoolTimeout)
+    /// Create an engine, reporting engines discarded for a failed prelude.
+    fn create_engine(&self) -> Result<Engine> {
+        self.config.create_engine().map_err(|e| {
+            if let Error::Prelude(message) = &e {
+                self.stats.prelude_failures.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(error = %message, "discarding engine whose prelude failed");
             }
-        }
+            e
+        })
     }
 
-    /// Try to acquire an engine without blocking.
-    pub fn try_acquire(&self) -> Result<PoolHandle> {
+    /// Check the pool is accepting acquisitions and count the attempt.
+    fn begin_acquire(&self) -> Result<()> {
         if self.shutdown.load(Ordering::Relaxed) {
             return Err(Error::PoolShutdown);
         }
+        self.count_acquisition();
+        Ok(())
+    }
 
-        self.stats.acquisitions.fetch_add(1, Ordering::Relaxed);
+    /// Get the acquire timeout for a priority class.
+    fn acquire_timeout(&self, priority: Priority) -> Duration {
+        self.config
+            .priorities
+            .class(priority)
+            .acquire_timeout
+            .unwrap_or(self.config.acquire_timeout)
+    }
 
-        match self.engine_rx.try_recv() {
-            Ok(engine) => Ok(PoolHandle {
-                engine: Some(engine),
-                return_tx: self.engine_tx.clone(),
-                stats: self.stats.clone(),
-                start_time: Instant::now(),
-            }),
-            Err(TryRecvError::Empty) => {
-                // Try lazy creation
-                if self.config.lazy_init {
-                    let created = self.created.load(Ordering::Relaxed);
-                    if created < self.config.size
-                        && self
-                            .created
-                            .compare_exchange(
-                                created,
-                                created + 1,
-                                Ordering::SeqCst,
-                                Ordering::Relaxed,
-                            )
-                            .is_ok()
-                    {
-                        let engine = Engine::new(self.config.engine_config.clone())?;
-                        return Ok(PoolHandle {
-                            engine: Some(PooledEngine::new(engine)),
-                            return_tx: self.engine_tx.clone(),
-                            stats: self.stats.clone(),
-                            start_time: Instant::now(),
-                        });
-                    }
-                }
-                Err(Error::PoolExhausted {
-                    count: self.config.size,
-                })
+    /// Turn the result of waiting for an engine into a handle.
+    fn complete_acquire(
+        self: &Arc<Self>,
+        priority: Priority,
+        received: Option<PooledEngine>,
+        waited: Duration,
+    ) -> Result<PoolHandle> {
+        self.stats.record_wait(waited);
+
+        let engine = match received {
+            Some(engine) => Some(self.recycle(engine)?),
+            None if self.shutdown.load(Ordering::Relaxed) => return Err(Error::PoolShutdown),
+            // Try lazy creation if we haven't reached capacity
+            None if self.config.lazy_init => self.try_grow()?,
+            None => None,
+        };
+        if self.shutdown.load(Ordering::Relaxed) {
+            if let Some(engine) = engine {
+                self.release(engine);
+            }
+            return Err(Error::PoolShutdown);
+        }
+
+        let counters = &self.stats.classes[priority.index()];
+        counters.record_wait(waited);
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = &self.config.metrics {
+            metrics.record_wait(waited, engine.is_none());
+        }
+        if let Some(host) = &self.config.host_context {
+            let tags = [("priority", priority.as_str())];
+            host.histogram(
+                "fusabi_pool_acquire_wait_seconds",
+                waited.as_secs_f64(),
+                &tags,
+            );
+            if engine.is_none() {
+                host.counter("fusabi_pool_timeouts_total", 1, &tags);
+            }
+        }
+        match engine {
+            Some(engine) => {
+                counters.acquisitions.fetch_add(1, Ordering::Relaxed);
+                self.handle(engine)
+            }
+            None => {
+                counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                Err(Error::PoolTimeout)
             }
-            Err(TryRecvError::Disconnected) => Err(Error::PoolShutdown),
         }
     }
 
-    /// Execute source code using a pooled engine.
-    ///
-    /// Convenience method that acquires an engine, executes, and returns it.
-    pub fn execute(&self, source: &str) -> Result<Value> {
-        let handle = self.acquire()?;
-        handle.execute(source)
+    /// Mark an engine as no longer in flight.
+    fn finish(&self, engine: &PooledEngine) {
+        self.in_flight.lock().remove(&engine.engine.id());
+        self.drained.notify_all();
     }
 
-    /// Execute bytecode using a pooled engine.
-    pub fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
-        let handle = self.acquire()?;
-        handle.execute_bytecode(bytecode)
+    /// Return an engine to a waiting caller or the idle queue.
+    fn release(&self, engine: PooledEngine) {
+        if self.shutdown.load(Ordering::Relaxed) {
+            drop(engine);
+            self.created.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+        if self.idle.idle_len() >= self.config.max_size {
+            // Only happens if the accounting is off. Drop the engine and keep
+            // counts honest.
+            tracing::warn!(
+                engine_id = engine.engine.id(),
+                "engine pool queue full, discarding engine"
+            );
+            self.created.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+        self.idle.push(engine);
     }
 
-    /// Get current pool statistics.
-    pub fn stats(&self) -> PoolStats {
-        let available = self.engine_rx.len();
-        let created = self.created.load(Ordering::Relaxed);
-        let in_use = created.saturating_sub(available);
+    /// Create a fresh engine in place of a discarded one.
+    fn replace_engine(&self) {
+        if self.shutdown.load(Ordering::Relaxed) {
+            self.created.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+        match self.create_engine() {
+            Ok(engine) => self.release(PooledEngine::new(engine)),
+            Err(e) => {
+                tracing::warn!(error = %e, "engine pool failed to replace engine");
+                self.created.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
 
-        let execution_nanos = self.stats.execution_time_nanos.load(Ordering::Relaxed);
+    /// Replace an engine that has outlived the recycling limits.
+    fn recycle(&self, engine: PooledEngine) -> Result<PooledEngine> {
+        let Some(reason) = engine.expiry(&self.config) else {
+            return Ok(engine);
+        };
 
-        PoolStats {
-            total: self.config.size,
-            available,
-            in_use,
-            acquisitions: self.stats.acquisitions.load(Ordering::Relaxed),
-            releases: self.stats.releases.load(Ordering::Relaxed),
-            timeouts: self.stats.timeouts.load(Ordering::Relaxed),
-            executions: self.stats.executions.load(Ordering::Relaxed),
-            total_execution_time: Duration::from_nanos(execution_nanos),
+        let counter = match reason {
+            Eviction::Idle => &self.stats.idle_evictions,
+            Eviction::MaxUses => &self.stats.use_evictions,
+            Eviction::MaxLifetime => &self.stats.lifetime_evictions,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(
+            engine_id = engine.engine.id(),
+            reason = ?reason,
+            uses = engine.use_count,
+            "recycling pooled engine"
+        );
+        drop(engine);
+
+        match self.create_engine() {
+            Ok(engine) => Ok(PooledEngine::new(engine)),
+            Err(e) => {
+                self.created.fetch_sub(1, Ordering::SeqCst);
+                Err(e)
+            }
         }
     }
 
-    /// Get the pool configuration.
-    pub fn config(&self) -> &PoolConfig {
-        &self.config
+    /// Recycle every expired engine that is currently idle.
+    fn reap(&self) {
+        for _ in 0..self.idle.idle_len() {
+            let Some(engine) = self.idle.try_pop() else {
+                break;
+            };
+            match self.recycle(engine) {
+                Ok(engine) => self.release(engine),
+                Err(e) => tracing::warn!(error = %e, "engine pool failed to replace engine"),
+            }
+        }
+        self.publish_engines();
     }
 
-    /// Check if the pool is healthy.
+    /// Create an engine for a lazy pool with none idle, so the caller does
+    /// not wait out the acquire timeout before the pool grows.
+    fn grow_on_demand(&self) -> Result<Option<PooledEngine>> {
+        if self.config.lazy_init && self.idle.idle_len() == 0 {
+            self.try_grow()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Create a new engine if the pool is below `max_size`.
+    fn try_grow(&self) -> Result<Option<PooledEngine>> {
+        let mut created = self.created.load(Ordering::Relaxed);
+        loop {
+            if created >= self.config.max_size {
+                return Ok(None);
+            }
+            match self.created.compare_exchange(
+                created,
+                created + 1,
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => created = current,
+            }
+        }
+
+        match self.create_engine() {
+            Ok(engine) => Ok(Some(PooledEngine::new(engine))),
+            Err(e) => {
+                self.created.fetch_sub(1, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    /// Sample the pool and apply one autoscaling decision.
+    fn autoscale(&self, scaler: &mut Autoscaler) {
+        let sample = PoolSample {
+            total: self.created.load(Ordering::Relaxed),
+            available: self.idle.idle_len(),
+            waiting: self.idle.total_waiting(),
+            min_size: self.config.min_size,
+            max_size: self.config.max_size,
+            avg_wait: self.stats.take_avg_wait(),
+        };
+
+        let Some((target, reason)) = scaler.decide(sample, Instant::now()) else {
+            return;
+        };
+
+        let from = sample.total;
+        if target > from {
+            for _ in from..target {
+                match self.try_grow() {
+                    Ok(Some(engine)) => self.release(engine),
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "engine pool failed to create engine");
+                        break;
+                    }
+                }
+            }
+        } else {
+            for _ in target..from {
+                match self.idle.try_pop() {
+                    Some(engine) => {
+                        drop(engine);
+                        self.created.fetch_sub(1, Ordering::SeqCst);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let to = self.created.load(Ordering::Relaxed);
+        if to == from {
+            return;
+        }
+        self.publish_engines();
+        if to > from {
+            self.stats.scale_ups.fetch_add(1, Ordering::Relaxed);
+            tracing::info!(from, to, reason = %reason, "engine pool scaled up");
+        } else {
+            self.stats.scale_downs.fetch_add(1, Ordering::Relaxed);
+            tracing::info!(from, to, reason = %reason, "engine pool scaled down");
+        }
+        *self.last_scale_event.lock() = Some(ScaleEvent {
+            from,
+            to,
+            reason,
+            at: Instant::now(),
+        });
+    }
+}
+
+/// A pool of Fusabi engines for concurrent execution.
+///
+/// The pool manages between `min_size` and `max_size` engines and provides
+/// thread-safe access to them for parallel script execution. With an
+/// [`AutoscaleConfig`], a background thread resizes the pool within those
+/// bounds.
+pub struct EnginePool {
+    inner: Arc<PoolInner>,
+}
+
+impl EnginePool {
+    /// Create a new engine pool with the given configuration.
+    ///
+    /// # Errors
+    ///
+    /// Unless engines are created lazily, fails if any engine cannot be
+    /// created, including with [`Error::Prelude`] when a prelude fails.
+    pub fn new(mut config: PoolConfig) -> Result<Self> {
+        config.size = config.size.max(config.min_size);
+        config.max_size = config.max_size.max(config.size).max(1);
+
+        // Compile source preludes once rather than in every engine.
+        config.prelude = config
+            .prelude
+            .iter()
+            .map(|p| p.to_bytecode().map(Prelude::Bytecode))
+            .collect::<Result<_>>()
+            .map_err(|e| Error::prelude(e.to_string()))?;
+
+        let inner = Arc::new(PoolInner {
+            config: config.clone(),
+            idle: WaitQueue::new(&config.priorities),
+            stats: Arc::new(PoolStatsInner::new()),
+            shutdown: AtomicBool::new(false),
+            created: AtomicUsize::new(0),
+            linked: Mutex::new(LinkedScripts::default()),
+            host_free: Mutex::new(LinkedScripts::default()),
+            in_flight: Mutex::new(HashMap::new()),
+            drained: Condvar::new(),
+            last_scale_event: Mutex::new(None),
+            jobs: Mutex::new(None),
+            queued_jobs: Arc::new(AtomicUsize::new(0)),
+            result_cache: config.result_cache.clone().map(ResultCache::new),
+        });
+
+        // Pre-create engines if not lazy
+        if !config.lazy_init {
+            for _ in 0..config.size {
+                match inner.create_engine() {
+                    Ok(engine) => {
+                        inner.idle.push(PooledEngine::new(engine));
+                        inner.created.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        inner.publish_engines();
+
+        if let Some(policy) = config.autoscale.clone() {
+            let interval = policy.interval;
+            let mut scaler = Autoscaler::new(policy);
+            spawn_maintenance(&inner, "autoscaler", interval, move |pool| {
+                pool.autoscale(&mut scaler)
+            })?;
+        }
+
+        if let Some(interval) = config.reaper_interval {
+            spawn_maintenance(&inner, "reaper", interval, PoolInner::reap)?;
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// Acquire an engine from the pool.
+    ///
+    /// Blocks until an engine is available or the timeout expires. Equivalent
+    /// to [`acquire_with_priority`](Self::acquire_with_priority) with
+    /// [`Priority::Normal`].
+    pub fn acquire(&self) -> Result<PoolHandle> {
+        self.acquire_with_priority(Priority::Normal)
+    }
+
+    /// Acquire an engine in the given priority class.
+    ///
+    /// Released engines are shared between waiting classes by weight, so
+    /// higher classes are served first without starving lower ones. The wait
+    /// is bounded by the class's timeout, or the pool's acquire timeout.
+    pub fn acquire_with_priority(&self, priority: Priority) -> Result<PoolHandle> {
+        let inner = &self.inner;
+        inner.begin_acquire()?;
+
+        let start = Instant::now();
+        let received = match inner.grow_on_demand()? {
+            Some(engine) => Some(engine),
+            None => inner
+                .idle
+                .pop_timeout(priority, inner.acquire_timeout(priority)),
+        };
+        inner.complete_acquire(priority, received, start.elapsed())
+    }
+
+    /// Create engines until the pool holds `size` of them.
+    ///
+    /// Each engine gets the initializer and prelude as usual. Engines that
+    /// fail to initialize are discarded and listed in the report, whose
+    /// [`is_ready`](WarmupReport::is_ready) tells whether the whole pool is
+    /// ready.
+    pub fn warmup(&self) -> WarmupReport {
+        let inner = &self.inner;
+        let start = Instant::now();
+        let target = inner.config.size;
+        let missing = target.saturating_sub(inner.created.load(Ordering::Relaxed));
+
+        let mut failures = Vec::new();
+        for _ in 0..missing {
+            match inner.try_grow() {
+                Ok(Some(engine)) => inner.release(engine),
+                Ok(None) => break,
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        inner.publish_engines();
+
+        let report = WarmupReport {
+            ready: inner.created.load(Ordering::Relaxed),
+            target,
+            failures,
+            elapsed: start.elapsed(),
+        };
+        if report.is_ready() {
+            tracing::info!(engines = report.ready, "engine pool warmed up");
+        } else {
+            tracing::warn!(
+                ready = report.ready,
+                target,
+                failures = report.failures.len(),
+                "engine pool warmup incomplete"
+            );
+        }
+        report
+    }
+
+    /// Try to acquire an engine without blocking.
+    pub fn try_acquire(&self) -> Result<PoolHandle> {
+        let inner = &self.inner;
+        if inner.shutdown.load(Ordering::Relaxed) {
+            return Err(Error::PoolShutdown);
+        }
+
+        inner.count_acquisition();
+
+        match inner.idle.try_pop() {
+            Some(engine) => inner.handle(inner.recycle(engine)?),
+            None => {
+                // Try lazy creation
+                if inner.config.lazy_init {
+                    if let Some(engine) = inner.try_grow()? {
+                        return inner.handle(engine);
+                    }
+                }
+                Err(Error::PoolExhausted {
+                    count: inner.created.load(Ordering::Relaxed),
+                })
+            }
+        }
+    }
+
+    /// Execute source code using a pooled engine.
+    ///
+    /// Convenience method that acquires an engine, executes, and returns it.
+    pub fn execute(&self, source: &str) -> Result<Value> {
+        let handle = self.acquire()?;
+        self.link_source(&handle, source)?;
+        handle.execute(source)
+    }
+
+    /// Execute bytecode using a pooled engine.
+    pub fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
+        self.execute_bytecode_with(bytecode, ExecOptions::default())
+    }
+
+    /// Execute source code with per-execution options using a pooled engine.
+    ///
+    /// The options can only narrow the pool's engine configuration, so one
+    /// pool can serve both trusted and untrusted scripts.
+    pub fn execute_with(&self, source: &str, options: ExecOptions) -> Result<Value> {
+        let handle = self.acquire()?;
+        self.link_source(&handle, source)?;
+        handle.execute_with(source, options)
+    }
+
+    /// Execute bytecode with per-execution options using a pooled engine.
+    ///
+    /// With a result cache, a cached result is returned without acquiring an
+    /// engine.
+    pub fn execute_bytecode_with(&self, bytecode: &[u8], options: ExecOptions) -> Result<Value> {
+        self.memoized(bytecode, &options, || {
+            let handle = self.acquire()?;
+            if self.inner.config.link_check {
+                self.ensure_linked(&handle, bytecode)?;
+            }
+            handle.execute_bytecode_with(bytecode, options.clone())
+        })
+    }
+
+    /// Run a job on a pooled engine acquired in the job's priority class.
+    pub fn execute_job(&self, job: &Job) -> Result<Value> {
+        let handle = self.acquire_with_priority(job.priority)?;
+        self.run_job(&handle, job)
+    }
+
+    /// Run a job on an acquired engine.
+    fn run_job(&self, handle: &PoolHandle, job: &Job) -> Result<Value> {
+        let options = job.options.clone();
+        match &job.script {
+            Script::Source(source) => {
+                self.link_source(handle, source)?;
+                handle.execute_with(source, options)
+            }
+            Script::Bytecode(bytecode) => self.memoized(bytecode, &options, || {
+                if self.inner.config.link_check {
+                    self.ensure_linked(handle, bytecode)?;
+                }
+                handle.execute_bytecode_with(bytecode, options.clone())
+            }),
+        }
+    }
+
+    /// Run a bytecode execution through the result cache, if enabled.
+    fn memoized(
+        &self,
+        bytecode: &[u8],
+        options: &ExecOptions,
+        run: impl FnOnce() -> Result<Value>,
+    ) -> Result<Value> {
+        match self.cache_lookup(bytecode, options)? {
+            Memo::Hit(value) => Ok(value),
+            Memo::Miss(key) => {
+                let result = run();
+                self.cache_store(key, &result);
+                result
+            }
+            Memo::Uncached => run(),
+        }
+    }
+
+    /// Look up a bytecode execution in the result cache.
+    ///
+    /// Executions are cacheable when their effective capabilities are pure
+    /// and they set no limits of their own, which could make the same script
+    /// fail where a cached run succeeded.
+    fn cache_lookup(&self, bytecode: &[u8], options: &ExecOptions) -> Result<Memo> {
+        let Some(cache) = &self.inner.result_cache else {
+            return Ok(Memo::Uncached);
+        };
+        if self.inner.shutdown.load(Ordering::Relaxed) {
+            return Err(Error::PoolShutdown);
+        }
+
+        let capabilities = &self.inner.config.engine_config.capabilities;
+        let effective = match &options.capabilities {
+            Some(narrowed) => capabilities.intersect(narrowed),
+            None => capabilities.clone(),
+        };
+        if !memo::is_pure(&effective) || options.limits.is_some() || !self.host_free(bytecode) {
+            cache.bypass();
+            return Ok(Memo::Uncached);
+        }
+
+        let key = CacheKey::new(bytecode, &options.globals);
+        Ok(match cache.get(&key) {
+            Some(value) => Memo::Hit(value),
+            None => Memo::Miss(key),
+        })
+    }
+
+    /// Check if a script calls no host functions, remembering the answer.
+    ///
+    /// Host functions are not gated by capabilities, so a script that calls
+    /// one may observe or change the host even when its capabilities are pure.
+    fn host_free(&self, bytecode: &[u8]) -> bool {
+        let key = LinkedScripts::key("bytecode", bytecode);
+        if self.inner.host_free.lock().contains(key) {
+            return true;
+        }
+        if memo::calls_host(bytecode) {
+            return false;
+        }
+        self.inner.host_free.lock().insert(key);
+        true
+    }
+
+    /// Cache a successful result under the key of a [`Memo::Miss`].
+    fn cache_store(&self, key: CacheKey, result: &Result<Value>) {
+        if let (Some(cache), Ok(value)) = (&self.inner.result_cache, result) {
+            cache.insert(key, value.clone());
+        }
+    }
+
+    /// Drop every result in the result cache.
+    pub fn clear_result_cache(&self) {
+        if let Some(cache) = &self.inner.result_cache {
+            cache.clear();
+        }
+    }
+
+    /// Queue a job to run on the pool's job workers.
+    ///
+    /// Returns immediately with a handle to the job's result. Jobs are taken
+    /// from the queue in submission order and acquire their engine in the
+    /// job's priority class. Fails with [`Error::Overloaded`] once
+    /// `job_queue_capacity` jobs are waiting.
+    pub fn submit(&self, job: Job) -> Result<JobHandle> {
+        let inner = &self.inner;
+        if inner.shutdown.load(Ordering::Relaxed) {
+            return Err(Error::PoolShutdown);
+        }
+
+        let mut jobs = inner.jobs.lock();
+        let sender = match &*jobs {
+            Some(sender) => sender,
+            None => jobs.insert(spawn_job_workers(inner)?),
+        };
+
+        let capacity = inner.config.job_queue_capacity;
+        let reserved =
+            inner
+                .queued_jobs
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                    (queued < capacity).then_some(queued + 1)
+                });
+        if reserved.is_err() {
+            inner.stats.rejected_jobs.fetch_add(1, Ordering::Relaxed);
+            return Err(Error::Overloaded { capacity });
+        }
+
+        let shared = JobShared::new(Arc::clone(&inner.queued_jobs));
+        let queued = QueuedJob {
+            job,
+            shared: Arc::clone(&shared),
+        };
+        match sender.send(queued) {
+            Ok(()) => Ok(JobHandle::new(shared)),
+            Err(_) => {
+                shared.finish(Err(Error::PoolShutdown));
+                Err(Error::PoolShutdown)
+            }
+        }
+    }
+
+    /// Run jobs in parallel across the pool, returning results in input order.
+    ///
+    /// Uses up to the pool's maximum size of concurrent jobs and collects
+    /// every error. See [`execute_many_with`](Self::execute_many_with).
+    pub fn execute_many<I>(&self, jobs: I) -> Vec<Result<Value>>
+    where
+        I: IntoIterator<Item = Job>,
+    {
+        self.execute_many_with(jobs, &BatchOptions::default())
+    }
+
+    /// Run jobs in parallel across the pool with batch options.
+    ///
+    /// Results are returned in input order. With
+    /// [`fail_fast`](BatchOptions::fail_fast), jobs not yet started when a job
+    /// fails are skipped and report [`Error::Cancelled`].
+    pub fn execute_many_with<I>(&self, jobs: I, options: &BatchOptions) -> Vec<Result<Value>>
+    where
+        I: IntoIterator<Item = Job>,
+    {
+        batch::execute_many(self, jobs.into_iter().collect(), options)
+    }
+
+    /// Execute a script bundle using a pooled engine.
+    pub fn execute_bundle(&self, bundle: &Bundle) -> Result<Value> {
+        let handle = self.acquire()?;
+        if self.inner.config.link_check {
+            for module in bundle.modules() {
+                self.ensure_linked(&handle, module.bytecode())?;
+            }
+        }
+        handle.execute_bundle(bundle)
+    }
+
+    /// Link-check a source script the first time it is dispatched, if the
+    /// pool is configured to.
+    ///
+    /// The check is keyed on the source text, so a script is compiled for it
+    /// only once.
+    fn link_source(&self, handle: &PoolHandle, source: &str) -> Result<()> {
+        if !self.inner.config.link_check {
+            return Ok(());
+        }
+        let key = LinkedScripts::key("source", source.as_bytes());
+        if self.inner.linked.lock().contains(key) {
+            return Ok(());
+        }
+
+        // Sources that fail to compile are left for the engine to report.
+        if let Ok(compiled) = compile_source(source, &CompileOptions::default()) {
+            self.check_links(handle, &compiled.bytecode)?;
+            self.inner.linked.lock().insert(key);
+        }
+        Ok(())
+    }
+
+    /// Link-check a script the first time it is dispatched.
+    fn ensure_linked(&self, handle: &PoolHandle, bytecode: &[u8]) -> Result<()> {
+        let key = LinkedScripts::key("bytecode", bytecode);
+        if self.inner.linked.lock().contains(key) {
+            return Ok(());
+        }
+        self.check_links(handle, bytecode)?;
+        self.inner.linked.lock().insert(key);
+        Ok(())
+    }
+
+    /// Run the link check, reporting every error it finds.
+    fn check_links(&self, handle: &PoolHandle, bytecode: &[u8]) -> Result<()> {
+        let errors = link_check(bytecode, handle.engine().registry());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Link(errors))
+        }
+    }
+
+    /// Get current pool statistics.
+    pub fn stats(&self) -> PoolStats {
+        let inner = &self.inner;
+        let available = inner.idle.idle_len();
+        let created = inner.created.load(Ordering::Relaxed);
+        let in_use = created.saturating_sub(available);
+
+        let execution_nanos = inner.stats.execution_time_nanos.load(Ordering::Relaxed);
+
+        PoolStats {
+            total: created,
+            available,
+            in_use,
+            min_size: inner.config.min_size,
+            max_size: inner.config.max_size,
+            waiting: inner.idle.total_waiting(),
+            peak_waiting: inner.idle.peak_waiting(),
+            acquisitions: inner.stats.acquisitions.load(Ordering::Relaxed),
+            releases: inner.stats.releases.load(Ordering::Relaxed),
+            timeouts: inner.stats.timeouts.load(Ordering::Relaxed),
+            executions: inner.stats.executions.load(Ordering::Relaxed),
+            total_execution_time: Duration::from_nanos(execution_nanos),
+            acquire_wait: inner.stats.acquire_wait.snapshot(),
+            execution_time: inner.stats.execution_time.snapshot(),
+            hold_time: inner.stats.hold_time.snapshot(),
+            errors: inner.stats.errors.lock().clone(),
+            scale_ups: inner.stats.scale_ups.load(Ordering::Relaxed),
+            scale_downs: inner.stats.scale_downs.load(Ordering::Relaxed),
+            last_scale_event: inner.last_scale_event.lock().clone(),
+            idle_evictions: inner.stats.idle_evictions.load(Ordering::Relaxed),
+            use_evictions: inner.stats.use_evictions.load(Ordering::Relaxed),
+            lifetime_evictions: inner.stats.lifetime_evictions.load(Ordering::Relaxed),
+            poisoned: inner.stats.poisoned.load(Ordering::Relaxed),
+            prelude_failures: inner.stats.prelude_failures.load(Ordering::Relaxed),
+            priorities: Priority::ALL
+                .iter()
+                .map(|&p| inner.stats.classes[p.index()].snapshot(p, inner.idle.waiting(p)))
+                .collect(),
+            queued_jobs: inner.queued_jobs.load(Ordering::Acquire),
+            rejected_jobs: inner.stats.rejected_jobs.load(Ordering::Relaxed),
+            result_cache: inner.result_cache.as_ref().map(ResultCache::stats),
+        }
+    }
+
+    /// Reset the latency histograms, error counts and peak queue depth.
+    ///
+    /// Cumulative counters such as `acquisitions` are left untouched. Pair
+    /// with [`stats`](Self::stats) to report metrics per interval.
+    pub fn reset_stats(&self) {
+        self.inner.stats.reset_latency();
+        self.inner.idle.reset_peak_waiting();
+    }
+
+    /// Get the pool configuration.
+    pub fn config(&self) -> &PoolConfig {
+        &self.inner.config
+    }
+
+    /// Check if the pool is healthy.
     pub fn is_healthy(&self) -> bool {
-        !self.shutdown.load(Ordering::Relaxed) && !self.engine_rx.is_empty()
+        !self.inner.shutdown.load(Ordering::Relaxed) && self.inner.idle.idle_len() > 0
+    }
+
+    /// Shut down the pool, preventing new acquisitions.
+    ///
+    /// Callers blocked in `acquire` return [`Error::PoolShutdown`], as do
+    /// submitted jobs still queued. Engines still checked out are dropped
+    /// when their handles are released.
+    pub fn shutdown(&self) {
+        self.inner.shutdown.store(true, Ordering::Relaxed);
+        self.inner.idle.close();
+        self.inner.jobs.lock().take();
+    }
+
+    /// Shut down the pool, waiting up to `deadline` for in-flight executions.
+    ///
+    /// New acquisitions fail immediately. Outstanding handles get until the
+    /// deadline to be released; engines still running after that are
+    /// cancelled. All idle engines are dropped, cleaning up their sandboxes.
+    pub fn shutdown_graceful(&self, deadline: Duration) -> ShutdownReport {
+        let inner = &self.inner;
+        let start = Instant::now();
+        self.shutdown();
+
+        let mut in_flight = inner.in_flight.lock();
+        let outstanding: Vec<u64> = in_flight.keys().copied().collect();
+        let until = start + deadline;
+        while !in_flight.is_empty() {
+            if inner.drained.wait_until(&mut in_flight, until).timed_out() {
+                break;
+            }
+        }
+
+        let cancelled: Vec<u64> = in_flight.keys().copied().collect();
+        for engine in in_flight.values() {
+            engine.cancel();
+        }
+        let completed = outstanding
+            .iter()
+            .filter(|id| !in_flight.contains_key(id))
+            .count();
+        drop(in_flight);
+
+        let mut engines_dropped = 0;
+        while let Some(engine) = inner.idle.try_pop() {
+            drop(engine);
+            inner.created.fetch_sub(1, Ordering::SeqCst);
+            engines_dropped += 1;
+        }
+
+        inner.publish_engines();
+
+        let report = ShutdownReport {
+            completed,
+            cancelled,
+            engines_dropped,
+            elapsed: start.elapsed(),
+        };
+        tracing::info!(
+            completed = report.completed,
+            cancelled = report.cancelled.len(),
+            engines_dropped = report.engines_dropped,
+            "engine pool shut down"
+        );
+        report
+    }
+
+    /// Check if the pool has been shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.shutdown.load(Ordering::Relaxed)
     }
+}
+
+impl std::fmt::Debug for EnginePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stats = self.stats();
+        f.debug_struct("EnginePool")
+            .field("size", &stats.total)
+            .field("available", &stats.available)
+            .field("in_use", &stats.in_use)
+            .field("shutdown", &self.is_shutdown())
+            .finish()
+    }
+}
+
+/// Spawn a background thread that runs `task` every `interval`.
+///
+/// The thread holds only a weak reference and exits once the pool is
+/// dropped or shut down.
+fn spawn_maintenance<F>(
+    pool: &Arc<PoolInner>,
+    name: &str,
+    interval: Duration,
+    mut task: F,
+) -> Result<()>
+where
+    F: FnMut(&PoolInner) + Send + 'static,
+{
+    let pool: Weak<PoolInner> = Arc::downgrade(pool);
+    thread::Builder::new()
+        .name(format!("fusabi-pool-{}", name))
+        .spawn(move || loop {
+            thread::sleep(interval);
+            let Some(inner) = pool.upgrade() else { break };
+            if inner.shutdown.load(Ordering::Relaxed) {
+                break;
+            }
+            task(&inner);
+        })
+        .map_err(|e| Error::Internal(format!("failed to spawn pool {}: {}", name, e)))?;
+    Ok(())
+}
+
+/// Start the job worker threads, returning the sender feeding them.
+///
+/// Workers hold the pool weakly and exit once the queue is closed, by
+/// [`EnginePool::shutdown`] or by dropping the pool.
+fn spawn_job_workers(pool: &Arc<PoolInner>) -> Result<Sender<QueuedJob>> {
+    // Capacity is enforced by `queued_jobs`, which cancelled jobs leave at
+    // once, rather than by the channel.
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let workers = pool.config.job_workers.unwrap_or(pool.config.max_size);
+    for i in 0..workers {
+        let pool = Arc::downgrade(pool);
+        let receiver = receiver.clone();
+        thread::Builder::new()
+            .name(format!("fusabi-pool-job-{}", i))
+            .spawn(move || run_job_worker(pool, receiver))
+            .map_err(|e| Error::Internal(format!("failed to spawn pool job worker: {}", e)))?;
+    }
+    Ok(sender)
+}
+
+fn run_job_worker(pool: Weak<PoolInner>, jobs: Receiver<QueuedJob>) {
+    while let Ok(QueuedJob { job, shared }) = jobs.recv() {
+        let Some(inner) = pool.upgrade() else {
+            shared.finish(Err(Error::PoolShutdown));
+            continue;
+        };
+        // Cancelled while queued: skip it without taking an engine.
+        if !shared.is_queued() {
+            continue;
+        }
+        let pool = EnginePool { inner };
+        let handle = match pool.acquire_with_priority(job.priority) {
+            Ok(handle) => handle,
+            Err(e) => {
+                shared.finish(Err(e));
+                continue;
+            }
+        };
+        // Cancelled while waiting for the engine.
+        if !shared.start(&handle.pooled().engine) {
+            continue;
+        }
+        shared.finish(pool.run_job(&handle, &job));
+    }
+}
+
+// Async support when an async runtime is enabled
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+mod async_support {
+    use super::*;
+    use std::future::Future;
+    use std::marker::PhantomData;
+
+    /// Blocking-task and timer primitives of an async runtime.
+    ///
+    /// [`AsyncEnginePool`] is written against this trait, so hosts on any
+    /// runtime share the same pool logic. Implementations for Tokio and
+    /// async-std are provided behind their cargo features.
+    pub trait AsyncRuntime: Send + Sync + 'static {
+        /// Run a blocking closure off the runtime's async worker threads.
+        ///
+        /// A panic in `f` resumes in the task awaiting the result. Fails with
+        /// [`Error::Cancelled`] if the runtime drops the task before it runs,
+        /// as it does while shutting down.
+        fn spawn_blocking<F, T>(f: F) -> impl Future<Output = Result<T>> + Send
+        where
+            F: FnOnce() -> T + Send + 'static,
+            T: Send + 'static;
+
+        /// Wait for `future`, returning `None` if `duration` elapses first.
+        fn timeout<F>(
+            duration: Duration,
+            future: F,
+        ) -> impl Future<Output = Option<F::Output>> + Send
+        where
+            F: Future + Send;
+    }
+
+    /// The Tokio runtime.
+    #[cfg(feature = "async-runtime-tokio")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TokioRuntime;
+
+    #[cfg(feature = "async-runtime-tokio")]
+    impl AsyncRuntime for TokioRuntime {
+        fn spawn_blocking<F, T>(f: F) -> impl Future<Output = Result<T>> + Send
+        where
+            F: FnOnce() -> T + Send + 'static,
+            T: Send + 'static,
+        {
+            let task = tokio::task::spawn_blocking(f);
+            async move {
+                match task.await {
+                    Ok(value) => Ok(value),
+                    Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                    Err(_) => Err(Error::Cancelled),
+                }
+            }
+        }
+
+        fn timeout<F>(
+            duration: Duration,
+            future: F,
+        ) -> impl Future<Output = Option<F::Output>> + Send
+        where
+            F: Future + Send,
+        {
+            let timeout = tokio::time::timeout(duration, future);
+            async move { timeout.await.ok() }
+        }
+    }
+
+    /// The async-std runtime.
+    #[cfg(feature = "async-runtime-async-std")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct AsyncStdRuntime;
+
+    #[cfg(feature = "async-runtime-async-std")]
+    impl AsyncRuntime for AsyncStdRuntime {
+        fn spawn_blocking<F, T>(f: F) -> impl Future<Output = Result<T>> + Send
+        where
+            F: FnOnce() -> T + Send + 'static,
+            T: Send + 'static,
+        {
+            let task = async_std::task::spawn_blocking(f);
+            async move { Ok(task.await) }
+        }
+
+        fn timeout<F>(
+            duration: Duration,
+            future: F,
+        ) -> impl Future<Output = Option<F::Output>> + Send
+        where
+            F: Future + Send,
+        {
+            let timeout = async_std::future::timeout(duration, future);
+            async move { timeout.await.ok() }
+        }
+    }
+
+    /// Async engine pool on the Tokio runtime.
+    #[cfg(feature = "async-runtime-tokio")]
+    pub type TokioEnginePool = AsyncEnginePool<TokioRuntime>;
+
+    /// Async engine pool on the async-std runtime.
+    #[cfg(feature = "async-runtime-async-std")]
+    pub type AsyncStdEnginePool = AsyncEnginePool<AsyncStdRuntime>;
+
+    /// Async front end for an [`EnginePool`].
+    ///
+    /// Waiting for an engine does not block a runtime thread, and scripts run
+    /// on the runtime's blocking thread pool. Dropping an execution future
+    /// cancels the script it started; the engine goes back to the pool once
+    /// the script stops.
+    pub struct AsyncEnginePool<R: AsyncRuntime> {
+        inner: Arc<EnginePool>,
+        runtime: PhantomData<R>,
+    }
+
+    impl<R: AsyncRuntime> AsyncEnginePool<R> {
+        /// Create a new async pool wrapper.
+        pub fn new(pool: EnginePool) -> Self {
+            Self {
+                inner: Arc::new(pool),
+                runtime: PhantomData,
+            }
+        }
+
+        /// Get the underlying pool.
+        pub fn pool(&self) -> &EnginePool {
+            &self.inner
+        }
+
+        /// Acquire an engine asynchronously.
+        ///
+        /// Waits up to the pool's acquire timeout. Dropping the future gives
+        /// up the caller's place in the queue.
+        pub async fn acquire(&self) -> Result<PoolHandle> {
+            self.acquire_with_priority(Priority::Normal).await
+        }
+
+        /// Acquire an engine asynchronously in the given priority class.
+        ///
+        /// Lazily created engines are built on the blocking thread pool.
+        pub async fn acquire_with_priority(&self, priority: Priority) -> Result<PoolHandle> {
+            let inner = &self.inner.inner;
+            inner.begin_acquire()?;
+
+            let start = Instant::now();
+            if inner.config.lazy_init && inner.idle.idle_len() == 0 {
+                // The engine comes back inside a handle, so a dropped future
+                // still returns it to the pool.
+                let pool = Arc::clone(inner);
+                let grown = R::spawn_blocking(move || match pool.grow_on_demand()? {
+                    Some(engine) => pool
+                        .complete_acquire(priority, Some(engine), start.elapsed())
+                        .map(Some),
+                    None => Ok(None),
+                })
+                .await??;
+                if let Some(handle) = grown {
+                    return Ok(handle);
+                }
+            }
+
+            let timeout = inner.acquire_timeout(priority);
+            let received = R::timeout(timeout, inner.idle.pop_async(priority))
+                .await
+                .flatten();
+            if received.is_none() && inner.config.lazy_init {
+                // Completing may still create an engine.
+                let pool = Arc::clone(inner);
+                return R::spawn_blocking(move || {
+                    pool.complete_acquire(priority, None, start.elapsed())
+                })
+                .await?;
+            }
+            inner.complete_acquire(priority, received, start.elapsed())
+        }
+
+        /// Execute source code asynchronously.
+        pub async fn execute(&self, source: &str) -> Result<Value> {
+            let pool = Arc::clone(&self.inner);
+            let source = source.to_string();
+            self.run(move |handle| {
+                pool.link_source(handle, &source)?;
+                handle.execute(&source)
+            })
+            .await
+        }
+
+        /// Execute bytecode asynchronously.
+        ///
+        /// With a result cache, a cached result is returned without acquiring
+        /// an engine.
+        pub async fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
+            let key = match self.inner.cache_lookup(bytecode, &ExecOptions::default())? {
+                Memo::Hit(value) => return Ok(value),
+                Memo::Miss(key) => Some(key),
+                Memo::Uncached => None,
+            };
+
+            let pool = Arc::clone(&self.inner);
+            let code = bytecode.to_vec();
+            let result = self
+                .run(move |handle| {
+                    if pool.config().link_check {
+                        pool.ensure_linked(handle, &code)?;
+                    }
+                    handle.execute_bytecode(&code)
+                })
+                .await;
+            if let Some(key) = key {
+                self.inner.cache_store(key, &result);
+            }
+            result
+        }
+
+        /// Run a closure against a pooled engine on the blocking thread pool.
+        ///
+        /// A panic in `f` poisons the engine like any other pooled execution.
+        /// Dropping the future cancels the engine, which `f` can observe
+        /// through [`ExecutionContext::is_cancelled`].
+        ///
+        /// [`ExecutionContext::is_cancelled`]: crate::ExecutionContext::is_cancelled
+        pub async fn call<T, F>(&self, f: F) -> Result<T>
+        where
+            F: FnOnce(&Engine) -> Result<T> + Send + 'static,
+            T: Send + 'static,
+        {
+            self.run(move |handle| handle.run(f)).await
+        }
+
+        /// Acquire an engine and run `f` with it on the blocking thread pool.
+        async fn run<T, F>(&self, f: F) -> Result<T>
+        where
+            F: FnOnce(&PoolHandle) -> Result<T> + Send + 'static,
+            T: Send + 'static,
+        {
+            let handle = self.acquire().await?;
+            let finished = Arc::new(Mutex::new(false));
+            let _guard = CancelOnDrop {
+                engine: Arc::clone(&handle.pooled().engine),
+                finished: Arc::clone(&finished),
+            };
+
+            R::spawn_blocking(move || {
+                let result = f(&handle);
+                // Marked before the handle drops, so a late cancellation can
+                // never reach an engine that is already back in the pool.
+                *finished.lock() = true;
+                drop(handle);
+                result
+            })
+            .await?
+        }
+
+        /// Warm up the pool on the blocking thread pool.
+        ///
+        /// See [`EnginePool::warmup`]. Fails with [`Error::Cancelled`] if the
+        /// runtime shuts down first.
+        pub async fn warmup(&self) -> Result<WarmupReport> {
+            let pool = Arc::clone(&self.inner);
+            R::spawn_blocking(move || pool.warmup()).await
+        }
+
+        /// Get pool statistics.
+        pub fn stats(&self) -> PoolStats {
+            self.inner.stats()
+        }
+
+        /// Reset the latency histograms, error counts and peak queue depth.
+        pub fn reset_stats(&self) {
+            self.inner.reset_stats();
+        }
+
+        /// Shut down the pool, preventing new acquisitions.
+        pub fn shutdown(&self) {
+            self.inner.shutdown();
+        }
 
-    /// Shut down the pool, preventing new acquisitions.
-    pub fn shutdown(&self) {
-        self.shutdown.store(true, Ordering::Relaxed);
+        /// Shut down the pool, waiting up to `deadline` for in-flight executions.
+        ///
+        /// See [`EnginePool::shutdown_graceful`]. The wait runs on the blocking
+        /// thread pool; fails with [`Error::Cancelled`] if the runtime shuts
+        /// down first.
+        pub async fn shutdown_graceful(&self, deadline: Duration) -> Result<ShutdownReport> {
+            let pool = Arc::clone(&self.inner);
+            R::spawn_blocking(move || pool.shutdown_graceful(deadline)).await
+        }
+
+        /// Check if the pool has been shut down.
+        pub fn is_shutdown(&self) -> bool {
+            self.inner.is_shutdown()
+        }
     }
 
-    /// Check if the pool has been shut down.
-    pub fn is_shutdown(&self) -> bool {
-        self.shutdown.load(Ordering::Relaxed)
+    impl<R: AsyncRuntime> Clone for AsyncEnginePool<R> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: Arc::clone(&self.inner),
+                runtime: PhantomData,
+            }
+        }
     }
-}
 
-impl std::fmt::Debug for EnginePool {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let stats = self.stats();
-        f.debug_struct("EnginePool")
-            .field("size", &self.config.size)
-            .field("available", &stats.available)
-            .field("in_use", &stats.in_use)
-            .field("shutdown", &self.is_shutdown())
-            .finish()
+    impl<R: AsyncRuntime> From<EnginePool> for AsyncEnginePool<R> {
+        fn from(pool: EnginePool) -> Self {
+            Self::new(pool)
+        }
     }
-}
 
-// Async support when tokio is enabled
-#[cfg(feature = "async-runtime-tokio")]
-mod async_support {
-    use super::*;
-    use std::sync::Arc;
-    use tokio::sync::Semaphore;
+    impl<R: AsyncRuntime> std::fmt::Debug for AsyncEnginePool<R> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_tuple("AsyncEnginePool").field(&self.inner).finish()
+        }
+    }
 
-    /// Async wrapper for the engine pool.
-    #[allow(dead_code)]
-    pub struct AsyncEnginePool {
-        inner: Arc<EnginePool>,
-        semaphore: Arc<Semaphore>,
+    /// Cancels an engine if the future driving it is dropped mid-execution.
+    struct CancelOnDrop {
+        engine: Arc<Engine>,
+        finished: Arc<Mutex<bool>>,
     }
 
-    #[allow(dead_code)]
-    impl AsyncEnginePool {
-        /// Create a new async pool wrapper.
-        pub fn new(pool: EnginePool) -> Self {
-            let permits = pool.config.size;
-            Self {
-                inner: Arc::new(pool),
-                semaphore: Arc::new(Semaphore::new(permits)),
+    impl Drop for CancelOnDrop {
+        fn drop(&mut self) {
+            let finished = self.finished.lock();
+            if !*finished {
+                tracing::debug!(
+                    engine_id = self.engine.id(),
+                    "async execution dropped, cancelling engine"
+                );
+                self.engine.cancel();
             }
         }
+    }
 
-        /// Acquire an engine asynchronously.
-        pub async fn acquire(&self) -> Result<PoolHandle> {
-            let _permit = self
-                .semaphore
-                .acquire()
-                .await
-                .map_err(|_| Error::PoolShutdown)?;
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::mpsc;
 
-            self.inner.try_acquire()
+        fn pool<R: AsyncRuntime>(size: usize) -> AsyncEnginePool<R> {
+            let config = PoolConfig::new(size).with_acquire_timeout(Duration::from_millis(100));
+            AsyncEnginePool::new(EnginePool::new(config).unwrap())
         }
 
-        /// Execute source code asynchronously.
-        pub async fn execute(&self, source: &str) -> Result<Value> {
-            let handle = self.acquire().await?;
+        /// Run each generic test body on every enabled runtime.
+        macro_rules! runtime_tests {
+            ($($name:ident),* $(,)?) => {
+                #[cfg(feature = "async-runtime-tokio")]
+                mod tokio_runtime {
+                    use super::*;
+                    $(
+                        #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+                        async fn $name() {
+                            super::$name::<TokioRuntime>().await;
+                        }
+                    )*
+                }
 
-            // Run execution in blocking task to avoid blocking the runtime
-            let source = source.to_string();
-            tokio::task::spawn_blocking(move || handle.execute(&source))
-                .await
-                .map_err(|e| Error::Internal(e.to_string()))?
+                #[cfg(feature = "async-runtime-async-std")]
+                mod async_std_runtime {
+                    use super::*;
+                    $(
+                        #[test]
+                        fn $name() {
+                            async_std::task::block_on(super::$name::<AsyncStdRuntime>());
+                        }
+                    )*
+                }
+            };
         }
 
-        /// Get pool statistics.
-        pub fn stats(&self) -> PoolStats {
-            self.inner.stats()
+        runtime_tests!(
+            test_async_execute,
+            test_async_call,
+            test_async_acquire_timeout,
+            test_async_lazy_acquire_grows,
+            test_async_acquire_waits_for_release,
+            test_dropped_future_cancels_execution,
+            test_async_shutdown_graceful,
+            test_async_result_cache,
+        );
+
+        async fn test_async_execute<R: AsyncRuntime>() {
+            let pool = pool::<R>(2);
+            assert_eq!(pool.execute("1 + 2").await.unwrap(), Value::Int(3));
+
+            let bytecode = compile_source("42", &CompileOptions::default())
+                .unwrap()
+                .bytecode;
+            assert!(pool.execute_bytecode(&bytecode).await.is_ok());
+            assert_eq!(pool.stats().in_use, 0);
         }
 
-        /// Shutdown the pool.
-        pub fn shutdown(&self) {
-            self.inner.shutdown();
+        async fn test_async_result_cache<R: AsyncRuntime>() {
+            let config = PoolConfig::new(1)
+                .with_capabilities(Capabilities::none())
+                .with_result_cache(ResultCacheConfig::new());
+            let pool = AsyncEnginePool::<R>::new(EnginePool::new(config).unwrap());
+            let bytecode = compile_source("1 + 2", &CompileOptions::default())
+                .unwrap()
+                .bytecode;
+
+            assert_eq!(
+                pool.execute_bytecode(&bytecode).await.unwrap(),
+                Value::Int(3)
+            );
+            assert_eq!(
+                pool.execute_bytecode(&bytecode).await.unwrap(),
+                Value::Int(3)
+            );
+            let stats = pool.stats();
+            assert_eq!(stats.executions, 1);
+            assert_eq!(stats.result_cache.unwrap().hits, 1);
+        }
+
+        async fn test_async_call<R: AsyncRuntime>() {
+            let pool = pool::<R>(1);
+            let id = pool.call(|engine| Ok(engine.id())).await.unwrap();
+            assert_eq!(pool.call(|engine| Ok(engine.id())).await.unwrap(), id);
+
+            let result: Result<()> = pool.call(|_| panic!("boom")).await;
+            assert!(matches!(result, Err(Error::EnginePoisoned(_))));
+            assert_eq!(pool.stats().poisoned, 1);
+        }
+
+        async fn test_async_acquire_timeout<R: AsyncRuntime>() {
+            let pool = pool::<R>(1);
+            let _held = pool.acquire().await.unwrap();
+
+            let start = Instant::now();
+            let result = pool.acquire().await;
+            assert!(matches!(result, Err(Error::PoolTimeout)));
+            assert!(start.elapsed() >= Duration::from_millis(100));
+            assert_eq!(pool.stats().waiting, 0);
+        }
+
+        async fn test_async_lazy_acquire_grows<R: AsyncRuntime>() {
+            let config = PoolConfig::new(0)
+                .with_max_size(2)
+                .with_lazy_init(true)
+                .with_acquire_timeout(Duration::from_millis(100));
+            let pool = AsyncEnginePool::<R>::new(EnginePool::new(config).unwrap());
+
+            let first = pool.acquire().await.unwrap();
+            let second = pool.acquire().await.unwrap();
+            assert_ne!(first.engine().id(), second.engine().id());
+            assert_eq!(pool.stats().total, 2);
+            assert!(matches!(pool.acquire().await, Err(Error::PoolTimeout)));
+
+            drop(first);
+            assert!(pool.acquire().await.is_ok());
+        }
+
+        #[cfg(feature = "async-runtime-tokio")]
+        #[test]
+        fn test_tokio_cancelled_blocking_task() {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let handle = runtime.handle().clone();
+            runtime.shutdown_background();
+
+            // A runtime that is shutting down drops new blocking tasks.
+            let cancelled = {
+                let _entered = handle.enter();
+                TokioRuntime::spawn_blocking(|| 1)
+            };
+            let waiter = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            assert!(matches!(waiter.block_on(cancelled), Err(Error::Cancelled)));
+        }
+
+        async fn test_async_acquire_waits_for_release<R: AsyncRuntime>() {
+            let pool = pool::<R>(1);
+            let held = pool.acquire().await.unwrap();
+
+            let watcher = pool.clone();
+            let releaser = thread::spawn(move || {
+                while watcher.stats().waiting == 0 {
+                    thread::yield_now();
+                }
+                drop(held);
+            });
+            assert_eq!(pool.execute("7").await.unwrap(), Value::Int(7));
+            releaser.join().unwrap();
+        }
+
+        async fn test_dropped_future_cancels_execution<R: AsyncRuntime>() {
+            let pool = pool::<R>(1);
+            let (started_tx, started_rx) = mpsc::channel();
+
+            let running = pool.call(move |engine| {
+                started_tx.send(()).unwrap();
+                while !engine.context().is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                Err::<(), _>(Error::Cancelled)
+            });
+            assert!(R::timeout(Duration::from_millis(20), running)
+                .await
+                .is_none());
+            started_rx.recv().unwrap();
+
+            // The cancelled engine comes back and is usable again.
+            assert_eq!(pool.execute("5").await.unwrap(), Value::Int(5));
+            assert_eq!(pool.stats().total, 1);
+        }
+
+        async fn test_async_shutdown_graceful<R: AsyncRuntime>() {
+            let pool = pool::<R>(2);
+            let held = pool.acquire().await.unwrap();
+
+            let releaser = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                drop(held);
+            });
+            let report = pool
+                .shutdown_graceful(Duration::from_secs(5))
+                .await
+                .unwrap();
+            releaser.join().unwrap();
+
+            assert!(report.is_clean());
+            assert_eq!(report.completed, 1);
+            assert!(pool.is_shutdown());
+            assert!(matches!(pool.execute("1").await, Err(Error::PoolShutdown)));
         }
     }
 }
 
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+pub use async_support::{AsyncEnginePool, AsyncRuntime};
+
+#[cfg(feature = "async-runtime-tokio")]
+pub use async_support::{TokioEnginePool, TokioRuntime};
+
+#[cfg(feature = "async-runtime-async-std")]
+pub use async_support::{AsyncStdEnginePool, AsyncStdRuntime};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -584,11 +2475,34 @@ mod tests {
         let pool = EnginePool::new(config).unwrap();
 
         // No engines created yet
-        assert_eq!(pool.created.load(Ordering::Relaxed), 0);
+        assert_eq!(pool.inner.created.load(Ordering::Relaxed), 0);
 
         // Acquire creates one
         let _handle = pool.try_acquire().unwrap();
-        assert_eq!(pool.created.load(Ordering::Relaxed), 1);
+        assert_eq!(pool.inner.created.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_lazy_pool_grows_without_waiting() {
+        let config = PoolConfig::new(1)
+            .with_max_size(2)
+            .with_lazy_init(true)
+            .with_acquire_timeout(Duration::from_secs(5));
+        let pool = EnginePool::new(config).unwrap();
+
+        let start = Instant::now();
+        let _a = pool.acquire().unwrap();
+        let _b = pool.acquire().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(pool.inner.created.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_min_size_raises_initial_size() {
+        let pool = EnginePool::new(PoolConfig::new(2).with_min_size(3)).unwrap();
+        assert_eq!(pool.inner.created.load(Ordering::Relaxed), 3);
+        assert_eq!(pool.inner.config.size, 3);
+        assert_eq!(pool.inner.config.max_size, 3);
     }
 
     #[test]
@@ -603,6 +2517,101 @@ mod tests {
         assert!(matches!(result, Err(Error::PoolShutdown)));
     }
 
+    #[test]
+    fn test_engine_taken_during_shutdown_is_released() {
+        let pool = EnginePool::new(PoolConfig::new(2)).unwrap();
+        let held = pool.acquire().unwrap();
+
+        // An acquisition that already holds an engine when shutdown starts.
+        let engine = pool.inner.idle.try_pop().unwrap();
+        pool.shutdown();
+        assert!(matches!(
+            pool.inner
+                .complete_acquire(Priority::Normal, Some(engine), Duration::ZERO),
+            Err(Error::PoolShutdown)
+        ));
+        assert_eq!(pool.stats().total, 1);
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            drop(held);
+        });
+        let report = pool.shutdown_graceful(Duration::from_secs(5));
+        releaser.join().unwrap();
+        assert_eq!(report.completed, 1);
+        assert!(report.cancelled.is_empty());
+    }
+
+    #[test]
+    fn test_pool_host_context() {
+        use crate::host_context::LogLevel;
+
+        #[derive(Default)]
+        struct Metrics(Mutex<Vec<String>>);
+
+        impl HostContext for Metrics {
+            fn log(&self, _level: LogLevel, _message: &str) {}
+
+            fn record_metric(&self, name: &str, _value: f64, _tags: &[(&str, &str)]) {
+                self.0.lock().push(name.to_string());
+            }
+
+            fn should_cancel(&self) -> bool {
+                false
+            }
+        }
+
+        let host = Arc::new(Metrics::default());
+        let pool = EnginePool::new(PoolConfig::new(1).with_host_context(host.clone())).unwrap();
+        assert!(pool.acquire().unwrap().engine().host_context().is_some());
+        pool.execute("1").unwrap();
+
+        let names = host.0.lock();
+        for name in [
+            "fusabi_pool_engines_in_use",
+            "fusabi_pool_acquisitions_total",
+            "fusabi_pool_acquire_wait_seconds",
+            "fusabi_executions_total",
+        ] {
+            assert!(names.iter().any(|n| n == name), "missing {}", name);
+        }
+    }
+
+    #[test]
+    fn test_pool_prelude() {
+        let config = PoolConfig::new(2)
+            .with_lazy_init(true)
+            .with_prelude("let base = 40");
+        let pool = EnginePool::new(config).unwrap();
+
+        let report = pool.warmup();
+        assert!(report.is_ready());
+        assert_eq!(report.ready, 2);
+        assert!(report.failures.is_empty());
+
+        assert_eq!(pool.execute("base + 2").unwrap(), Value::Int(42));
+        let handle = pool.acquire().unwrap();
+        assert_eq!(handle.engine().prelude_len(), 1);
+    }
+
+    #[test]
+    fn test_pool_failed_prelude_discards_engines() {
+        // An eager pool reports the failure rather than starting short.
+        let config = PoolConfig::new(2).with_prelude("let base = missing + 40");
+        assert!(matches!(
+            EnginePool::new(config.clone()),
+            Err(Error::Prelude(_))
+        ));
+
+        let pool = EnginePool::new(config.with_lazy_init(true)).unwrap();
+        let report = pool.warmup();
+        assert!(!report.is_ready());
+        assert_eq!(report.ready, 0);
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(pool.stats().total, 0);
+        assert_eq!(pool.stats().prelude_failures, 2);
+    }
+
     #[test]
     fn test_pool_stats() {
         let pool = EnginePool::new(PoolConfig::new(2)).unwrap();
@@ -618,6 +2627,108 @@ mod tests {
         assert!(stats.total_execution_time > Duration::ZERO);
     }
 
+    #[test]
+    fn test_pool_latency_stats() {
+        let pool = EnginePool::new(PoolConfig::new(1)).unwrap();
+
+        let handle = pool.acquire().unwrap();
+        handle.execute("1").unwrap();
+        let result = handle.run(|_| Err::<(), _>(Error::runtime("boom")));
+        assert!(result.is_err());
+        thread::sleep(Duration::from_millis(5));
+        drop(handle);
+
+        let stats = pool.stats();
+        assert_eq!(stats.acquire_wait.count(), 1);
+        assert_eq!(stats.execution_time.count(), 2);
+        assert_eq!(stats.hold_time.count(), 1);
+        assert!(stats.hold_time.p50() >= Duration::from_millis(5));
+        assert!(stats.execution_time.max() < stats.hold_time.max());
+        assert_eq!(stats.total_execution_time, stats.execution_time.total());
+        assert_eq!(stats.errors.get("runtime"), Some(&1));
+        assert_eq!(stats.error_count(), 1);
+
+        pool.reset_stats();
+        let stats = pool.stats();
+        assert_eq!(stats.execution_time.count(), 0);
+        assert!(stats.errors.is_empty());
+        // Cumulative counters survive a reset.
+        assert_eq!(stats.executions, 2);
+    }
+
+    #[cfg(feature = "metrics-prometheus")]
+    #[test]
+    fn test_pool_prometheus_metrics() {
+        use crate::metrics::{encode_metrics, PrometheusMetrics};
+
+        let registry = prometheus::Registry::new();
+        let metrics = PrometheusMetrics::new(&registry).unwrap();
+        let config = PoolConfig::new(2)
+            .with_acquire_timeout(Duration::from_millis(10))
+            .with_metrics(metrics);
+        let pool = EnginePool::new(config).unwrap();
+
+        let handle = pool.acquire().unwrap();
+        handle.execute("1 + 2").unwrap();
+        let limited = EngineConfig::new().with_limits(Limits::default().with_max_instructions(1));
+        let other = pool.acquire().unwrap();
+        let text = encode_metrics(&registry).unwrap();
+        assert!(text.contains("fusabi_pool_engines_in_use 2"));
+        assert!(pool.acquire().is_err());
+        drop(other);
+
+        // Engines created outside the pool report through their own config.
+        let engine =
+            Engine::new(limited.with_metrics(pool.config().metrics.clone().unwrap())).unwrap();
+        assert!(engine.execute("1").is_err());
+        drop(handle);
+
+        let text = encode_metrics(&registry).unwrap();
+        for line in [
+            "fusabi_pool_engines 2",
+            "fusabi_pool_engines_available 2",
+            "fusabi_pool_engines_in_use 0",
+            "fusabi_pool_acquisitions_total 3",
+            "fusabi_pool_timeouts_total 1",
+            "fusabi_pool_acquire_wait_seconds_count 3",
+            "fusabi_executions_total 2",
+            "fusabi_execution_errors_total{kind=\"limit_violation\"} 1",
+            "fusabi_limit_violations_total{kind=\"instructions\"} 1",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {:?} in\n{}",
+                line,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_pool_peak_waiting() {
+        let config = PoolConfig::new(1).with_acquire_timeout(Duration::from_millis(20));
+        let pool = Arc::new(EnginePool::new(config).unwrap());
+        let _held = pool.acquire().unwrap();
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || pool.acquire().is_err())
+            })
+            .collect();
+        for waiter in waiters {
+            assert!(waiter.join().unwrap());
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.peak_waiting, 2);
+        assert_eq!(stats.errors.len(), 0);
+
+        pool.reset_stats();
+        assert_eq!(pool.stats().peak_waiting, 0);
+    }
+
     #[test]
     fn test_pool_config_builder() {
         let config = PoolConfig::new(8)
@@ -641,6 +2752,394 @@ mod tests {
         let result = handle.execute("42");
         assert!(matches!(result, Err(Error::Cancelled)));
     }
+
+    #[test]
+    fn test_pool_registry_and_initializer() {
+        let mut registry = HostRegistry::new();
+        registry.register("shared_fn", |_args, _ctx| Ok(Value::Null));
+        let initialized = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&initialized);
+        let config = PoolConfig::new(2)
+            .with_registry(Arc::new(registry))
+            .on_engine_create(move |engine| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                engine
+                    .registry_mut()
+                    .register("engine_fn", |_args, _ctx| Ok(Value::Null));
+                Ok(())
+            });
+
+        let pool = EnginePool::new(config.clone()).unwrap();
+        assert_eq!(initialized.load(Ordering::SeqCst), 2);
+
+        let handle = pool.acquire().unwrap();
+        assert!(handle.engine().registry().get("shared_fn").is_some());
+        assert!(handle.engine().registry().get("engine_fn").is_some());
+        drop(handle);
+
+        // Lazily created engines are initialized too.
+        let lazy = EnginePool::new(config.with_lazy_init(true)).unwrap();
+        let handle = lazy.try_acquire().unwrap();
+        assert_eq!(initialized.load(Ordering::SeqCst), 3);
+        assert!(handle.engine().registry().get("engine_fn").is_some());
+    }
+
+    #[test]
+    fn test_pool_initializer_error() {
+        let config =
+            PoolConfig::new(1).on_engine_create(|_engine| Err(Error::host_function("init failed")));
+        assert!(EnginePool::new(config).is_err());
+    }
+
+    #[test]
+    fn test_elastic_pool_grows_under_load() {
+        let config = PoolConfig::elastic(1, 3)
+            .with_autoscale(AutoscaleConfig::new().with_interval(Duration::from_millis(5)))
+            .with_acquire_timeout(Duration::from_secs(5));
+        let pool = Arc::new(EnginePool::new(config).unwrap());
+        assert_eq!(pool.stats().total, 1);
+
+        let held = pool.acquire().unwrap();
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || pool.acquire().map(|_| ()))
+        };
+        waiter.join().unwrap().unwrap();
+        drop(held);
+
+        let stats = pool.stats();
+        assert!(stats.total >= 2);
+        assert!(stats.scale_ups >= 1);
+        assert!(stats.last_scale_event.unwrap().reason.is_scale_up());
+    }
+
+    #[test]
+    fn test_elastic_pool_shrinks_when_idle() {
+        let policy = AutoscaleConfig::new()
+            .with_interval(Duration::from_secs(3600))
+            .with_scale_down_idle(Duration::ZERO);
+        let config = PoolConfig::new(3)
+            .with_min_size(1)
+            .with_autoscale(policy.clone());
+        let pool = EnginePool::new(config).unwrap();
+        let mut scaler = Autoscaler::new(policy);
+
+        pool.inner.autoscale(&mut scaler);
+        pool.inner.autoscale(&mut scaler);
+        pool.inner.autoscale(&mut scaler);
+
+        let stats = pool.stats();
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.scale_downs, 2);
+        assert!(pool.execute("1").is_ok());
+    }
+
+    #[test]
+    fn test_max_uses_recycling() {
+        let pool = EnginePool::new(PoolConfig::new(1).with_max_uses(Some(2))).unwrap();
+
+        let first = pool.acquire().unwrap().engine().id();
+        assert_eq!(pool.acquire().unwrap().engine().id(), first);
+
+        // The engine has now been used twice and is replaced on acquire.
+        let handle = pool.acquire().unwrap();
+        assert_ne!(handle.engine().id(), first);
+        drop(handle);
+
+        let stats = pool.stats();
+        assert_eq!(stats.use_evictions, 1);
+        assert_eq!(stats.evictions(), 1);
+        assert_eq!(stats.total, 1);
+    }
+
+    #[test]
+    fn test_max_lifetime_recycling() {
+        let config = PoolConfig::new(1).with_max_lifetime(Some(Duration::from_millis(1)));
+        let pool = EnginePool::new(config).unwrap();
+        let first = pool.inner.idle.try_pop().unwrap();
+        let first_id = first.engine.id();
+        pool.inner.idle.push(first);
+
+        thread::sleep(Duration::from_millis(5));
+        assert_ne!(pool.acquire().unwrap().engine().id(), first_id);
+        assert_eq!(pool.stats().lifetime_evictions, 1);
+    }
+
+    #[test]
+    fn test_reaper_evicts_idle_engines() {
+        let config = PoolConfig::new(2).with_max_idle_time(Some(Duration::from_millis(1)));
+        let pool = EnginePool::new(config).unwrap();
+
+        thread::sleep(Duration::from_millis(5));
+        pool.inner.reap();
+
+        let stats = pool.stats();
+        assert_eq!(stats.idle_evictions, 2);
+        assert_eq!(stats.available, 2);
+        assert_eq!(stats.total, 2);
+    }
+
+    #[test]
+    fn test_panic_poisons_and_replaces_engine() {
+        let pool = EnginePool::new(PoolConfig::new(1)).unwrap();
+
+        let handle = pool.acquire().unwrap();
+        let poisoned_id = handle.engine().id();
+        let result: Result<()> = handle.run(|_| panic!("host function exploded"));
+
+        match result {
+            Err(Error::EnginePoisoned(msg)) => assert_eq!(msg, "host function exploded"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(handle.is_poisoned());
+        assert!(matches!(handle.execute("1"), Err(Error::EnginePoisoned(_))));
+        drop(handle);
+
+        // A fresh engine takes the poisoned one's place.
+        let handle = pool.acquire().unwrap();
+        assert_ne!(handle.engine().id(), poisoned_id);
+        assert_eq!(handle.execute("42").unwrap(), Value::Int(42));
+        drop(handle);
+
+        let stats = pool.stats();
+        assert_eq!(stats.poisoned, 1);
+        assert_eq!(stats.total, 1);
+    }
+
+    #[test]
+    fn test_acquire_with_priority() {
+        let config = PoolConfig::new(1)
+            .with_acquire_timeout(Duration::from_secs(5))
+            .with_priority_timeout(Priority::Low, Duration::from_millis(10));
+        let pool = Arc::new(EnginePool::new(config).unwrap());
+
+        let held = pool.acquire_with_priority(Priority::High).unwrap();
+
+        // Low-priority callers give up after their class timeout.
+        assert!(matches!(
+            pool.acquire_with_priority(Priority::Low),
+            Err(Error::PoolTimeout)
+        ));
+
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || pool.acquire_with_priority(Priority::High).map(|_| ()))
+        };
+        while pool.stats().waiting == 0 {
+            thread::yield_now();
+        }
+        drop(held);
+        waiter.join().unwrap().unwrap();
+
+        let stats = pool.stats();
+        let high = stats.priority(Priority::High).unwrap();
+        assert_eq!(high.acquisitions, 2);
+        assert!(high.max_wait > Duration::ZERO);
+        let low = stats.priority(Priority::Low).unwrap();
+        assert_eq!(low.timeouts, 1);
+        assert_eq!(low.acquisitions, 0);
+        assert_eq!(stats.timeouts, 1);
+    }
+
+    #[test]
+    fn test_graceful_shutdown_drains() {
+        let pool = Arc::new(EnginePool::new(PoolConfig::new(2)).unwrap());
+        let handle = pool.acquire().unwrap();
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            drop(handle);
+        });
+        let report = pool.shutdown_graceful(Duration::from_secs(5));
+        releaser.join().unwrap();
+
+        assert!(report.is_clean());
+        assert_eq!(report.completed, 1);
+        assert_eq!(report.engines_dropped, 1);
+        assert!(matches!(pool.acquire(), Err(Error::PoolShutdown)));
+        // The released engine was dropped rather than returned.
+        assert_eq!(pool.stats().total, 0);
+    }
+
+    #[test]
+    fn test_graceful_shutdown_cancels_at_deadline() {
+        let pool = EnginePool::new(PoolConfig::new(2)).unwrap();
+        let handle = pool.acquire().unwrap();
+        let id = handle.engine().id();
+
+        let report = pool.shutdown_graceful(Duration::from_millis(10));
+        assert_eq!(report.cancelled, vec![id]);
+        assert_eq!(report.completed, 0);
+        assert_eq!(report.engines_dropped, 1);
+        assert!(!handle.engine().is_healthy());
+        assert!(matches!(handle.execute("1"), Err(Error::Cancelled)));
+
+        drop(handle);
+        assert_eq!(pool.stats().total, 0);
+    }
+
+    #[test]
+    fn test_shutdown_wakes_waiters() {
+        let config = PoolConfig::new(1).with_acquire_timeout(Duration::from_secs(5));
+        let pool = Arc::new(EnginePool::new(config).unwrap());
+        let _held = pool.acquire().unwrap();
+
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || pool.acquire().map(|_| ()))
+        };
+        while pool.stats().waiting == 0 {
+            thread::yield_now();
+        }
+        pool.shutdown();
+        assert!(matches!(waiter.join().unwrap(), Err(Error::PoolShutdown)));
+    }
+
+    #[test]
+    fn test_pool_link_check() {
+        let pool = EnginePool::new(PoolConfig::new(1).with_link_check(true)).unwrap();
+
+        assert_eq!(pool.execute("42").unwrap(), Value::Int(42));
+        assert_eq!(pool.inner.linked.lock().len(), 1);
+
+        // A second dispatch of the same script hits the cache.
+        assert_eq!(pool.execute("42").unwrap(), Value::Int(42));
+        assert_eq!(pool.inner.linked.lock().len(), 1);
+
+        // Every unresolved reference is reported, and failures are not cached.
+        match pool.execute("let a = missing 1\nother 2") {
+            Err(Error::Link(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(pool.inner.linked.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_linked_scripts_are_bounded() {
+        let mut linked = LinkedScripts::default();
+        for n in 0..LINKED_CAPACITY as u64 + 10 {
+            linked.insert(n);
+        }
+        assert_eq!(linked.len(), LINKED_CAPACITY);
+        assert!(!linked.contains(0));
+        assert!(linked.contains(LINKED_CAPACITY as u64 + 9));
+    }
+
+    #[test]
+    fn test_result_cache() {
+        use crate::capabilities::Capability;
+
+        let pure = Capabilities::none().with(Capability::Logging);
+        let pool = EnginePool::new(
+            PoolConfig::new(1)
+                .with_capabilities(pure)
+                .with_result_cache(ResultCacheConfig::new()),
+        )
+        .unwrap();
+        let bytecode = compile_source("1 + 2", &CompileOptions::default())
+            .unwrap()
+            .bytecode;
+
+        assert_eq!(pool.execute_bytecode(&bytecode).unwrap(), Value::Int(3));
+        assert_eq!(pool.execute_bytecode(&bytecode).unwrap(), Value::Int(3));
+        let job = Job::bytecode(bytecode.clone());
+        assert_eq!(pool.execute_job(&job).unwrap(), Value::Int(3));
+        assert_eq!(pool.stats().executions, 1);
+
+        // Different globals are a different input.
+        let options = ExecOptions::new().with_global("n", 1);
+        pool.execute_bytecode_with(&bytecode, options).unwrap();
+        let stats = pool.stats();
+        assert_eq!(stats.executions, 2);
+        let cache = stats.result_cache.unwrap();
+        assert_eq!((cache.hits, cache.misses, cache.entries), (2, 2, 2));
+        assert_eq!(cache.hit_rate(), 0.5);
+
+        pool.clear_result_cache();
+        assert_eq!(pool.stats().result_cache.unwrap().entries, 0);
+    }
+
+    #[test]
+    fn test_result_cache_requires_pure_capabilities() {
+        let pool = EnginePool::new(
+            PoolConfig::new(1)
+                .with_capabilities(Capabilities::safe_defaults())
+                .with_result_cache(ResultCacheConfig::new()),
+        )
+        .unwrap();
+        let bytecode = compile_source("1 + 2", &CompileOptions::default())
+            .unwrap()
+            .bytecode;
+
+        pool.execute_bytecode(&bytecode).unwrap();
+        pool.execute_bytecode(&bytecode).unwrap();
+        assert_eq!(pool.stats().executions, 2);
+        assert_eq!(pool.stats().result_cache.unwrap().bypassed, 2);
+
+        // Narrowing an execution to no capabilities makes it cacheable.
+        let options = ExecOptions::new().with_capabilities(Capabilities::none());
+        pool.execute_bytecode_with(&bytecode, options.clone())
+            .unwrap();
+        pool.execute_bytecode_with(&bytecode, options).unwrap();
+        assert_eq!(pool.stats().executions, 3);
+        assert_eq!(pool.stats().result_cache.unwrap().hits, 1);
+    }
+
+    #[test]
+    fn test_result_cache_bypassed_with_limits_and_after_shutdown() {
+        let pool = EnginePool::new(
+            PoolConfig::new(1)
+                .with_capabilities(Capabilities::none())
+                .with_result_cache(ResultCacheConfig::new()),
+        )
+        .unwrap();
+        let bytecode = compile_source("1 + 2", &CompileOptions::default())
+            .unwrap()
+            .bytecode;
+        pool.execute_bytecode(&bytecode).unwrap();
+
+        // A cached success must not hide a limit the script would exceed.
+        let options = ExecOptions::new().with_limits(Limits::default().with_max_instructions(1));
+        assert!(matches!(
+            pool.execute_bytecode_with(&bytecode, options),
+            Err(Error::LimitViolation(_))
+        ));
+        assert_eq!(pool.stats().result_cache.unwrap().bypassed, 1);
+
+        pool.shutdown();
+        assert!(matches!(
+            pool.execute_bytecode(&bytecode),
+            Err(Error::PoolShutdown)
+        ));
+        assert_eq!(pool.stats().result_cache.unwrap().hits, 0);
+    }
+
+    #[test]
+    fn test_result_cache_bypassed_for_host_calls() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let mut registry = HostRegistry::new();
+        registry.register("bump", move |_args, _ctx| {
+            Ok(Value::Int(counter.fetch_add(1, Ordering::SeqCst) as i64))
+        });
+        let pool = EnginePool::new(
+            PoolConfig::new(1)
+                .with_capabilities(Capabilities::none())
+                .with_registry(Arc::new(registry))
+                .with_result_cache(ResultCacheConfig::new()),
+        )
+        .unwrap();
+        let bytecode = compile_source("bump ()", &CompileOptions::default())
+            .unwrap()
+            .bytecode;
+
+        assert_eq!(pool.execute_bytecode(&bytecode).unwrap(), Value::Int(0));
+        assert_eq!(pool.execute_bytecode(&bytecode).unwrap(), Value::Int(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let cache = pool.stats().result_cache.unwrap();
+        assert_eq!((cache.hits, cache.bypassed, cache.entries), (0, 2, 0));
+    }
 }
 
 // Mock num_cpus for the default
@@ -0,0 +1,528 @@
+//! Priority classes and the weighted-fair wait queue behind `EnginePool`.
+//!
+//! Idle engines and blocked callers meet in a [`WaitQueue`]. When an engine
+//! is released and callers are waiting, it is handed to the waiting class
+//! chosen by stride scheduling: every class advances a virtual "pass" by
+//! the inverse of its weight each time it is served, and the class with the
+//! smallest pass goes next. Heavier classes are served more often, but every
+//! class with waiters is served eventually, so none can starve.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::task::Waker;
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use parking_lot::Mutex;
+
+/// Scheduling class for an engine acquisition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Priority {
+    /// Latency-sensitive work such as interactive requests.
+    High,
+    /// Regular work.
+    #[default]
+    Normal,
+    /// Background and batch work.
+    Low,
+}
+
+impl Priority {
+    /// All priority classes, highest first.
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Scheduling settings for one priority class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityClass {
+    /// Relative share of released engines handed to this class.
+    pub weight: u32,
+    /// Acquire timeout for this class (`None` uses the pool's timeout).
+    pub acquire_timeout: Option<Duration>,
+}
+
+impl PriorityClass {
+    /// Create a class with the given weight.
+    pub fn new(weight: u32) -> Self {
+        Self {
+            weight: weight.max(1),
+            acquire_timeout: None,
+        }
+    }
+}
+
+/// Scheduling settings for all priority classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityConfig {
+    /// Settings for [`Priority::High`].
+    pub high: PriorityClass,
+    /// Settings for [`Priority::Normal`].
+    pub normal: PriorityClass,
+    /// Settings for [`Priority::Low`].
+    pub low: PriorityClass,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            high: PriorityClass::new(8),
+            normal: PriorityClass::new(4),
+            low: PriorityClass::new(1),
+        }
+    }
+}
+
+impl PriorityConfig {
+    /// Get the settings for a class.
+    pub fn class(&self, priority: Priority) -> &PriorityClass {
+        match priority {
+            Priority::High => &self.high,
+            Priority::Normal => &self.normal,
+            Priority::Low => &self.low,
+        }
+    }
+
+    /// Get mutable settings for a class.
+    pub fn class_mut(&mut self, priority: Priority) -> &mut PriorityClass {
+        match priority {
+            Priority::High => &mut self.high,
+            Priority::Normal => &mut self.normal,
+            Priority::Low => &mut self.low,
+        }
+    }
+
+    fn weights(&self) -> [u32; 3] {
+        Priority::ALL.map(|p| self.class(p).weight.max(1))
+    }
+}
+
+/// Wait statistics for one priority class.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityStats {
+    /// The class these statistics describe.
+    pub priority: Priority,
+    /// Successful acquisitions.
+    pub acquisitions: u64,
+    /// Acquisitions that timed out.
+    pub timeouts: u64,
+    /// Callers currently waiting.
+    pub waiting: usize,
+    /// Total time spent waiting, including timeouts.
+    pub total_wait: Duration,
+    /// Longest single wait.
+    pub max_wait: Duration,
+}
+
+impl PriorityStats {
+    /// Calculate the average wait per acquisition attempt.
+    pub fn avg_wait(&self) -> Duration {
+        let attempts = self.acquisitions + self.timeouts;
+        if attempts == 0 {
+            Duration::ZERO
+        } else {
+            self.total_wait / attempts as u32
+        }
+    }
+}
+
+/// Virtual time a class with weight 1 advances per engine handed to it.
+const STRIDE: u64 = 1 << 20;
+
+/// Waker slot shared between an async waiter and the queue.
+type WakerSlot = Arc<Mutex<Option<Waker>>>;
+
+struct Waiter<T> {
+    id: u64,
+    tx: Sender<T>,
+    /// Woken once an item has been sent, for async waiters.
+    waker: Option<WakerSlot>,
+}
+
+impl<T> Waiter<T> {
+    fn wake(&self) {
+        if let Some(waker) = self.waker.as_ref().and_then(|slot| slot.lock().take()) {
+            waker.wake();
+        }
+    }
+}
+
+struct QueueState<T> {
+    idle: VecDeque<T>,
+    waiters: [VecDeque<Waiter<T>>; 3],
+    pass: [u64; 3],
+    vtime: u64,
+    next_id: u64,
+    /// Most waiters at once since the last reset.
+    peak_waiting: usize,
+}
+
+/// Idle items plus a weighted-fair queue of callers waiting for one.
+pub(crate) struct WaitQueue<T> {
+    state: Mutex<QueueState<T>>,
+    weights: [u32; 3],
+}
+
+impl<T> WaitQueue<T> {
+    pub(crate) fn new(config: &PriorityConfig) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                idle: VecDeque::new(),
+                waiters: Default::default(),
+                pass: [0; 3],
+                vtime: 0,
+                next_id: 0,
+                peak_waiting: 0,
+            }),
+            weights: config.weights(),
+        }
+    }
+
+    /// Hand an item to the next waiter, or park it as idle.
+    pub(crate) fn push(&self, mut item: T) {
+        let mut state = self.state.lock();
+        loop {
+            let next = (0..3)
+                .filter(|&c| !state.waiters[c].is_empty())
+                .min_by_key(|&c| (state.pass[c], c));
+            let Some(class) = next else {
+                state.idle.push_back(item);
+                return;
+            };
+
+            let waiter = state.waiters[class].pop_front().expect("class has waiters");
+            state.vtime = state.pass[class];
+            state.pass[class] += STRIDE / self.weights[class] as u64;
+
+            // Sent under the lock so a timing-out waiter that no longer finds
+            // itself in the queue is guaranteed to find the item instead.
+            match waiter.tx.try_send(item) {
+                Ok(()) => {
+                    waiter.wake();
+                    return;
+                }
+                Err(e) => item = e.into_inner(),
+            }
+        }
+    }
+
+    /// Wake every waiter empty-handed. Later waits still see idle items.
+    pub(crate) fn close(&self) {
+        let waiters: Vec<Waiter<T>> = {
+            let mut state = self.state.lock();
+            state.waiters.iter_mut().flat_map(|q| q.drain(..)).collect()
+        };
+        for waiter in waiters {
+            let Waiter { tx, waker, .. } = waiter;
+            // Drop the sender first so the woken waiter sees a disconnect.
+            drop(tx);
+            if let Some(waker) = waker.and_then(|slot| slot.lock().take()) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Take an idle item without waiting.
+    pub(crate) fn try_pop(&self) -> Option<T> {
+        self.state.lock().idle.pop_front()
+    }
+
+    /// Take an item, waiting up to `timeout` in the given class.
+    pub(crate) fn pop_timeout(&self, priority: Priority, timeout: Duration) -> Option<T> {
+        let class = priority.index();
+        let (id, rx) = match self.enqueue(class, None) {
+            Ok(item) => return Some(item),
+            Err(waiting) => waiting,
+        };
+
+        match rx.recv_timeout(timeout) {
+            Ok(item) => Some(item),
+            // The queue was closed and dropped our sender.
+            Err(RecvTimeoutError::Disconnected) => None,
+            Err(RecvTimeoutError::Timeout) => self.dequeue(class, id, &rx),
+        }
+    }
+
+    /// Take an item, waiting asynchronously in the given class.
+    ///
+    /// The wait has no deadline of its own; wrap the future in the runtime's
+    /// timeout. Dropping the future gives up its place in the queue, and an
+    /// item handed to it in the meantime goes to the next waiter.
+    #[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+    pub(crate) fn pop_async(&self, priority: Priority) -> Pop<'_, T> {
+        Pop {
+            queue: self,
+            class: priority.index(),
+            state: PopState::Start,
+        }
+    }
+
+    /// Take an idle item, or join the class's waiters.
+    fn enqueue(&self, class: usize, waker: Option<WakerSlot>) -> Result<T, (u64, Receiver<T>)> {
+        let (tx, rx) = bounded(1);
+        let mut state = self.state.lock();
+        if let Some(item) = state.idle.pop_front() {
+            return Ok(item);
+        }
+        // A class that was idle rejoins at the current virtual time rather
+        // than spending credit banked while it had no waiters.
+        if state.waiters[class].is_empty() {
+            state.pass[class] = state.pass[class].max(state.vtime);
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.waiters[class].push_back(Waiter { id, tx, waker });
+        let waiting = state.waiters.iter().map(VecDeque::len).sum();
+        state.peak_waiting = state.peak_waiting.max(waiting);
+        Err((id, rx))
+    }
+
+    /// Leave the class's waiters, returning any item already handed over.
+    fn dequeue(&self, class: usize, id: u64, rx: &Receiver<T>) -> Option<T> {
+        let mut state = self.state.lock();
+        let queue = &mut state.waiters[class];
+        if let Some(pos) = queue.iter().position(|w| w.id == id) {
+            queue.remove(pos);
+            return None;
+        }
+        // Handed an item between giving up and taking the lock.
+        rx.try_recv().ok()
+    }
+
+    /// Number of idle items.
+    pub(crate) fn idle_len(&self) -> usize {
+        self.state.lock().idle.len()
+    }
+
+    /// Number of callers waiting in a class.
+    pub(crate) fn waiting(&self, priority: Priority) -> usize {
+        self.state.lock().waiters[priority.index()].len()
+    }
+
+    /// Number of callers waiting across all classes.
+    pub(crate) fn total_waiting(&self) -> usize {
+        self.state.lock().waiters.iter().map(VecDeque::len).sum()
+    }
+
+    /// Most callers waiting at once since the last reset.
+    pub(crate) fn peak_waiting(&self) -> usize {
+        self.state.lock().peak_waiting
+    }
+
+    /// Restart peak tracking from the current number of waiters.
+    pub(crate) fn reset_peak_waiting(&self) {
+        let mut state = self.state.lock();
+        state.peak_waiting = state.waiters.iter().map(VecDeque::len).sum();
+    }
+}
+
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+enum PopState<T> {
+    Start,
+    Waiting {
+        id: u64,
+        rx: Receiver<T>,
+        waker: WakerSlot,
+    },
+    Done,
+}
+
+/// Future returned by [`WaitQueue::pop_async`].
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+pub(crate) struct Pop<'a, T> {
+    queue: &'a WaitQueue<T>,
+    class: usize,
+    state: PopState<T>,
+}
+
+// The future never pins its fields, so it can move freely.
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+impl<T> Unpin for Pop<'_, T> {}
+
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+impl<T> std::future::Future for Pop<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<T>> {
+        use crossbeam_channel::TryRecvError;
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        if let PopState::Start = this.state {
+            let waker = Arc::new(Mutex::new(Some(cx.waker().clone())));
+            match this.queue.enqueue(this.class, Some(Arc::clone(&waker))) {
+                Ok(item) => {
+                    this.state = PopState::Done;
+                    return Poll::Ready(Some(item));
+                }
+                Err((id, rx)) => this.state = PopState::Waiting { id, rx, waker },
+            }
+        }
+
+        let PopState::Waiting { rx, waker, .. } = &this.state else {
+            return Poll::Ready(None);
+        };
+        // Register before checking so a send in between still wakes us.
+        *waker.lock() = Some(cx.waker().clone());
+        let result = match rx.try_recv() {
+            Ok(item) => Some(item),
+            Err(TryRecvError::Disconnected) => None,
+            Err(TryRecvError::Empty) => return Poll::Pending,
+        };
+        this.state = PopState::Done;
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+impl<T> Drop for Pop<'_, T> {
+    fn drop(&mut self) {
+        if let PopState::Waiting { id, rx, .. } = &self.state {
+            if let Some(item) = self.queue.dequeue(self.class, *id, rx) {
+                self.queue.push(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_idle_items() {
+        let queue = WaitQueue::new(&PriorityConfig::default());
+        queue.push(1);
+        queue.push(2);
+
+        assert_eq!(queue.idle_len(), 2);
+        assert_eq!(queue.try_pop(), Some(1));
+        assert_eq!(queue.pop_timeout(Priority::Low, Duration::ZERO), Some(2));
+        assert_eq!(queue.try_pop(), None);
+        assert_eq!(
+            queue.pop_timeout(Priority::High, Duration::from_millis(5)),
+            None
+        );
+        assert_eq!(queue.total_waiting(), 0);
+    }
+
+    #[test]
+    fn test_weighted_handoff() {
+        let queue = Arc::new(WaitQueue::new(&PriorityConfig::default()));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let mut threads = Vec::new();
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            for _ in 0..4 {
+                let queue = Arc::clone(&queue);
+                let order = Arc::clone(&order);
+                threads.push(thread::spawn(move || {
+                    let item = queue.pop_timeout(priority, Duration::from_secs(10));
+                    order.lock().push(priority);
+                    item
+                }));
+            }
+        }
+        while queue.total_waiting() < 12 {
+            thread::yield_now();
+        }
+
+        // Release items one at a time so the handoff order is observable.
+        for i in 0..12 {
+            let served = order.lock().len();
+            queue.push(i);
+            while order.lock().len() == served {
+                thread::yield_now();
+            }
+        }
+        for t in threads {
+            assert!(t.join().unwrap().is_some());
+        }
+
+        let order = order.lock();
+        assert_eq!(order[0], Priority::High);
+        // High outweighs low, but low is still served before the others drain.
+        let first_low = order.iter().position(|p| *p == Priority::Low).unwrap();
+        let last_high = order.iter().rposition(|p| *p == Priority::High).unwrap();
+        assert!(first_low > 0);
+        assert!(first_low < 11);
+        assert!(last_high < order.len() - 1);
+    }
+
+    #[test]
+    fn test_timed_out_waiter_is_skipped() {
+        let queue = Arc::new(WaitQueue::new(&PriorityConfig::default()));
+        assert_eq!(
+            queue.pop_timeout(Priority::High, Duration::from_millis(1)),
+            None
+        );
+        assert_eq!(queue.waiting(Priority::High), 0);
+
+        queue.push(7);
+        assert_eq!(queue.try_pop(), Some(7));
+    }
+
+    #[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+    #[tokio::test]
+    async fn test_async_pop() {
+        let queue = Arc::new(WaitQueue::new(&PriorityConfig::default()));
+        queue.push(1);
+        assert_eq!(queue.pop_async(Priority::Normal).await, Some(1));
+
+        let pusher = Arc::clone(&queue);
+        let task = tokio::spawn(async move {
+            while pusher.total_waiting() == 0 {
+                tokio::task::yield_now().await;
+            }
+            pusher.push(2);
+        });
+        assert_eq!(queue.pop_async(Priority::Low).await, Some(2));
+        task.await.unwrap();
+
+        // Closing wakes an async waiter empty-handed.
+        let closer = Arc::clone(&queue);
+        let task = tokio::spawn(async move {
+            while closer.total_waiting() == 0 {
+                tokio::task::yield_now().await;
+            }
+            closer.close();
+        });
+        assert_eq!(queue.pop_async(Priority::High).await, None);
+        task.await.unwrap();
+    }
+
+    #[cfg(any(feature = "async-runtime-tokio", feature = "async-runtime-async-std"))]
+    #[tokio::test]
+    async fn test_dropped_async_pop_leaves_queue() {
+        let queue = WaitQueue::new(&PriorityConfig::default());
+        let wait = tokio::time::timeout(Duration::from_millis(5), queue.pop_async(Priority::High));
+        assert!(wait.await.is_err());
+        assert_eq!(queue.total_waiting(), 0);
+
+        queue.push(3);
+        assert_eq!(queue.try_pop(), Some(3));
+    }
+}
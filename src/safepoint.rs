@@ -0,0 +1,199 @@
+//! Safe points inside running scripts.
+//!
+//! The VM offers no per-instruction hook, so chunks are rewritten before
+//! they run: a call to the [`SAFEPOINT`] native is inserted at the start of
+//! every function and ahead of every backward jump. Loops and recursion
+//! therefore reach a safe point regularly, where cancellation and timeouts
+//! are checked.
+
+use std::sync::Arc;
+
+use fusabi_vm::{Chunk, Instruction, Value as VmValue};
+
+use crate::error::{Error, Result};
+
+/// Global name of the safe point native. Scripts cannot spell it.
+pub(crate) const SAFEPOINT: &str = "#safepoint";
+
+/// Insert safe points into `chunk` and every function prototype it holds.
+pub(crate) fn instrument(chunk: &mut Chunk) -> Result<()> {
+    for constant in &mut chunk.constants {
+        if let VmValue::Closure(closure) = constant {
+            instrument(&mut Arc::make_mut(closure).chunk)?;
+        }
+    }
+
+    let name = push_constant(chunk, VmValue::Str(SAFEPOINT.to_string()))?;
+    let unit = push_constant(chunk, VmValue::Unit)?;
+    // Stack-neutral: call the native with unit and drop its result.
+    let probe = [
+        Instruction::LoadGlobal(name),
+        Instruction::LoadConst(unit),
+        Instruction::Call(1),
+        Instruction::Pop,
+    ];
+
+    let original = std::mem::take(&mut chunk.instructions);
+    // Where each original instruction, and any probe ahead of it, now starts.
+    let mut starts = Vec::with_capacity(original.len() + 1);
+    // Where each original instruction itself now sits.
+    let mut positions = Vec::with_capacity(original.len());
+    let mut code = Vec::with_capacity(original.len() + probe.len());
+    // Probes take the source line of the instruction they precede.
+    let old_lines = std::mem::take(&mut chunk.lines);
+    let line_of = |index: usize| old_lines.get(index).or(old_lines.last()).copied();
+    let probe_lines = |index: usize| {
+        line_of(index)
+            .into_iter()
+            .flat_map(|line| std::iter::repeat(line).take(probe.len()))
+    };
+    let mut lines = Vec::new();
+    code.extend(probe);
+    lines.extend(probe_lines(0));
+    for (index, instruction) in original.iter().enumerate() {
+        starts.push(code.len());
+        if jump_offset(instruction).is_some_and(|offset| offset < 0) {
+            code.extend(probe);
+            lines.extend(probe_lines(index));
+        }
+        positions.push(code.len());
+        code.push(*instruction);
+        lines.extend(line_of(index));
+    }
+    starts.push(code.len());
+
+    // Jumps are relative to the next instruction; retarget them.
+    for (index, instruction) in original.iter().enumerate() {
+        let Some(offset) = jump_offset(instruction) else {
+            continue;
+        };
+        let target = usize::try_from(index as i64 + 1 + i64::from(offset))
+            .ok()
+            .and_then(|target| starts.get(target))
+            .ok_or_else(|| {
+                Error::invalid_bytecode(format!("jump at offset {} leaves its function", index))
+            })?;
+        let position = positions[index];
+        let offset = i16::try_from(*target as i64 - (position as i64 + 1)).map_err(|_| {
+            Error::invalid_bytecode(format!(
+                "jump at offset {} is too long once safe points are added",
+                index
+            ))
+        })?;
+        code[position] = match instruction {
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(offset),
+            _ => Instruction::Jump(offset),
+        };
+    }
+
+    chunk.instructions = code;
+    chunk.lines = lines;
+    Ok(())
+}
+
+fn jump_offset(instruction: &Instruction) -> Option<i16> {
+    match instruction {
+        Instruction::Jump(offset) | Instruction::JumpIfFalse(offset) => Some(*offset),
+        _ => None,
+    }
+}
+
+fn push_constant(chunk: &mut Chunk, value: VmValue) -> Result<u16> {
+    let index = u16::try_from(chunk.constants.len())
+        .map_err(|_| Error::invalid_bytecode("too many constants to add safe points"))?;
+    chunk.constants.push(value);
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> Chunk {
+        fusabi_frontend::compile_program_from_source(source).unwrap()
+    }
+
+    fn probes(chunk: &Chunk) -> usize {
+        chunk
+            .instructions
+            .iter()
+            .filter(|i| matches!(i, Instruction::Call(1)))
+            .count()
+    }
+
+    #[test]
+    fn test_function_entries_and_loops_get_safe_points() {
+        let mut chunk = compile("let rec count n = if n = 0 then 0 else count (n - 1)\ncount 3");
+        instrument(&mut chunk).unwrap();
+        assert_eq!(chunk.lines.len(), chunk.instructions.len());
+        assert_eq!(chunk.lines[0], 1);
+
+        assert_eq!(
+            chunk.instructions[0],
+            Instruction::LoadGlobal(name_index(&chunk))
+        );
+        let function = chunk
+            .constants
+            .iter()
+            .find_map(|c| match c {
+                VmValue::Closure(closure) => Some(closure),
+                _ => None,
+            })
+            .unwrap();
+        assert!(matches!(
+            function.chunk.instructions[0],
+            Instruction::LoadGlobal(_)
+        ));
+    }
+
+    #[test]
+    fn test_jumps_are_retargeted() {
+        let mut chunk = compile("if 1 < 2 then 10 else 20");
+        instrument(&mut chunk).unwrap();
+        assert_eq!(probes(&chunk), 1);
+
+        let mut vm = fusabi_vm::Vm::new();
+        vm.globals.insert(
+            SAFEPOINT.to_string(),
+            VmValue::NativeFn {
+                name: SAFEPOINT.to_string(),
+                arity: 0,
+                args: Vec::new(),
+            },
+        );
+        vm.host_registry
+            .lock()
+            .unwrap()
+            .register(SAFEPOINT, |_vm, _args| Ok(VmValue::Unit));
+        assert!(matches!(vm.execute(chunk).unwrap(), VmValue::Int(10)));
+    }
+
+    #[test]
+    fn test_backward_jumps_get_safe_points() {
+        let mut chunk = Chunk {
+            instructions: vec![
+                Instruction::Pop,
+                Instruction::JumpIfFalse(1),
+                Instruction::Jump(-3),
+                Instruction::Return,
+            ],
+            ..Chunk::default()
+        };
+        instrument(&mut chunk).unwrap();
+
+        // Entry probe, the loop body, then a probe ahead of the backward jump.
+        assert_eq!(probes(&chunk), 2);
+        assert_eq!(chunk.instructions[4], Instruction::Pop);
+        assert_eq!(chunk.instructions[5], Instruction::JumpIfFalse(5));
+        assert_eq!(chunk.instructions[10], Instruction::Jump(-7));
+        assert_eq!(chunk.instructions[11], Instruction::Return);
+    }
+
+    fn name_index(chunk: &Chunk) -> u16 {
+        chunk
+            .constants
+            .iter()
+            .position(|c| matches!(c, VmValue::Str(s) if s == SAFEPOINT))
+            .unwrap() as u16
+    }
+}
@@ -3,6 +3,8 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+use parking_lot::Mutex;
+
 /// Policy for filesystem path access.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum PathPolicy {
@@ -249,6 +251,8 @@ impl SandboxConfig {
 pub struct Sandbox {
     config: SandboxConfig,
     temp_dir: Option<PathBuf>,
+    /// Extra policy enforced for the current execution.
+    restriction: Mutex<Option<SandboxConfig>>,
 }
 
 impl Sandbox {
@@ -263,7 +267,11 @@ impl Sandbox {
             None
         };
 
-        Ok(Self { config, temp_dir })
+        Ok(Self {
+            config,
+            temp_dir,
+            restriction: Mutex::new(None),
+        })
     }
 
     /// Get the sandbox configuration.
@@ -271,6 +279,21 @@ impl Sandbox {
         &self.config
     }
 
+    /// Get the policy enforced on top of the configuration, if any.
+    pub fn restriction(&self) -> Option<SandboxConfig> {
+        self.restriction.lock().clone()
+    }
+
+    /// Enforce `restriction` in addition to the configuration.
+    pub(crate) fn restrict(&self, restriction: Option<SandboxConfig>) {
+        *self.restriction.lock() = restriction;
+    }
+
+    /// Check a permission against the configuration and any restriction.
+    fn allows(&self, check: impl Fn(&SandboxConfig) -> bool) -> bool {
+        check(&self.config) && self.restriction.lock().as_ref().map_or(true, check)
+    }
+
     /// Get the isolated temp directory, if any.
     pub fn temp_dir(&self) -> Option<&Path> {
         self.temp_dir.as_deref()
@@ -278,7 +301,7 @@ impl Sandbox {
 
     /// Check read permission and return an error if denied.
     pub fn check_read(&self, path: &Path) -> crate::Result<()> {
-        if self.config.can_read(path) {
+        if self.allows(|c| c.can_read(path)) {
             Ok(())
         } else {
             Err(crate::Error::sandbox_violation(format!(
@@ -290,7 +313,7 @@ impl Sandbox {
 
     /// Check write permission and return an error if denied.
     pub fn check_write(&self, path: &Path) -> crate::Result<()> {
-        if self.config.can_write(path) {
+        if self.allows(|c| c.can_write(path)) {
             Ok(())
         } else {
             Err(crate::Error::sandbox_violation(format!(
@@ -302,7 +325,7 @@ impl Sandbox {
 
     /// Check network connection permission and return an error if denied.
     pub fn check_connect(&self, host: &str) -> crate::Result<()> {
-        if self.config.can_connect(host) {
+        if self.allows(|c| c.can_connect(host)) {
             Ok(())
         } else {
             Err(crate::Error::sandbox_violation(format!(
@@ -314,7 +337,7 @@ impl Sandbox {
 
     /// Check environment variable access and return an error if denied.
     pub fn check_env(&self, name: &str) -> crate::Result<()> {
-        if self.config.can_access_env(name) {
+        if self.allows(|c| c.can_access_env(name)) {
             Ok(())
         } else {
             Err(crate::Error::sandbox_violation(format!(
@@ -415,4 +438,24 @@ mod tests {
         assert!(config.can_access_env("HOME"));
         assert!(!config.can_access_env("SECRET"));
     }
+
+    #[test]
+    fn test_sandbox_restriction_only_narrows() {
+        let sandbox = Sandbox::new(SandboxConfig::permissive()).unwrap();
+        sandbox.restrict(Some(SandboxConfig::locked().with_allowed_hosts(["a.com"])));
+        assert!(sandbox.check_connect("a.com").is_ok());
+        assert!(sandbox.check_connect("b.com").is_err());
+        assert!(sandbox.check_read(Path::new("/etc/passwd")).is_err());
+
+        sandbox.restrict(None);
+        assert!(sandbox.check_connect("b.com").is_ok());
+
+        let locked = Sandbox::new(SandboxConfig {
+            isolate_temp: false,
+            ..SandboxConfig::locked()
+        })
+        .unwrap();
+        locked.restrict(Some(SandboxConfig::permissive()));
+        assert!(locked.check_connect("a.com").is_err());
+    }
 }
@@ -0,0 +1,759 @@
+//! Periodic and scheduled script execution.
+//!
+//! A [`Scheduler`] runs named [`ScheduledTask`]s on an [`EnginePool`] at a
+//! fixed interval or at the times matching a [`Cron`] expression. A task
+//! never overlaps itself: a run that comes due while the previous one is
+//! still going is skipped. Failed runs push the next run out with
+//! exponential backoff, and each task keeps a bounded history of its runs.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::cron::Cron;
+use crate::error::{Error, Result};
+use crate::job::{Job, JobHandle};
+use crate::pool::EnginePool;
+use crate::value::Value;
+
+/// Longest the scheduler sleeps when no task is due.
+const IDLE_WAIT: Duration = Duration::from_secs(60);
+
+/// When a scheduled task runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run at a fixed rate.
+    Interval(Duration),
+    /// Run at the times matching a cron expression.
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Run every `interval`.
+    pub fn every(interval: Duration) -> Self {
+        Self::Interval(interval.max(Duration::from_millis(1)))
+    }
+
+    /// Run at the times matching a cron expression.
+    pub fn cron(expr: &str) -> Result<Self> {
+        Cron::parse(expr).map(Self::Cron)
+    }
+
+    /// Get the first run time no earlier than `earliest`.
+    fn first(&self, earliest: Instant) -> Option<Instant> {
+        match self {
+            Self::Interval(_) => Some(earliest),
+            Self::Cron(cron) => {
+                let now = Instant::now();
+                let wall = SystemTime::now() + earliest.saturating_duration_since(now);
+                let next = cron.next_after(wall)?;
+                Some(now + next.duration_since(SystemTime::now()).unwrap_or_default())
+            }
+        }
+    }
+
+    /// Get the run time following one that was due at `due`.
+    ///
+    /// Intervals missed entirely, such as while the process was suspended,
+    /// are dropped rather than run back to back.
+    fn next(&self, due: Instant, now: Instant) -> Option<Instant> {
+        match self {
+            Self::Interval(interval) => {
+                let missed = now.saturating_duration_since(due).as_nanos() / interval.as_nanos();
+                // Too many missed intervals to count: restart from now.
+                let next = u32::try_from(missed + 1)
+                    .ok()
+                    .and_then(|skip| interval.checked_mul(skip))
+                    .and_then(|step| due.checked_add(step));
+                Some(next.unwrap_or(now + *interval))
+            }
+            Self::Cron(_) => self.first(now),
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Interval(interval) => write!(f, "every {:?}", interval),
+            Self::Cron(cron) => write!(f, "cron {}", cron),
+        }
+    }
+}
+
+/// Exponential backoff applied after failed runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay after the first failure.
+    pub initial: Duration,
+    /// Longest delay, reached after repeated failures.
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(300),
+        }
+    }
+}
+
+impl Backoff {
+    /// Get the delay after `failures` consecutive failures.
+    pub fn delay(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u32.checked_shl(failures - 1).unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// A script registered with a [`Scheduler`].
+#[derive(Debug, Clone)]
+pub struct ScheduledTask {
+    /// Job run on each occasion.
+    pub job: Job,
+    /// When the job runs.
+    pub schedule: Schedule,
+    /// Delay before the first run.
+    pub initial_delay: Duration,
+    /// Most random delay added to each run.
+    pub jitter: Duration,
+    /// Delay applied after failed runs.
+    pub backoff: Backoff,
+    /// Number of recent runs kept in the history.
+    pub history_limit: usize,
+}
+
+impl ScheduledTask {
+    /// Create a task running `job` on `schedule`.
+    ///
+    /// Interval tasks first run immediately; cron tasks at the next
+    /// matching time.
+    pub fn new(job: Job, schedule: Schedule) -> Self {
+        Self {
+            job,
+            schedule,
+            initial_delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            backoff: Backoff::default(),
+            history_limit: 16,
+        }
+    }
+
+    /// Delay the first run.
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Add a random delay of up to `jitter` to each run.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the backoff applied after failed runs.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Backoff {
+            initial,
+            max: max.max(initial),
+        };
+        self
+    }
+
+    /// Set the number of recent runs kept in the history.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+}
+
+/// Outcome of a scheduled run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    /// The script returned a value.
+    Succeeded(Value),
+    /// The run failed.
+    Failed {
+        /// The error's [`Error::kind`].
+        kind: &'static str,
+        /// The error message.
+        message: String,
+    },
+}
+
+impl RunOutcome {
+    /// Check if the run succeeded.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Succeeded(_))
+    }
+}
+
+/// Record of one scheduled run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRecord {
+    /// Run number, starting at 1.
+    pub run: u64,
+    /// When the run was dispatched to the pool.
+    pub started_at: SystemTime,
+    /// Time from dispatch to completion.
+    pub duration: Duration,
+    /// How the run ended.
+    pub outcome: RunOutcome,
+}
+
+/// Statistics for one scheduled task.
+#[derive(Debug, Clone)]
+pub struct TaskStats {
+    /// Task name.
+    pub name: String,
+    /// When the task runs.
+    pub schedule: Schedule,
+    /// Completed runs, successful or not.
+    pub runs: u64,
+    /// Failed runs.
+    pub failures: u64,
+    /// Failures since the last successful run.
+    pub consecutive_failures: u32,
+    /// Runs skipped because the previous run was still going.
+    pub skipped: u64,
+    /// Whether a run is in progress.
+    pub running: bool,
+    /// When the next run is due, if the schedule has one.
+    pub next_run: Option<SystemTime>,
+    /// Most recent completed run.
+    pub last_run: Option<RunRecord>,
+}
+
+/// A run in progress.
+struct ActiveRun {
+    handle: JobHandle,
+    started: Instant,
+    started_at: SystemTime,
+}
+
+struct TaskState {
+    task: ScheduledTask,
+    /// When the next run is due, before jitter.
+    due: Option<Instant>,
+    /// Jitter added to the next run.
+    delay: Duration,
+    active: Option<ActiveRun>,
+    runs: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    skipped: u64,
+    history: VecDeque<RunRecord>,
+}
+
+impl TaskState {
+    fn new(task: ScheduledTask) -> Self {
+        let due = task.schedule.first(Instant::now() + task.initial_delay);
+        Self {
+            delay: jitter(task.jitter),
+            task,
+            due,
+            active: None,
+            runs: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            skipped: 0,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// When the next run starts, including jitter.
+    fn next_run(&self) -> Option<Instant> {
+        self.due.map(|due| due + self.delay)
+    }
+
+    /// Start a run if one is due, waking the scheduler when it finishes.
+    fn dispatch(
+        &mut self,
+        name: &str,
+        pool: &EnginePool,
+        scheduler: &Weak<SchedulerShared>,
+        now: Instant,
+    ) {
+        let (Some(due), Some(at)) = (self.due, self.next_run()) else {
+            return;
+        };
+        if at > now {
+            return;
+        }
+        self.due = self.task.schedule.next(due, now);
+        self.delay = jitter(self.task.jitter);
+
+        if self.active.is_some() {
+            self.skipped += 1;
+            tracing::debug!(
+                task = name,
+                "skipping scheduled run, previous run still going"
+            );
+            return;
+        }
+        let started_at = SystemTime::now();
+        match pool.submit(self.task.job.clone()) {
+            Ok(handle) => {
+                let scheduler = Weak::clone(scheduler);
+                handle.on_finish(move || {
+                    if let Some(scheduler) = scheduler.upgrade() {
+                        // Taking the lock ensures the scheduler is waiting.
+                        let _state = scheduler.state.lock();
+                        scheduler.changed.notify_all();
+                    }
+                });
+                self.active = Some(ActiveRun {
+                    handle,
+                    started: now,
+                    started_at,
+                })
+            }
+            Err(e) => self.finish(name, started_at, Duration::ZERO, Err(e), now),
+        }
+    }
+
+    /// Record the active run if it has finished.
+    fn collect(&mut self, name: &str, now: Instant) {
+        let Some(active) = &self.active else { return };
+        let Some(result) = active.handle.try_result() else {
+            return;
+        };
+        let active = self.active.take().expect("active run");
+        let duration = now.saturating_duration_since(active.started);
+        self.finish(name, active.started_at, duration, result, now);
+    }
+
+    fn finish(
+        &mut self,
+        name: &str,
+        started_at: SystemTime,
+        duration: Duration,
+        result: Result<Value>,
+        now: Instant,
+    ) {
+        self.runs += 1;
+        let outcome = match result {
+            Ok(value) => {
+                self.consecutive_failures = 0;
+                RunOutcome::Succeeded(value)
+            }
+            Err(e) => {
+                self.failures += 1;
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                let backoff = self.task.backoff.delay(self.consecutive_failures);
+                tracing::warn!(
+                    task = name,
+                    error = %e,
+                    failures = self.consecutive_failures,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "scheduled run failed"
+                );
+                let earliest = now + backoff;
+                if self.due.is_some_and(|due| due < earliest) {
+                    self.due = self.task.schedule.first(earliest);
+                }
+                RunOutcome::Failed {
+                    kind: e.kind(),
+                    message: e.to_string(),
+                }
+            }
+        };
+
+        self.history.push_back(RunRecord {
+            run: self.runs,
+            started_at,
+            duration,
+            outcome,
+        });
+        while self.history.len() > self.task.history_limit {
+            self.history.pop_front();
+        }
+    }
+
+    fn stats(&self, name: &str) -> TaskStats {
+        let now = Instant::now();
+        TaskStats {
+            name: name.to_string(),
+            schedule: self.task.schedule.clone(),
+            runs: self.runs,
+            failures: self.failures,
+            consecutive_failures: self.consecutive_failures,
+            skipped: self.skipped,
+            running: self.active.is_some(),
+            next_run: self
+                .next_run()
+                .map(|at| SystemTime::now() + at.saturating_duration_since(now)),
+            last_run: self.history.back().cloned(),
+        }
+    }
+}
+
+/// Random delay of up to `max`.
+fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_nanos(random % (max.as_nanos() as u64).saturating_add(1))
+}
+
+struct SchedulerState {
+    tasks: HashMap<String, TaskState>,
+    stopped: bool,
+}
+
+struct SchedulerShared {
+    pool: Arc<EnginePool>,
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+}
+
+impl SchedulerShared {
+    fn run(self: &Arc<Self>) {
+        let scheduler = Arc::downgrade(self);
+        let mut state = self.state.lock();
+        while !state.stopped {
+            let now = Instant::now();
+            let mut wake = now + IDLE_WAIT;
+            for (name, task) in &mut state.tasks {
+                task.collect(name, now);
+                task.dispatch(name, &self.pool, &scheduler, now);
+                // A run may finish before its completion callback is set.
+                task.collect(name, now);
+                if let Some(at) = task.next_run() {
+                    wake = wake.min(at);
+                }
+            }
+            self.changed.wait_until(&mut state, wake);
+        }
+    }
+}
+
+/// Runs scripts periodically on an engine pool.
+///
+/// ```rust,ignore
+/// use fusabi_host::{EnginePool, Job, PoolConfig, Schedule, ScheduledTask, Scheduler};
+///
+/// let scheduler = Scheduler::new(Arc::new(EnginePool::new(PoolConfig::new(2))?))?;
+/// scheduler.add(
+///     "cleanup",
+///     ScheduledTask::new(Job::source(cleanup), Schedule::cron("*/15 * * * *")?)
+///         .with_jitter(Duration::from_secs(30)),
+/// )?;
+///
+/// let stats = scheduler.stats("cleanup").unwrap();
+/// ```
+///
+/// Dropping the scheduler stops it; runs already dispatched finish on the
+/// pool.
+pub struct Scheduler {
+    shared: Arc<SchedulerShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    /// Create a scheduler running tasks on `pool`.
+    pub fn new(pool: Arc<EnginePool>) -> Result<Self> {
+        let shared = Arc::new(SchedulerShared {
+            pool,
+            state: Mutex::new(SchedulerState {
+                tasks: HashMap::new(),
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let worker = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("fusabi-scheduler".to_string())
+            .spawn(move || worker.run())
+            .map_err(|e| Error::Internal(format!("failed to spawn scheduler: {}", e)))?;
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Register a task.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a task with the same name exists.
+    pub fn add(&self, name: impl Into<String>, task: ScheduledTask) -> Result<()> {
+        let name = name.into();
+        let mut state = self.shared.state.lock();
+        if state.tasks.contains_key(&name) {
+            return Err(Error::invalid_config(format!(
+                "scheduled task {} already exists",
+                name
+            )));
+        }
+        state.tasks.insert(name, TaskState::new(task));
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// Remove a task. A run already in progress finishes normally.
+    pub fn remove(&self, name: &str) -> bool {
+        self.shared.state.lock().tasks.remove(name).is_some()
+    }
+
+    /// Get the names of all tasks.
+    pub fn tasks(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.shared.state.lock().tasks.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Get statistics for a task.
+    pub fn stats(&self, name: &str) -> Option<TaskStats> {
+        let state = self.shared.state.lock();
+        state.tasks.get(name).map(|task| task.stats(name))
+    }
+
+    /// Get statistics for all tasks, sorted by name.
+    pub fn all_stats(&self) -> Vec<TaskStats> {
+        let state = self.shared.state.lock();
+        let mut stats: Vec<TaskStats> = state
+            .tasks
+            .iter()
+            .map(|(name, task)| task.stats(name))
+            .collect();
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+
+    /// Get a task's recent runs, oldest first.
+    pub fn history(&self, name: &str) -> Option<Vec<RunRecord>> {
+        let state = self.shared.state.lock();
+        state
+            .tasks
+            .get(name)
+            .map(|task| task.history.iter().cloned().collect())
+    }
+
+    /// Get the pool tasks run on.
+    pub fn pool(&self) -> &Arc<EnginePool> {
+        &self.shared.pool
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.state.lock().stopped = true;
+        self.shared.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("tasks", &self.tasks())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_context::{HostContext, LogLevel};
+    use crate::limits::Limits;
+    use crate::options::ExecOptions;
+    use crate::pool::PoolConfig;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn scheduler() -> Scheduler {
+        Scheduler::new(Arc::new(EnginePool::new(PoolConfig::new(2)).unwrap())).unwrap()
+    }
+
+    fn wait_until(scheduler: &Scheduler, name: &str, f: impl Fn(&TaskStats) -> bool) {
+        let until = Instant::now() + Duration::from_secs(5);
+        while !f(&scheduler.stats(name).unwrap()) {
+            assert!(Instant::now() < until, "condition never held");
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn test_interval_runs_and_history() {
+        let scheduler = scheduler();
+        let task = ScheduledTask::new(Job::source("1"), Schedule::every(Duration::from_millis(5)))
+            .with_history_limit(2);
+        scheduler.add("tick", task.clone()).unwrap();
+        assert!(matches!(
+            scheduler.add("tick", task),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        wait_until(&scheduler, "tick", |s| s.runs >= 3);
+        let history = scheduler.history("tick").unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].run < history[1].run);
+        assert_eq!(history[1].outcome, RunOutcome::Succeeded(Value::Int(1)));
+
+        assert_eq!(scheduler.tasks(), vec!["tick".to_string()]);
+        assert!(scheduler.remove("tick"));
+        assert!(scheduler.stats("tick").is_none());
+    }
+
+    #[test]
+    fn test_initial_delay() {
+        let scheduler = scheduler();
+        scheduler
+            .add(
+                "later",
+                ScheduledTask::new(Job::source("1"), Schedule::every(Duration::from_millis(5)))
+                    .with_initial_delay(Duration::from_secs(3600)),
+            )
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(30));
+        let stats = scheduler.stats("later").unwrap();
+        assert_eq!(stats.runs, 0);
+        let next = stats.next_run.unwrap();
+        assert!(next > SystemTime::now() + Duration::from_secs(3500));
+    }
+
+    #[test]
+    fn test_no_overlapping_runs() {
+        /// Holds executions at their cancellation check until opened.
+        #[derive(Default)]
+        struct Gate {
+            open: AtomicBool,
+        }
+
+        impl HostContext for Gate {
+            fn log(&self, _level: LogLevel, _message: &str) {}
+
+            fn record_metric(&self, _name: &str, _value: f64, _tags: &[(&str, &str)]) {}
+
+            fn should_cancel(&self) -> bool {
+                while !self.open.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                false
+            }
+        }
+
+        let scheduler = scheduler();
+        let gate = Arc::new(Gate::default());
+        let job = Job::source("1").with_options(ExecOptions::new().with_host_context(gate.clone()));
+        scheduler
+            .add(
+                "slow",
+                ScheduledTask::new(job, Schedule::every(Duration::from_millis(5))),
+            )
+            .unwrap();
+
+        wait_until(&scheduler, "slow", |s| s.skipped >= 3);
+        let stats = scheduler.stats("slow").unwrap();
+        assert!(stats.running);
+        assert_eq!(stats.runs, 0);
+        assert_eq!(scheduler.pool().stats().in_use, 1);
+
+        gate.open.store(true, Ordering::SeqCst);
+        wait_until(&scheduler, "slow", |s| s.runs >= 2);
+    }
+
+    #[test]
+    fn test_backoff_after_failure() {
+        let scheduler = scheduler();
+        let job = Job::source("1").with_options(
+            ExecOptions::new().with_limits(Limits::default().with_max_instructions(1)),
+        );
+        scheduler
+            .add(
+                "failing",
+                ScheduledTask::new(job, Schedule::every(Duration::from_millis(5)))
+                    .with_backoff(Duration::from_secs(60), Duration::from_secs(600)),
+            )
+            .unwrap();
+
+        wait_until(&scheduler, "failing", |s| s.runs == 1);
+        thread::sleep(Duration::from_millis(30));
+        let stats = scheduler.stats("failing").unwrap();
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.consecutive_failures, 1);
+        assert!(stats.next_run.unwrap() > SystemTime::now() + Duration::from_secs(50));
+        assert!(matches!(
+            stats.last_run.unwrap().outcome,
+            RunOutcome::Failed {
+                kind: "limit_violation",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+        };
+        assert_eq!(backoff.delay(0), Duration::ZERO);
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(4));
+        assert_eq!(backoff.delay(5), Duration::from_secs(10));
+        assert_eq!(backoff.delay(100), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let schedule = Schedule::cron("@hourly").unwrap();
+        let now = Instant::now();
+        let first = schedule.first(now).unwrap();
+        assert!(first > now && first <= now + Duration::from_secs(3600));
+        assert!(Schedule::cron("61 * * * *").is_err());
+
+        let interval = Schedule::every(Duration::from_secs(10));
+        let due = now;
+        assert_eq!(interval.next(due, now), Some(due + Duration::from_secs(10)));
+        assert_eq!(
+            interval.next(due, now + Duration::from_secs(25)),
+            Some(due + Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_missed_intervals_saturate() {
+        let interval = Schedule::every(Duration::from_millis(1));
+        let due = Instant::now();
+        // More missed intervals than fit in a u32.
+        let now = due + Duration::from_secs(100 * 24 * 3600);
+        let next = interval.next(due, now).unwrap();
+        assert!(next > now);
+    }
+
+    #[test]
+    fn test_finished_run_wakes_scheduler() {
+        let scheduler = scheduler();
+        scheduler
+            .add(
+                "hourly",
+                ScheduledTask::new(Job::source("1"), Schedule::every(Duration::from_secs(3600))),
+            )
+            .unwrap();
+
+        // Nothing else is due for an hour, so only the run finishing can
+        // wake the scheduler to record it.
+        wait_until(&scheduler, "hourly", |s| s.runs == 1);
+        let stats = scheduler.stats("hourly").unwrap();
+        assert!(!stats.running);
+        assert_eq!(
+            stats.last_run.unwrap().outcome,
+            RunOutcome::Succeeded(Value::Int(1))
+        );
+    }
+}
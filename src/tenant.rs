@@ -0,0 +1,815 @@
+//! Multi-tenant engine pools.
+//!
+//! A [`TenantPool`] partitions a fixed number of concurrent executions
+//! between tenants. All tenants run on one [`EnginePool`] of at most that
+//! many engines; each tenant's limits, capabilities and sandbox narrow the
+//! pool's configuration for its executions. Tenants get a reserved minimum
+//! that other tenants can never take and a concurrency cap. Capacity a
+//! tenant is not using is lent to busy tenants in proportion to their
+//! weights.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::capabilities::Capabilities;
+use crate::engine::Engine;
+use crate::error::{Error, Result};
+use crate::histogram::{LatencyHistogram, LatencySnapshot};
+use crate::limits::Limits;
+use crate::options::ExecOptions;
+use crate::pool::{EnginePool, PoolConfig, PoolHandle, PoolStats};
+use crate::priority::Priority;
+use crate::sandbox::SandboxConfig;
+use crate::value::Value;
+
+/// Configuration for one tenant of a [`TenantPool`].
+#[derive(Debug, Clone)]
+pub struct TenantConfig {
+    /// Executions always available to this tenant.
+    pub reserved: usize,
+    /// Most concurrent executions this tenant may run.
+    pub max_concurrency: usize,
+    /// Share of lent capacity relative to other tenants.
+    pub weight: u32,
+    /// Limits combined with the pool's, keeping the tighter bound.
+    pub limits: Option<Limits>,
+    /// Capabilities intersected with the pool's.
+    pub capabilities: Option<Capabilities>,
+    /// Sandbox policy enforced alongside the pool's.
+    pub sandbox: Option<SandboxConfig>,
+}
+
+impl Default for TenantConfig {
+    fn default() -> Self {
+        Self {
+            reserved: 0,
+            max_concurrency: usize::MAX,
+            weight: 1,
+            limits: None,
+            capabilities: None,
+            sandbox: None,
+        }
+    }
+}
+
+impl TenantConfig {
+    /// Create a tenant configuration with no reservation or cap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of reserved executions.
+    pub fn with_reserved(mut self, reserved: usize) -> Self {
+        self.reserved = reserved;
+        self
+    }
+
+    /// Set the concurrency cap.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = max.max(1);
+        self
+    }
+
+    /// Set the weight used when lending idle capacity.
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+
+    /// Narrow resource limits for this tenant.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Narrow capabilities for this tenant.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Narrow the sandbox policy for this tenant.
+    pub fn with_sandbox(mut self, sandbox: SandboxConfig) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Execution options applying this tenant's restrictions.
+    fn options(&self) -> ExecOptions {
+        ExecOptions {
+            limits: self.limits.clone(),
+            capabilities: self.capabilities.clone(),
+            sandbox: self.sandbox.clone(),
+            ..ExecOptions::default()
+        }
+    }
+}
+
+/// Statistics for one tenant.
+#[derive(Debug, Clone)]
+pub struct TenantStats {
+    /// Tenant name.
+    pub tenant: String,
+    /// Reserved executions.
+    pub reserved: usize,
+    /// Concurrency cap.
+    pub max_concurrency: usize,
+    /// Executions currently running.
+    pub in_use: usize,
+    /// Executions running on capacity lent by other tenants.
+    pub borrowed: usize,
+    /// Callers waiting for admission.
+    pub waiting: usize,
+    /// Admissions that timed out.
+    pub rejections: u64,
+    /// Executions run through the tenant's handles.
+    pub executions: u64,
+    /// Time admitted callers waited for admission and an engine.
+    pub acquire_wait: LatencySnapshot,
+    /// Time spent executing the tenant's scripts.
+    pub execution_time: LatencySnapshot,
+    /// Failed executions by [`Error::kind`].
+    pub errors: BTreeMap<&'static str, u64>,
+}
+
+impl TenantStats {
+    /// Total number of failed executions.
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Execution metrics of one tenant, shared with its handles.
+struct TenantMetrics {
+    executions: AtomicU64,
+    acquire_wait: LatencyHistogram,
+    execution_time: LatencyHistogram,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl TenantMetrics {
+    fn new() -> Self {
+        Self {
+            executions: AtomicU64::new(0),
+            acquire_wait: LatencyHistogram::new(),
+            execution_time: LatencyHistogram::new(),
+            errors: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn record_execution<T>(&self, duration: Duration, result: &Result<T>) {
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        self.execution_time.record(duration);
+        if let Err(error) = result {
+            *self.errors.lock().entry(error.kind()).or_insert(0) += 1;
+        }
+    }
+}
+
+struct Tenant {
+    /// Distinguishes this tenant from a later one added under the same name.
+    id: u64,
+    config: TenantConfig,
+    in_use: usize,
+    waiting: usize,
+    rejections: u64,
+    metrics: Arc<TenantMetrics>,
+}
+
+impl Tenant {
+    fn new(id: u64, config: TenantConfig) -> Self {
+        Self {
+            id,
+            config,
+            in_use: 0,
+            waiting: 0,
+            rejections: 0,
+            metrics: Arc::new(TenantMetrics::new()),
+        }
+    }
+
+    /// Capacity this tenant holds: its reservation or what it is using.
+    fn held(&self) -> usize {
+        self.in_use.max(self.config.reserved)
+    }
+
+    fn borrowed(&self) -> usize {
+        self.in_use.saturating_sub(self.config.reserved)
+    }
+
+    /// Whether the next admission would borrow lent capacity.
+    fn wants_to_borrow(&self) -> bool {
+        self.waiting > 0
+            && self.in_use >= self.config.reserved
+            && self.in_use < self.config.max_concurrency
+    }
+}
+
+struct TenantState {
+    capacity: usize,
+    tenants: HashMap<String, Tenant>,
+    /// Executions of removed tenants that are still running.
+    retired: usize,
+    next_id: u64,
+}
+
+impl TenantState {
+    fn can_admit(&self, name: &str) -> bool {
+        let tenant = &self.tenants[name];
+        if tenant.in_use >= tenant.config.max_concurrency {
+            return false;
+        }
+        if tenant.in_use < tenant.config.reserved {
+            return true;
+        }
+
+        let held = self.retired + self.tenants.values().map(Tenant::held).sum::<usize>();
+        if held >= self.capacity {
+            return false;
+        }
+
+        // Lend to the waiting borrower with the smallest weighted share so
+        // far: admit only if no other borrower is further behind.
+        let weight = tenant.config.weight as u64;
+        let borrowed = tenant.borrowed() as u64;
+        self.tenants
+            .iter()
+            .filter(|(other, t)| other.as_str() != name && t.wants_to_borrow())
+            .all(|(_, t)| borrowed * t.config.weight as u64 <= t.borrowed() as u64 * weight)
+    }
+}
+
+struct TenantShared {
+    state: Mutex<TenantState>,
+    admitted: Condvar,
+}
+
+impl TenantShared {
+    fn release(&self, tenant: &str, id: u64) {
+        let mut state = self.state.lock();
+        match state.tenants.get_mut(tenant) {
+            Some(t) if t.id == id => t.in_use = t.in_use.saturating_sub(1),
+            _ => state.retired = state.retired.saturating_sub(1),
+        }
+        drop(state);
+        self.admitted.notify_all();
+    }
+}
+
+/// An engine pool partitioned between tenants.
+///
+/// ```rust,ignore
+/// use fusabi_host::{Limits, PoolConfig, TenantConfig, TenantPool};
+///
+/// let pool = TenantPool::new(PoolConfig::default(), 8)?;
+/// pool.add_tenant("acme", TenantConfig::new().with_reserved(2).with_max_concurrency(6))?;
+/// pool.add_tenant("globex", TenantConfig::new().with_reserved(2).with_limits(Limits::strict()))?;
+///
+/// let result = pool.execute("acme", "1 + 2")?;
+/// ```
+pub struct TenantPool {
+    pool: EnginePool,
+    shared: Arc<TenantShared>,
+}
+
+impl TenantPool {
+    /// Create a tenant pool running at most `capacity` executions at once.
+    ///
+    /// `template` supplies the engine configuration, registry, initializer
+    /// and timeouts of the shared pool, which creates engines on demand and
+    /// never holds more than `capacity` of them.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the engine pool cannot be created.
+    pub fn new(template: PoolConfig, capacity: usize) -> Result<Self> {
+        let capacity = capacity.max(1);
+        let mut config = template;
+        config.size = capacity;
+        config.min_size = capacity;
+        config.max_size = capacity;
+        config.lazy_init = true;
+        config.autoscale = None;
+
+        Ok(Self {
+            pool: EnginePool::new(config)?,
+            shared: Arc::new(TenantShared {
+                state: Mutex::new(TenantState {
+                    capacity,
+                    tenants: HashMap::new(),
+                    retired: 0,
+                    next_id: 0,
+                }),
+                admitted: Condvar::new(),
+            }),
+        })
+    }
+
+    /// Add a tenant.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tenant exists or the reservations would exceed the
+    /// pool's capacity.
+    pub fn add_tenant(&self, name: impl Into<String>, config: TenantConfig) -> Result<()> {
+        let name = name.into();
+        let mut state = self.shared.state.lock();
+        if state.tenants.contains_key(&name) {
+            return Err(Error::invalid_config(format!(
+                "tenant {} already exists",
+                name
+            )));
+        }
+        let reserved: usize = state.tenants.values().map(|t| t.config.reserved).sum();
+        if reserved + config.reserved > state.capacity {
+            return Err(Error::invalid_config(format!(
+                "tenant {} reserves {} engines but only {} of {} are unreserved",
+                name,
+                config.reserved,
+                state.capacity - reserved,
+                state.capacity
+            )));
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.tenants.insert(name, Tenant::new(id, config));
+        Ok(())
+    }
+
+    /// Remove a tenant.
+    ///
+    /// Executions already running finish normally and hold their share of
+    /// the capacity until they are released.
+    pub fn remove_tenant(&self, name: &str) -> bool {
+        let mut state = self.shared.state.lock();
+        let removed = match state.tenants.remove(name) {
+            Some(tenant) => {
+                state.retired += tenant.in_use;
+                true
+            }
+            None => false,
+        };
+        drop(state);
+        self.shared.admitted.notify_all();
+        removed
+    }
+
+    /// Get the names of all tenants.
+    pub fn tenants(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.shared.state.lock().tenants.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Acquire an engine for a tenant.
+    pub fn acquire(&self, tenant: &str) -> Result<TenantHandle> {
+        self.acquire_with_priority(tenant, Priority::Normal)
+    }
+
+    /// Acquire an engine for a tenant in the given priority class.
+    ///
+    /// Blocks until the tenant is admitted under its reservation, cap and
+    /// weighted share of lent capacity, or the class's acquire timeout (the
+    /// pool's by default) expires. Admission never exceeds the pool's
+    /// capacity, so an admitted tenant gets an engine without waiting again.
+    pub fn acquire_with_priority(&self, tenant: &str, priority: Priority) -> Result<TenantHandle> {
+        let config = self.pool.config();
+        let timeout = config
+            .priorities
+            .class(priority)
+            .acquire_timeout
+            .unwrap_or(config.acquire_timeout);
+        let start = Instant::now();
+        let admission = self.admit(tenant, timeout)?;
+
+        match self.pool.try_acquire() {
+            Ok(handle) => {
+                admission.metrics.acquire_wait.record(start.elapsed());
+                Ok(TenantHandle {
+                    handle: Some(handle),
+                    options: admission.options,
+                    tenant: tenant.to_string(),
+                    id: admission.id,
+                    metrics: admission.metrics,
+                    shared: Arc::clone(&self.shared),
+                })
+            }
+            Err(e) => {
+                self.shared.release(tenant, admission.id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Wait for admission and reserve an execution slot.
+    fn admit(&self, tenant: &str, timeout: Duration) -> Result<Admission> {
+        let unknown = || Error::invalid_config(format!("unknown tenant {}", tenant));
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state.lock();
+        state.tenants.get_mut(tenant).ok_or_else(unknown)?.waiting += 1;
+
+        loop {
+            if !state.tenants.contains_key(tenant) {
+                return Err(unknown());
+            }
+            if state.can_admit(tenant) {
+                let t = state.tenants.get_mut(tenant).expect("tenant checked above");
+                t.waiting -= 1;
+                t.in_use += 1;
+                return Ok(Admission {
+                    options: t.config.options(),
+                    id: t.id,
+                    metrics: Arc::clone(&t.metrics),
+                });
+            }
+            if self
+                .shared
+                .admitted
+                .wait_until(&mut state, deadline)
+                .timed_out()
+            {
+                if let Some(t) = state.tenants.get_mut(tenant) {
+                    t.waiting -= 1;
+                    t.rejections += 1;
+                }
+                drop(state);
+                // Another waiter may now be first in line for lent capacity.
+                self.shared.admitted.notify_all();
+                return Err(Error::PoolTimeout);
+            }
+        }
+    }
+
+    /// Execute source code for a tenant.
+    pub fn execute(&self, tenant: &str, source: &str) -> Result<Value> {
+        self.acquire(tenant)?.execute(source)
+    }
+
+    /// Execute bytecode for a tenant.
+    pub fn execute_bytecode(&self, tenant: &str, bytecode: &[u8]) -> Result<Value> {
+        self.acquire(tenant)?.execute_bytecode(bytecode)
+    }
+
+    /// Get statistics for a tenant.
+    pub fn stats(&self, tenant: &str) -> Option<TenantStats> {
+        let state = self.shared.state.lock();
+        let t = state.tenants.get(tenant)?;
+        let errors = t.metrics.errors.lock().clone();
+        Some(TenantStats {
+            tenant: tenant.to_string(),
+            reserved: t.config.reserved,
+            max_concurrency: t.config.max_concurrency,
+            in_use: t.in_use,
+            borrowed: t.borrowed(),
+            waiting: t.waiting,
+            rejections: t.rejections,
+            executions: t.metrics.executions.load(Ordering::Relaxed),
+            acquire_wait: t.metrics.acquire_wait.snapshot(),
+            execution_time: t.metrics.execution_time.snapshot(),
+            errors,
+        })
+    }
+
+    /// Get statistics of the engine pool shared by all tenants.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Get the total execution capacity.
+    pub fn capacity(&self) -> usize {
+        self.shared.state.lock().capacity
+    }
+
+    /// Shut down the shared engine pool.
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+}
+
+/// An admitted execution slot.
+struct Admission {
+    options: ExecOptions,
+    id: u64,
+    metrics: Arc<TenantMetrics>,
+}
+
+impl std::fmt::Debug for TenantPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantPool")
+            .field("capacity", &self.capacity())
+            .field("tenants", &self.tenants())
+            .finish()
+    }
+}
+
+/// A handle to an engine admitted for a tenant.
+///
+/// Executions through the handle run under the tenant's restrictions.
+/// Dropping it returns the engine to the pool and frees the tenant's
+/// execution slot.
+pub struct TenantHandle {
+    handle: Option<PoolHandle>,
+    options: ExecOptions,
+    tenant: String,
+    id: u64,
+    metrics: Arc<TenantMetrics>,
+    shared: Arc<TenantShared>,
+}
+
+impl TenantHandle {
+    /// Get the tenant this handle was admitted for.
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Execute source code under the tenant's restrictions.
+    pub fn execute(&self, source: &str) -> Result<Value> {
+        self.execute_with(source, ExecOptions::new())
+    }
+
+    /// Execute bytecode under the tenant's restrictions.
+    pub fn execute_bytecode(&self, bytecode: &[u8]) -> Result<Value> {
+        self.execute_bytecode_with(bytecode, ExecOptions::new())
+    }
+
+    /// Execute source code with per-execution options.
+    ///
+    /// The options can only narrow the tenant's restrictions further.
+    pub fn execute_with(&self, source: &str, options: ExecOptions) -> Result<Value> {
+        let options = self.options.narrow(options)?;
+        self.measured(|handle| handle.execute_with(source, options))
+    }
+
+    /// Execute bytecode with per-execution options.
+    pub fn execute_bytecode_with(&self, bytecode: &[u8], options: ExecOptions) -> Result<Value> {
+        let options = self.options.narrow(options)?;
+        self.measured(|handle| handle.execute_bytecode_with(bytecode, options))
+    }
+
+    /// Run an execution, recording it in the tenant's statistics.
+    fn measured(&self, f: impl FnOnce(&PoolHandle) -> Result<Value>) -> Result<Value> {
+        let start = Instant::now();
+        let result = f(self.pool_handle());
+        self.metrics.record_execution(start.elapsed(), &result);
+        result
+    }
+
+    /// Get a reference to the underlying engine.
+    pub fn engine(&self) -> &Engine {
+        self.pool_handle().engine()
+    }
+
+    /// Cancel the current execution.
+    pub fn cancel(&self) {
+        self.pool_handle().cancel();
+    }
+
+    fn pool_handle(&self) -> &PoolHandle {
+        self.handle.as_ref().expect("handle is present until drop")
+    }
+}
+
+impl Drop for TenantHandle {
+    fn drop(&mut self) {
+        // Return the engine before admitting the next caller.
+        drop(self.handle.take());
+        self.shared.release(&self.tenant, self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn pool(capacity: usize) -> TenantPool {
+        let template = PoolConfig::new(1).with_acquire_timeout(Duration::from_millis(20));
+        TenantPool::new(template, capacity).unwrap()
+    }
+
+    #[test]
+    fn test_reservations_and_caps() {
+        let pool = pool(4);
+        pool.add_tenant(
+            "a",
+            TenantConfig::new().with_reserved(1).with_max_concurrency(2),
+        )
+        .unwrap();
+        pool.add_tenant("b", TenantConfig::new().with_reserved(2))
+            .unwrap();
+
+        assert!(pool.add_tenant("a", TenantConfig::new()).is_err());
+        assert!(pool
+            .add_tenant("c", TenantConfig::new().with_reserved(2))
+            .is_err());
+        assert!(matches!(
+            pool.acquire("missing"),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        let a1 = pool.acquire("a").unwrap();
+        let a2 = pool.acquire("a").unwrap();
+        // Capped at two even though capacity remains.
+        assert!(matches!(pool.acquire("a"), Err(Error::PoolTimeout)));
+
+        let stats = pool.stats("a").unwrap();
+        assert_eq!(stats.in_use, 2);
+        assert_eq!(stats.borrowed, 1);
+        assert_eq!(stats.rejections, 1);
+
+        // b's reservation is untouched by a's borrowing.
+        let _b1 = pool.acquire("b").unwrap();
+        let _b2 = pool.acquire("b").unwrap();
+        assert!(matches!(pool.acquire("b"), Err(Error::PoolTimeout)));
+
+        drop(a1);
+        drop(a2);
+        assert_eq!(pool.stats("a").unwrap().in_use, 0);
+    }
+
+    #[test]
+    fn test_idle_capacity_is_lent() {
+        let pool = pool(3);
+        pool.add_tenant("a", TenantConfig::new().with_reserved(1))
+            .unwrap();
+        pool.add_tenant("b", TenantConfig::new().with_reserved(1))
+            .unwrap();
+
+        // a borrows the unreserved slot but not b's reservation.
+        let _a1 = pool.acquire("a").unwrap();
+        let _a2 = pool.acquire("a").unwrap();
+        assert!(matches!(pool.acquire("a"), Err(Error::PoolTimeout)));
+        assert_eq!(pool.stats("a").unwrap().borrowed, 1);
+
+        let _b1 = pool.acquire("b").unwrap();
+        assert!(matches!(pool.acquire("b"), Err(Error::PoolTimeout)));
+    }
+
+    #[test]
+    fn test_weighted_lending() {
+        let mut state = TenantState {
+            capacity: 10,
+            tenants: HashMap::new(),
+            retired: 0,
+            next_id: 0,
+        };
+        for (id, (name, weight, in_use)) in
+            [("heavy", 3, 3), ("light", 1, 1)].into_iter().enumerate()
+        {
+            let mut tenant = Tenant::new(id as u64, TenantConfig::new().with_weight(weight));
+            tenant.in_use = in_use;
+            tenant.waiting = 1;
+            state.tenants.insert(name.into(), tenant);
+        }
+
+        // 3/3 vs 1/1: equal weighted shares, either may go.
+        assert!(state.can_admit("heavy"));
+        assert!(state.can_admit("light"));
+
+        state.tenants.get_mut("heavy").unwrap().in_use = 4;
+        assert!(!state.can_admit("heavy"));
+        assert!(state.can_admit("light"));
+    }
+
+    #[test]
+    fn test_tenant_overrides_narrow_template() {
+        use crate::capabilities::Capability;
+        use crate::compile::{compile_source, CompileOptions};
+        use crate::engine::{EngineConfig, HostFnSignature, HostRegistry};
+
+        let mut registry = HostRegistry::new();
+        registry.register_with_signature("caps", HostFnSignature::fixed(0), |_args, ctx| {
+            let names = ctx.effective_capabilities().to_names();
+            Ok(Value::List(names.into_iter().map(Value::from).collect()))
+        });
+        let template = PoolConfig::new(1)
+            .with_engine_config(
+                EngineConfig::default()
+                    .with_capabilities(Capabilities::none().with(Capability::Logging)),
+            )
+            .with_registry(Arc::new(registry));
+        let pool = TenantPool::new(template, 2).unwrap();
+        pool.add_tenant(
+            "wide",
+            TenantConfig::new().with_capabilities(Capabilities::all()),
+        )
+        .unwrap();
+        pool.add_tenant(
+            "narrow",
+            TenantConfig::new()
+                .with_capabilities(Capabilities::none())
+                .with_sandbox(SandboxConfig::locked()),
+        )
+        .unwrap();
+
+        let caps = compile_source("caps ()", &CompileOptions::default()).unwrap();
+        // Granting everything cannot widen the pool's capabilities.
+        assert_eq!(
+            pool.execute_bytecode("wide", &caps.bytecode).unwrap(),
+            Value::List(vec![Value::from("logging")])
+        );
+        assert_eq!(
+            pool.execute_bytecode("narrow", &caps.bytecode).unwrap(),
+            Value::List(vec![])
+        );
+
+        let handle = pool.acquire("narrow").unwrap();
+        assert_eq!(handle.tenant(), "narrow");
+        assert_eq!(handle.execute("1 + 2").unwrap(), Value::Int(3));
+        let options = ExecOptions::new().with_sandbox(SandboxConfig::permissive());
+        assert!(matches!(
+            handle.execute_with("1", options),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_engines_shared_between_tenants() {
+        let pool = pool(2);
+        for name in ["a", "b", "c"] {
+            pool.add_tenant(name, TenantConfig::new()).unwrap();
+            assert_eq!(pool.execute(name, "1").unwrap(), Value::Int(1));
+        }
+
+        let _a = pool.acquire("a").unwrap();
+        let _b = pool.acquire("b").unwrap();
+        let start = Instant::now();
+        assert!(matches!(pool.acquire("c"), Err(Error::PoolTimeout)));
+        // Admission is the only wait.
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(pool.pool_stats().total, 2);
+    }
+
+    #[test]
+    fn test_waiter_admitted_on_release() {
+        let template = PoolConfig::new(1).with_acquire_timeout(Duration::from_secs(5));
+        let pool = Arc::new(TenantPool::new(template, 1).unwrap());
+        pool.add_tenant("a", TenantConfig::new()).unwrap();
+
+        let held = pool.acquire("a").unwrap();
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || pool.execute("a", "7"))
+        };
+        while pool.stats("a").unwrap().waiting == 0 {
+            thread::yield_now();
+        }
+        drop(held);
+
+        assert_eq!(waiter.join().unwrap().unwrap(), Value::Int(7));
+        let stats = pool.pool_stats();
+        assert_eq!(stats.releases, 2);
+        assert_eq!(stats.executions, 1);
+    }
+
+    #[test]
+    fn test_tenant_stats() {
+        let pool = pool(2);
+        pool.add_tenant("a", TenantConfig::new()).unwrap();
+        pool.add_tenant("b", TenantConfig::new()).unwrap();
+
+        assert_eq!(pool.execute("a", "1 + 2").unwrap(), Value::Int(3));
+        assert!(pool.execute("a", "1 +").is_err());
+        pool.execute("b", "1").unwrap();
+
+        let a = pool.stats("a").unwrap();
+        assert_eq!(a.executions, 2);
+        assert_eq!(a.execution_time.count(), 2);
+        assert_eq!(a.acquire_wait.count(), 2);
+        assert_eq!(a.error_count(), 1);
+        assert_eq!(a.errors.len(), 1);
+        let b = pool.stats("b").unwrap();
+        assert_eq!((b.executions, b.error_count()), (1, 0));
+        assert_eq!(pool.pool_stats().executions, 3);
+    }
+
+    #[test]
+    fn test_removed_tenant_holds_capacity_until_released() {
+        let pool = pool(2);
+        pool.add_tenant("a", TenantConfig::new()).unwrap();
+        pool.add_tenant("b", TenantConfig::new()).unwrap();
+
+        let a1 = pool.acquire("a").unwrap();
+        let a2 = pool.acquire("a").unwrap();
+        assert!(pool.remove_tenant("a"));
+        // Re-adding the name must not pick up the old tenant's executions.
+        pool.add_tenant("a", TenantConfig::new()).unwrap();
+        assert!(matches!(pool.acquire("b"), Err(Error::PoolTimeout)));
+        assert_eq!(a1.execute("1").unwrap(), Value::Int(1));
+
+        drop(a1);
+        let b = pool.acquire("b").unwrap();
+        assert!(matches!(pool.acquire("a"), Err(Error::PoolTimeout)));
+        drop(a2);
+        let a = pool.acquire("a").unwrap();
+        assert_eq!(pool.stats("a").unwrap().in_use, 1);
+        assert_eq!(pool.stats("a").unwrap().executions, 0);
+        drop((a, b));
+        assert_eq!(pool.stats("b").unwrap().in_use, 0);
+    }
+}
@@ -0,0 +1,537 @@
+//! Recording and replay of host function calls.
+//!
+//! An engine configured with
+//! [`with_host_call_recording`](crate::EngineConfig::with_host_call_recording)
+//! logs every call made through [`HostRegistry::call`](crate::HostRegistry::call)
+//! into a [`Trace`]. [`Engine::replay`](crate::Engine::replay) runs the same
+//! bytecode again, serving the recorded results instead of calling the host,
+//! and reports the first [`Divergence`] from the recording.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::value::Value;
+
+/// How a host function call or execution ended.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum CallOutcome {
+    /// Returned a value.
+    Returned(Value),
+    /// Failed with an error.
+    Failed {
+        /// The error's [`Error::kind`].
+        kind: String,
+        /// The error's own message, without the prefix its kind adds.
+        message: String,
+    },
+}
+
+impl CallOutcome {
+    /// Capture the outcome of a result.
+    pub fn from_result(result: &Result<Value>) -> Self {
+        match result {
+            Ok(value) => Self::Returned(value.clone()),
+            Err(e) => Self::Failed {
+                kind: e.kind().to_string(),
+                message: inner_message(e),
+            },
+        }
+    }
+
+    /// Turn the outcome back into a result.
+    ///
+    /// Failures are rebuilt as the error of the recorded kind. Errors
+    /// carrying structured details (limit violations, timeouts, link and
+    /// conversion failures, version mismatches) cannot be rebuilt and come
+    /// back as [`Error::HostFunction`] with the recorded message.
+    pub fn to_result(&self) -> Result<Value> {
+        match self {
+            Self::Returned(value) => Ok(value.clone()),
+            Self::Failed { kind, message } => Err(rebuild_error(kind, message.clone())),
+        }
+    }
+}
+
+/// The message an error carries, as [`rebuild_error`] expects it back.
+fn inner_message(error: &Error) -> String {
+    match error {
+        Error::Compilation(message)
+        | Error::Runtime(message)
+        | Error::SandboxViolation(message)
+        | Error::EnginePoisoned(message)
+        | Error::InvalidConfig(message)
+        | Error::HostFunction(message)
+        | Error::InvalidBytecode(message)
+        | Error::InvalidBundle(message)
+        | Error::Prelude(message)
+        | Error::ReplayDiverged(message)
+        | Error::Internal(message) => message.clone(),
+        Error::CapabilityDenied { capability } => capability.clone(),
+        Error::PoolExhausted { count } => count.to_string(),
+        Error::Overloaded { capacity } => capacity.to_string(),
+        Error::Io(e) => e.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Rebuild an error of `kind` from its [`inner_message`].
+fn rebuild_error(kind: &str, message: String) -> Error {
+    match kind {
+        "compilation" => Error::Compilation(message),
+        "runtime" => Error::Runtime(message),
+        "capability_denied" => Error::CapabilityDenied {
+            capability: message,
+        },
+        "sandbox_violation" => Error::SandboxViolation(message),
+        "pool_exhausted" => match message.parse() {
+            Ok(count) => Error::PoolExhausted { count },
+            Err(_) => Error::HostFunction(message),
+        },
+        "pool_timeout" => Error::PoolTimeout,
+        "pool_shutdown" => Error::PoolShutdown,
+        "overloaded" => match message.parse() {
+            Ok(capacity) => Error::Overloaded { capacity },
+            Err(_) => Error::HostFunction(message),
+        },
+        "engine_poisoned" => Error::EnginePoisoned(message),
+        "io" => Error::Io(std::io::Error::other(message)),
+        "invalid_config" => Error::InvalidConfig(message),
+        "invalid_bytecode" => Error::InvalidBytecode(message),
+        "invalid_bundle" => Error::InvalidBundle(message),
+        "prelude" => Error::Prelude(message),
+        "replay_diverged" => Error::ReplayDiverged(message),
+        "cancelled" => Error::Cancelled,
+        "internal" => Error::Internal(message),
+        _ => Error::HostFunction(message),
+    }
+}
+
+/// One recorded host function call.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct HostCall {
+    /// Function name, as `name` or `module.name`.
+    pub function: String,
+    /// Arguments passed by the script.
+    pub args: Vec<Value>,
+    /// What the host function returned.
+    pub outcome: CallOutcome,
+    /// Time from the start of the execution to the call.
+    pub offset: Duration,
+    /// Time spent in the host function.
+    pub duration: Duration,
+}
+
+/// Host function calls made by one execution, with its result.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Trace {
+    /// Calls in the order the script made them.
+    pub calls: Vec<HostCall>,
+    /// How the execution ended.
+    pub result: CallOutcome,
+    /// Random seed of the execution, in deterministic mode.
+    pub seed: Option<u64>,
+    /// Time the execution took.
+    pub duration: Duration,
+}
+
+impl Trace {
+    /// Render the trace as a JSON string.
+    #[cfg(feature = "serde-support")]
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Parse a trace rendered by [`to_json_string`](Self::to_json_string).
+    #[cfg(feature = "serde-support")]
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::invalid_config(format!("invalid trace: {}", e)))
+    }
+}
+
+/// The first point where a replay departed from its trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// The script called a different function, or passed different
+    /// arguments, than recorded.
+    Call {
+        /// Index of the call in the trace.
+        index: usize,
+        /// The recorded call.
+        expected: HostCall,
+        /// Function the script called.
+        function: String,
+        /// Arguments the script passed.
+        args: Vec<Value>,
+    },
+    /// The script made more calls than were recorded.
+    ExtraCall {
+        /// Index of the call.
+        index: usize,
+        /// Function the script called.
+        function: String,
+        /// Arguments the script passed.
+        args: Vec<Value>,
+    },
+    /// The script finished without making every recorded call.
+    MissingCalls {
+        /// Index of the first call not made.
+        index: usize,
+        /// Number of recorded calls not made.
+        remaining: usize,
+    },
+    /// The script made the recorded calls but ended differently.
+    Result {
+        /// The recorded outcome.
+        expected: CallOutcome,
+        /// The replayed outcome.
+        actual: CallOutcome,
+    },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Call {
+                index,
+                expected,
+                function,
+                ..
+            } if *function != expected.function => write!(
+                f,
+                "call {} was to {}, recorded {}",
+                index, function, expected.function
+            ),
+            Self::Call {
+                index, function, ..
+            } => write!(
+                f,
+                "call {} to {} passed different arguments",
+                index, function
+            ),
+            Self::ExtraCall {
+                index, function, ..
+            } => write!(f, "call {} to {} was not recorded", index, function),
+            Self::MissingCalls { index, remaining } => write!(
+                f,
+                "script finished before call {} ({} recorded calls not made)",
+                index, remaining
+            ),
+            Self::Result { .. } => write!(f, "script result differs from the recording"),
+        }
+    }
+}
+
+/// Outcome of replaying a trace.
+#[derive(Debug)]
+pub struct ReplayReport {
+    /// Result of the replayed execution.
+    pub result: Result<Value>,
+    /// Recorded calls served to the script.
+    pub replayed: usize,
+    /// First departure from the trace, if any.
+    pub divergence: Option<Divergence>,
+}
+
+impl ReplayReport {
+    /// Check if the replay matched the trace exactly.
+    pub fn is_faithful(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// Recording or replay state of the current execution.
+#[derive(Debug)]
+pub(crate) enum TraceMode {
+    Record {
+        start: Instant,
+        calls: Vec<HostCall>,
+        /// Host calls in progress; only outermost calls are logged.
+        depth: usize,
+    },
+    Replay {
+        trace: Arc<Trace>,
+        next: usize,
+        divergence: Option<Divergence>,
+    },
+}
+
+impl TraceMode {
+    pub(crate) fn record() -> Self {
+        Self::Record {
+            start: Instant::now(),
+            calls: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub(crate) fn replay(trace: Arc<Trace>) -> Self {
+        Self::Replay {
+            trace,
+            next: 0,
+            divergence: None,
+        }
+    }
+
+    /// Start a host call.
+    ///
+    /// When replaying, returns the recorded result to serve instead of
+    /// calling the host, or a [`Error::ReplayDiverged`] error at the first
+    /// divergence. Returns `None` when recording, so the host function runs.
+    pub(crate) fn enter(&mut self, function: &str, args: &[Value]) -> Option<Result<Value>> {
+        let (trace, next, divergence) = match self {
+            Self::Record { depth, .. } => {
+                *depth += 1;
+                return None;
+            }
+            Self::Replay {
+                trace,
+                next,
+                divergence,
+            } => (trace, next, divergence),
+        };
+        if let Some(divergence) = divergence {
+            return Some(Err(Error::ReplayDiverged(divergence.to_string())));
+        }
+
+        let index = *next;
+        let found = match trace.calls.get(index) {
+            Some(call) if call.function == function && call.args == args => {
+                *next += 1;
+                return Some(call.outcome.to_result());
+            }
+            Some(call) => Divergence::Call {
+                index,
+                expected: call.clone(),
+                function: function.to_string(),
+                args: args.to_vec(),
+            },
+            None => Divergence::ExtraCall {
+                index,
+                function: function.to_string(),
+                args: args.to_vec(),
+            },
+        };
+        let error = Error::ReplayDiverged(found.to_string());
+        *divergence = Some(found);
+        Some(Err(error))
+    }
+
+    /// Finish a host call started with [`enter`](Self::enter), logging it
+    /// if it was made by the script rather than by another host function.
+    pub(crate) fn exit(
+        &mut self,
+        function: &str,
+        args: &[Value],
+        result: &Result<Value>,
+        called: Instant,
+    ) {
+        if let Self::Record {
+            start,
+            calls,
+            depth,
+        } = self
+        {
+            *depth = depth.saturating_sub(1);
+            if *depth == 0 {
+                calls.push(HostCall {
+                    function: function.to_string(),
+                    args: args.to_vec(),
+                    outcome: CallOutcome::from_result(result),
+                    offset: called.saturating_duration_since(*start),
+                    duration: called.elapsed(),
+                });
+            }
+        }
+    }
+
+    /// Finish a recording into a trace.
+    pub(crate) fn into_trace(
+        self,
+        result: &Result<Value>,
+        seed: Option<u64>,
+        duration: Duration,
+    ) -> Option<Trace> {
+        match self {
+            Self::Record { calls, .. } => Some(Trace {
+                calls,
+                result: CallOutcome::from_result(result),
+                seed,
+                duration,
+            }),
+            Self::Replay { .. } => None,
+        }
+    }
+
+    /// Finish a replay into a report.
+    pub(crate) fn into_report(self, result: Result<Value>) -> ReplayReport {
+        let Self::Replay {
+            trace,
+            next,
+            divergence,
+        } = self
+        else {
+            return ReplayReport {
+                result,
+                replayed: 0,
+                divergence: None,
+            };
+        };
+
+        let divergence = divergence.or_else(|| {
+            if next < trace.calls.len() {
+                return Some(Divergence::MissingCalls {
+                    index: next,
+                    remaining: trace.calls.len() - next,
+                });
+            }
+            let actual = CallOutcome::from_result(&result);
+            (actual != trace.result).then(|| Divergence::Result {
+                expected: trace.result.clone(),
+                actual,
+            })
+        });
+        ReplayReport {
+            result,
+            replayed: next,
+            divergence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function: &str, arg: i64, result: i64) -> HostCall {
+        HostCall {
+            function: function.to_string(),
+            args: vec![Value::Int(arg)],
+            outcome: CallOutcome::Returned(Value::Int(result)),
+            offset: Duration::ZERO,
+            duration: Duration::from_micros(5),
+        }
+    }
+
+    fn trace() -> Arc<Trace> {
+        Arc::new(Trace {
+            calls: vec![call("read", 1, 10), call("io.write", 2, 20)],
+            result: CallOutcome::Returned(Value::Int(30)),
+            seed: Some(7),
+            duration: Duration::from_millis(1),
+        })
+    }
+
+    #[test]
+    fn test_record_logs_outermost_calls() {
+        let mut mode = TraceMode::record();
+        let called = Instant::now();
+        assert!(mode.enter("outer", &[]).is_none());
+        assert!(mode.enter("inner", &[]).is_none());
+        mode.exit("inner", &[], &Ok(Value::Null), called);
+        mode.exit("outer", &[], &Err(Error::host_function("boom")), called);
+
+        let trace = mode
+            .into_trace(&Ok(Value::Int(1)), None, Duration::ZERO)
+            .unwrap();
+        assert_eq!(trace.calls.len(), 1);
+        assert_eq!(trace.calls[0].function, "outer");
+        assert_eq!(
+            trace.calls[0].outcome,
+            CallOutcome::Failed {
+                kind: "host_function".to_string(),
+                message: "boom".to_string(),
+            }
+        );
+        assert_eq!(
+            trace.calls[0].outcome.to_result().unwrap_err().to_string(),
+            "host function error: boom"
+        );
+    }
+
+    #[test]
+    fn test_failures_rebuild_their_kind() {
+        let errors = [
+            Error::host_function("boom"),
+            Error::runtime("bad"),
+            Error::capability_denied("net"),
+            Error::PoolExhausted { count: 4 },
+            Error::PoolTimeout,
+            Error::Io(std::io::Error::other("disk")),
+            Error::Cancelled,
+        ];
+        for error in errors {
+            let outcome = CallOutcome::from_result(&Err(error));
+            let rebuilt = outcome.to_result().unwrap_err();
+            assert_eq!(CallOutcome::from_result(&Err(rebuilt)), outcome);
+        }
+    }
+
+    #[test]
+    fn test_replay_reports_first_divergence() {
+        let mut mode = TraceMode::replay(trace());
+        assert_eq!(
+            mode.enter("read", &[Value::Int(1)]).unwrap().unwrap(),
+            Value::Int(10)
+        );
+        assert!(matches!(
+            mode.enter("io.write", &[Value::Int(3)]),
+            Some(Err(Error::ReplayDiverged(_)))
+        ));
+        // Later calls keep failing with the first divergence.
+        assert!(matches!(
+            mode.enter("io.write", &[Value::Int(2)]),
+            Some(Err(Error::ReplayDiverged(_)))
+        ));
+
+        let report = mode.into_report(Ok(Value::Int(30)));
+        assert_eq!(report.replayed, 1);
+        let divergence = report.divergence.unwrap();
+        assert!(matches!(divergence, Divergence::Call { index: 1, .. }));
+        assert_eq!(
+            divergence.to_string(),
+            "call 1 to io.write passed different arguments"
+        );
+
+        let mut mode = TraceMode::replay(trace());
+        mode.enter("read", &[Value::Int(1)]);
+        mode.enter("io.write", &[Value::Int(2)]);
+        assert!(mode.enter("read", &[]).is_some());
+        assert!(matches!(
+            mode.into_report(Ok(Value::Int(30))).divergence,
+            Some(Divergence::ExtraCall { index: 2, .. })
+        ));
+
+        let mut mode = TraceMode::replay(trace());
+        mode.enter("read", &[Value::Int(1)]);
+        mode.enter("io.write", &[Value::Int(2)]);
+        let report = mode.into_report(Ok(Value::Int(30)));
+        assert!(report.is_faithful());
+        assert_eq!(report.replayed, 2);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_trace_json_round_trip() {
+        let trace = trace();
+        let json = trace.to_json_string();
+        assert_eq!(Trace::from_json_str(&json).unwrap(), *trace);
+        assert!(matches!(
+            Trace::from_json_str("{"),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
@@ -50,6 +50,10 @@ impl fmt::Display for ValueType {
 /// This is a representation of values that can be passed between
 /// the host and Fusabi scripts.
 #[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub enum Value {
     /// Null/nil value.
     #[default]
@@ -64,8 +68,11 @@ pub enum Value {
     String(String),
     /// Ordered list of values.
     List(Vec<Value>),
-    /// Key-value map (string keys).
-    Map(HashMap<String, Value>),
+    /// Key-value map (string keys). Displays and serializes in key order.
+    Map(
+        #[cfg_attr(feature = "serde-support", serde(serialize_with = "serialize_sorted"))]
+        HashMap<String, Value>,
+    ),
     /// Opaque function reference (not directly usable by host).
     Function(FunctionRef),
     /// Binary data.
@@ -76,6 +83,10 @@ pub enum Value {
 
 /// An opaque reference to a Fusabi function.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub struct FunctionRef {
     /// Internal identifier.
     pub(crate) id: u64,
@@ -166,6 +177,18 @@ impl Value {
         }
     }
 
+    /// Get a map's entries in key order.
+    ///
+    /// `Value::Map` is a `HashMap`, so iterating it directly visits entries in
+    /// an order that differs between runs. Use this where the order matters,
+    /// such as comparing the results of deterministic executions.
+    pub fn sorted_entries(&self) -> Option<Vec<(&str, &Value)>> {
+        let map = self.as_map()?;
+        let mut entries: Vec<_> = map.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        Some(entries)
+    }
+
     /// Try to get as bytes.
     pub fn as_bytes(&self) -> Option<&[u8]> {
         match self {
@@ -193,6 +216,17 @@ impl Value {
     }
 }
 
+/// Serialize a map's entries in key order.
+#[cfg(feature = "serde-support")]
+fn serialize_sorted<S: serde::Serializer>(
+    map: &HashMap<String, Value>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    serializer.collect_map(entries)
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -211,9 +245,15 @@ impl fmt::Display for Value {
                 }
                 write!(f, "]")
             }
-            Value::Map(m) => {
+            Value::Map(_) => {
+                // Keys in order, so equal maps always render alike.
                 write!(f, "{{")?;
-                for (i, (k, v)) in m.iter().enumerate() {
+                for (i, (k, v)) in self
+                    .sorted_entries()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
@@ -359,5 +399,45 @@ mod tests {
         assert_eq!(format!("{}", Value::Bool(true)), "true");
         assert_eq!(format!("{}", Value::Int(42)), "42");
         assert_eq!(format!("{}", Value::String("test".into())), "\"test\"");
+
+        let map: HashMap<String, Value> = ["b", "c", "a"]
+            .iter()
+            .map(|k| (k.to_string(), Value::Null))
+            .collect();
+        assert_eq!(
+            format!("{}", Value::from(map)),
+            "{\"a\": null, \"b\": null, \"c\": null}"
+        );
+    }
+
+    #[test]
+    fn test_sorted_entries_are_stable() {
+        // Each map gets its own hasher seed, so raw iteration order varies.
+        let build = || {
+            let map: HashMap<String, Value> = (0..32)
+                .map(|i| (format!("key{}", i), Value::Int(i)))
+                .collect();
+            Value::from(map)
+        };
+        let first = build();
+        let expected: Vec<(String, Value)> = first
+            .sorted_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        assert!(expected.windows(2).all(|w| w[0].0 < w[1].0));
+
+        for _ in 0..8 {
+            let again = build();
+            let entries: Vec<(String, Value)> = again
+                .sorted_entries()
+                .unwrap()
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect();
+            assert_eq!(entries, expected);
+        }
+        assert!(Value::Int(1).sorted_entries().is_none());
     }
 }
@@ -0,0 +1,524 @@
+//! Structural verification of bytecode from untrusted sources.
+//!
+//! [`validate_bytecode`](crate::validate_bytecode) only checks that a chunk
+//! deserializes. The verifier goes further and walks every instruction of the
+//! top-level chunk and of every function prototype, rejecting chunks whose
+//! opcodes are unknown or whose jumps, constant, local and upvalue references,
+//! stack usage, or call arities could drive the VM out of bounds.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::disasm::{disassemble, Disassembly, FunctionListing, InstructionListing};
+use crate::error::{Error, Result};
+
+/// Largest argument count a call or function declaration may use.
+pub const MAX_ARITY: usize = 255;
+
+/// Verify FZB bytecode before execution.
+///
+/// Checks every function in the chunk: each opcode is known, jump targets
+/// land on an instruction, constant, local and upvalue indices are in range,
+/// the operand stack never underflows and agrees at every basic-block
+/// boundary, and call and function arities are sane.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidBytecode`] naming the function and instruction
+/// offset of the first failure.
+pub fn verify_bytecode(bytecode: &[u8]) -> Result<()> {
+    let disassembly = disassemble(bytecode)?;
+    verify_disassembly(&disassembly)
+}
+
+/// Verify an already disassembled chunk.
+pub fn verify_disassembly(disassembly: &Disassembly) -> Result<()> {
+    let upvalues = upvalue_counts(disassembly);
+    for function in &disassembly.functions {
+        if let Some(arity) = function.arity {
+            if arity > MAX_ARITY {
+                return Err(Error::invalid_bytecode(format!(
+                    "fn #{}: arity {} exceeds maximum of {}",
+                    function.index, arity, MAX_ARITY
+                )));
+            }
+        }
+        let upvalues = upvalues.get(&function.index).copied().unwrap_or(0);
+        verify_function(function, upvalues)?;
+    }
+    Ok(())
+}
+
+/// Number of upvalues each function is closed over with.
+///
+/// A prototype instantiated by several `MakeClosure` sites gets the smallest
+/// count, so every instantiation can satisfy its upvalue reads.
+fn upvalue_counts(disassembly: &Disassembly) -> HashMap<usize, usize> {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for function in &disassembly.functions {
+        for instr in &function.instructions {
+            if instr.opcode != "MakeClosure" {
+                continue;
+            }
+            let prototype = instr
+                .int_operand(0)
+                .and_then(|k| usize::try_from(k).ok())
+                .and_then(|k| function.constants.get(k))
+                .and_then(|c| c.function);
+            let count = instr
+                .int_operand(1)
+                .and_then(|n| usize::try_from(n).ok())
+                .unwrap_or(0);
+            if let Some(prototype) = prototype {
+                counts
+                    .entry(prototype)
+                    .and_modify(|c| *c = (*c).min(count))
+                    .or_insert(count);
+            }
+        }
+    }
+    counts
+}
+
+fn fail(function: &FunctionListing, offset: usize, msg: impl std::fmt::Display) -> Error {
+    Error::invalid_bytecode(format!(
+        "fn #{} at offset {:04}: {}",
+        function.index, offset, msg
+    ))
+}
+
+fn verify_function(function: &FunctionListing, upvalues: usize) -> Result<()> {
+    let len = function.instructions.len();
+    let params = function.arity.unwrap_or(0);
+    let stored: HashSet<i64> = function
+        .instructions
+        .iter()
+        .filter(|i| i.opcode == "StoreLocal")
+        .filter_map(|i| i.int_operand(0))
+        .filter(|i| *i >= params as i64)
+        .collect();
+    // Locals are allocated in order after the parameters, so the frame holds
+    // the parameters plus one slot per distinct local the function assigns.
+    let frame_size = params + stored.len();
+
+    for instr in &function.instructions {
+        let offset = instr.offset;
+
+        if stack_effect(instr).is_none() {
+            return Err(fail(
+                function,
+                offset,
+                format!("unknown opcode {}", instr.opcode),
+            ));
+        }
+
+        if instr.is_jump() {
+            match instr.jump_target {
+                Some(target) if target < len => {}
+                Some(target) => {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!("jump target {} out of range", target),
+                    ))
+                }
+                None => {
+                    return Err(fail(
+                        function,
+                        offset,
+                        "jump target before start of function",
+                    ))
+                }
+            }
+        }
+
+        match instr.opcode.as_str() {
+            "LoadConst" | "MakeClosure" => {
+                let index = constant_index(function, instr)?;
+                if instr.opcode == "MakeClosure" && function.constants[index].function.is_none() {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!("constant {} is not a function", index),
+                    ));
+                }
+            }
+            "LoadLocal" | "StoreLocal" => {
+                let Some(index) = instr.int_operand(0).filter(|i| *i >= 0) else {
+                    return Err(fail(function, offset, "invalid local index"));
+                };
+                if index as usize >= frame_size {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!(
+                            "local {} out of range (frame has {} slots)",
+                            index, frame_size
+                        ),
+                    ));
+                }
+                if instr.opcode == "LoadLocal"
+                    && index as usize >= params
+                    && !stored.contains(&index)
+                {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!("local {} is read but never assigned", index),
+                    ));
+                }
+            }
+            "LoadUpvalue" | "StoreUpvalue" => match instr.int_operand(0) {
+                Some(index) if index >= 0 && (index as usize) < upvalues => {}
+                index => {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!(
+                            "upvalue {} out of range (closure has {})",
+                            index.unwrap_or(-1),
+                            upvalues
+                        ),
+                    ))
+                }
+            },
+            "Call" | "TailCall" => {
+                let argc = instr.int_operand(0).unwrap_or(0);
+                if argc < 0 || argc as usize > MAX_ARITY {
+                    return Err(fail(
+                        function,
+                        offset,
+                        format!("invalid call arity {}", argc),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    check_stack(function)
+}
+
+fn constant_index(function: &FunctionListing, instr: &InstructionListing) -> Result<usize> {
+    match instr.int_operand(0) {
+        Some(index) if index >= 0 && (index as usize) < function.constants.len() => {
+            Ok(index as usize)
+        }
+        Some(index) => Err(fail(
+            function,
+            instr.offset,
+            format!(
+                "constant index {} out of range (pool has {})",
+                index,
+                function.constants.len()
+            ),
+        )),
+        None => Err(fail(function, instr.offset, "missing constant index")),
+    }
+}
+
+/// Stack effect of an instruction as `(pops, pushes)`.
+///
+/// Returns `None` for opcodes whose effect is not known here, which the
+/// verifier rejects.
+pub(crate) fn stack_effect(instr: &InstructionListing) -> Option<(usize, usize)> {
+    let n = |i| instr.int_operand(i).map(|n| n.max(0) as usize);
+    let effect = match instr.opcode.as_str() {
+        "LoadConst" | "LoadLocal" | "LoadUpvalue" | "LoadGlobal" => (0, 1),
+        "Pop" | "StoreLocal" | "StoreUpvalue" | "StoreGlobal" | "JumpIfFalse" => (1, 0),
+        "Dup" => (1, 2),
+        "Add" | "Sub" | "Mul" | "Div" | "Mod" | "Eq" | "Neq" | "Lt" | "Lte" | "Gt" | "Gte"
+        | "And" | "Or" | "Cons" => (2, 1),
+        "Not" | "Neg" | "ListHead" | "ListTail" | "IsNil" | "GetTupleField" => (1, 1),
+        "Jump" => (0, 0),
+        "Return" => (1, 0),
+        "Call" | "TailCall" => (n(0)? + 1, 1),
+        "MakeTuple" | "MakeList" | "MakeArray" => (n(0)?, 1),
+        "MakeClosure" => (n(1)?, 1),
+        _ => return None,
+    };
+    Some(effect)
+}
+
+fn is_terminator(instr: &InstructionListing) -> bool {
+    matches!(instr.opcode.as_str(), "Return" | "TailCall" | "Jump")
+}
+
+/// Check stack balance by propagating the stack height across basic blocks.
+fn check_stack(function: &FunctionListing) -> Result<()> {
+    let instrs = &function.instructions;
+    if instrs.is_empty() {
+        return Ok(());
+    }
+
+    let mut leaders: HashSet<usize> = HashSet::from([0]);
+    for instr in instrs {
+        if let Some(target) = instr.jump_target {
+            leaders.insert(target);
+        }
+        if instr.is_jump() || is_terminator(instr) {
+            leaders.insert(instr.offset + 1);
+        }
+    }
+
+    let mut entry_heights: BTreeMap<usize, usize> = BTreeMap::from([(0, 0)]);
+    let mut queue = VecDeque::from([0usize]);
+
+    while let Some(start) = queue.pop_front() {
+        let mut height = entry_heights[&start];
+        let mut offset = start;
+        let mut successors = Vec::new();
+
+        while let Some(instr) = instrs.get(offset) {
+            let Some((pops, pushes)) = stack_effect(instr) else {
+                return Err(fail(
+                    function,
+                    offset,
+                    format!("unknown opcode {}", instr.opcode),
+                ));
+            };
+            if height < pops {
+                return Err(fail(
+                    function,
+                    offset,
+                    format!(
+                        "stack underflow: {} needs {} operands, {} available",
+                        instr.opcode, pops, height
+                    ),
+                ));
+            }
+            height = height - pops + pushes;
+
+            if let Some(target) = instr.jump_target {
+                successors.push(target);
+            }
+            if is_terminator(instr) {
+                break;
+            }
+            offset += 1;
+            if leaders.contains(&offset) {
+                successors.push(offset);
+                break;
+            }
+        }
+
+        for succ in successors {
+            if succ >= instrs.len() {
+                continue;
+            }
+            match entry_heights.get(&succ) {
+                Some(&expected) if expected != height => {
+                    return Err(fail(
+                        function,
+                        succ,
+                        format!(
+                            "stack height mismatch at block entry: {} vs {}",
+                            expected, height
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    entry_heights.insert(succ, height);
+                    queue.push_back(succ);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_vm::{Chunk, Instruction};
+
+    fn chunk(source: &str) -> Chunk {
+        fusabi_frontend::compile_program_from_source(source).unwrap()
+    }
+
+    fn verify_chunk(chunk: &Chunk) -> Result<()> {
+        verify_bytecode(&fusabi_vm::serialize_chunk(chunk).unwrap())
+    }
+
+    fn position(chunk: &Chunk, pred: impl Fn(&Instruction) -> bool) -> usize {
+        chunk.instructions.iter().position(pred).unwrap()
+    }
+
+    fn message(err: Error) -> String {
+        match err {
+            Error::InvalidBytecode(msg) => msg,
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_valid_programs() {
+        for source in [
+            "1 + 2",
+            "let x = 1 + 2 in x * x",
+            "if 1 < 2 then 10 else 20",
+            "let add a b = a + b\nadd 1 2",
+        ] {
+            assert!(verify_chunk(&chunk(source)).is_ok(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_jump_out_of_range() {
+        let mut chunk = chunk("if true then 1 else 2");
+        let at = position(&chunk, |i| matches!(i, Instruction::Jump(_)));
+        chunk.instructions[at] = Instruction::Jump(100);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains(&format!("offset {:04}", at)));
+        assert!(msg.contains("jump target"));
+    }
+
+    #[test]
+    fn test_constant_out_of_range() {
+        let mut chunk = chunk("1 + 2");
+        chunk.instructions[1] = Instruction::LoadConst(9);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("offset 0001"));
+        assert!(msg.contains("constant index 9"));
+    }
+
+    #[test]
+    fn test_closure_requires_function_constant() {
+        let mut chunk = chunk("let f x = x\nf 1");
+        let name = chunk
+            .constants
+            .iter()
+            .position(|c| matches!(c, fusabi_vm::Value::Str(_)))
+            .unwrap();
+        let at = position(&chunk, |i| matches!(i, Instruction::MakeClosure(..)));
+        chunk.instructions[at] = Instruction::MakeClosure(name as u16, 0);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("is not a function"));
+    }
+
+    #[test]
+    fn test_unassigned_local() {
+        let mut chunk = chunk("1");
+        chunk.instructions.insert(0, Instruction::LoadLocal(2));
+        chunk.instructions.insert(1, Instruction::Pop);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("local 2"));
+
+        // Parameters are assigned by the caller.
+        assert!(verify_chunk(&self::chunk("let second a b = b\nsecond 1 2")).is_ok());
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut chunk = chunk("1");
+        chunk.instructions.insert(1, Instruction::Add);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("offset 0001"));
+        assert!(msg.contains("underflow"));
+    }
+
+    #[test]
+    fn test_stack_mismatch_at_merge() {
+        // Make the `then` branch skip its push, so the join point sees two
+        // heights.
+        let mut chunk = chunk("if true then 1 else 2");
+        let at = position(&chunk, |i| matches!(i, Instruction::JumpIfFalse(_)));
+        chunk.instructions[at + 1] = Instruction::Jump(0);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("mismatch"));
+    }
+
+    #[test]
+    fn test_local_outside_frame() {
+        // A store may only extend the frame by one slot.
+        let mut chunk = chunk("let x = 1 in x");
+        let at = position(&chunk, |i| matches!(i, Instruction::StoreLocal(_)));
+        chunk.instructions[at] = Instruction::StoreLocal(7);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("local 7 out of range (frame has 1 slots)"));
+
+        let mut chunk = self::chunk("let x = 1 in x");
+        let at = position(&chunk, |i| matches!(i, Instruction::LoadLocal(_)));
+        chunk.instructions[at] = Instruction::LoadLocal(1);
+        assert!(message(verify_chunk(&chunk).unwrap_err()).contains("local 1 out of range"));
+    }
+
+    #[test]
+    fn test_upvalue_out_of_range() {
+        let mut chunk = chunk("let f x = x\nf 1");
+        let fusabi_vm::Value::Closure(proto) = chunk
+            .constants
+            .iter_mut()
+            .find(|c| matches!(c, fusabi_vm::Value::Closure(_)))
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        let proto = std::sync::Arc::make_mut(proto);
+        proto
+            .chunk
+            .instructions
+            .insert(0, Instruction::LoadUpvalue(0));
+        proto.chunk.instructions.insert(1, Instruction::Pop);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("fn #1"));
+        assert!(msg.contains("upvalue 0 out of range"));
+    }
+
+    #[test]
+    fn test_function_prototypes_are_verified() {
+        let mut chunk = chunk("let add a b = a + b\nadd 1 2");
+        let fusabi_vm::Value::Closure(proto) = chunk
+            .constants
+            .iter_mut()
+            .find(|c| matches!(c, fusabi_vm::Value::Closure(_)))
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        // Read a local beyond the two parameters.
+        let proto = std::sync::Arc::make_mut(proto);
+        proto.chunk.instructions[1] = Instruction::LoadLocal(2);
+
+        let msg = message(verify_chunk(&chunk).unwrap_err());
+        assert!(msg.contains("fn #1 at offset 0001"));
+        assert!(msg.contains("local 2 out of range"));
+    }
+
+    #[test]
+    fn test_unknown_opcode_is_rejected() {
+        let mut disassembly =
+            disassemble(&fusabi_vm::serialize_chunk(&chunk("1")).unwrap()).unwrap();
+        disassembly.functions[0].instructions[0].opcode = "Frobnicate".into();
+
+        let msg = message(verify_disassembly(&disassembly).unwrap_err());
+        assert!(msg.contains("offset 0000"));
+        assert!(msg.contains("unknown opcode Frobnicate"));
+    }
+
+    #[test]
+    fn test_arity_limits() {
+        let mut disassembly =
+            disassemble(&fusabi_vm::serialize_chunk(&chunk("1")).unwrap()).unwrap();
+        disassembly.functions[0].arity = Some(MAX_ARITY + 1);
+        assert!(verify_disassembly(&disassembly).is_err());
+    }
+
+    #[test]
+    fn test_verify_compiled_bytecode() {
+        use crate::compile::{compile_source, CompileOptions};
+
+        let compiled = compile_source("1 + 2", &CompileOptions::default()).unwrap();
+        assert!(verify_bytecode(&compiled.bytecode).is_ok());
+        assert!(verify_bytecode(b"garbage").is_err());
+    }
+}
@@ -7,8 +7,8 @@ use std::thread;
 use std::time::Duration;
 
 use fusabi_host::{
-    compile_source, Capabilities, Capability, CompileOptions, Engine, EngineConfig, EnginePool,
-    Error, Limits, PoolConfig, SandboxConfig, Value,
+    compile_source, BundleBuilder, Capabilities, Capability, CompileOptions, Engine, EngineConfig,
+    EnginePool, Error, ExecOptions, Limits, PoolConfig, SandboxConfig, Value,
 };
 
 #[test]
@@ -233,6 +233,26 @@ fn test_host_registry() {
     assert!(registry.get("nonexistent").is_none());
 }
 
+#[test]
+fn test_pooled_scripts_call_shared_registry() {
+    use fusabi_host::HostRegistry;
+
+    let mut registry = HostRegistry::new();
+    registry.register("greet", |args, _ctx| {
+        Ok(Value::String(format!(
+            "hello {}",
+            args[0].as_str().unwrap()
+        )))
+    });
+    let pool = EnginePool::new(PoolConfig::new(2).with_registry(Arc::new(registry))).unwrap();
+
+    let compiled = compile_source("greet \"pool\"", &CompileOptions::default()).unwrap();
+    for _ in 0..3 {
+        let result = pool.execute_bytecode(&compiled.bytecode).unwrap();
+        assert_eq!(result, Value::String("hello pool".to_string()));
+    }
+}
+
 #[test]
 fn test_typed_host_functions() {
     use fusabi_host::typed_host_fn_2;
@@ -248,6 +268,45 @@ fn test_typed_host_functions() {
     assert_eq!(result, Value::Int(7));
 }
 
+#[test]
+fn test_bundle_execution() {
+    use fusabi_host::Bundle;
+
+    let bundle = BundleBuilder::new("plugin", "1.0.0")
+        .with_module("main", "// import util\n// @require time:read\n1 + 2")
+        .with_module("util", "40")
+        .build()
+        .unwrap();
+
+    // Bundles survive a round-trip through their single-file encoding.
+    let bundle = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+
+    let engine = Engine::new(EngineConfig::default()).unwrap();
+    assert_eq!(engine.execute_bundle(&bundle).unwrap(), Value::Int(3));
+
+    let pool = EnginePool::new(PoolConfig::new(1)).unwrap();
+    assert_eq!(pool.execute_bundle(&bundle).unwrap(), Value::Int(3));
+
+    // Missing required capability is rejected before execution.
+    let strict = Engine::new(EngineConfig::strict()).unwrap();
+    assert!(matches!(
+        strict.execute_bundle(&bundle),
+        Err(Error::CapabilityDenied { .. })
+    ));
+}
+
+#[test]
+fn test_bundle_entrypoint_calls_imported_function() {
+    let bundle = BundleBuilder::new("plugin", "1.0.0")
+        .with_module("main", "// import util\ndouble 21")
+        .with_module("util", "let double x = x + x")
+        .build()
+        .unwrap();
+
+    let engine = Engine::new(EngineConfig::default()).unwrap();
+    assert_eq!(engine.execute_bundle(&bundle).unwrap(), Value::Int(42));
+}
+
 #[test]
 fn test_lazy_pool_init() {
     let config = PoolConfig::new(4).with_lazy_init(true);
@@ -305,3 +364,33 @@ mod serde_tests {
         assert_eq!(map.get("key"), Some(&Value::String("value".into())));
     }
 }
+
+#[test]
+fn test_shared_pool_per_execution_options() {
+    let config = PoolConfig::new(2).with_capabilities(Capabilities::all());
+    let pool = EnginePool::new(config).unwrap();
+
+    // Trusted scripts use the pool's configuration.
+    assert_eq!(pool.execute("1 + 1").unwrap(), Value::Int(2));
+
+    // Untrusted scripts run narrowed on the same pool.
+    let untrusted = ExecOptions::new()
+        .with_limits(Limits::strict().with_max_instructions(1))
+        .with_capabilities(Capabilities::none())
+        .with_sandbox(SandboxConfig::locked());
+    assert!(matches!(
+        pool.execute_with("1 + 1", untrusted),
+        Err(Error::LimitViolation(_))
+    ));
+
+    let handle = pool.acquire().unwrap();
+    let options = ExecOptions::new().with_global("base", 10i64);
+    assert_eq!(
+        handle.execute_with("base + 5", options).unwrap(),
+        Value::Int(15)
+    );
+    assert!(handle
+        .engine()
+        .context()
+        .has_capability(Capability::FsWrite));
+}